@@ -0,0 +1,39 @@
+/*
+ * The blocks runtime.  Blocks are objects of the classes stored at the _NSConcrete symbols, which
+ * respond to -copy, -retain and -release.
+ *
+ * The declarations are checked against the exports of the runtime by tests/headers.rs.
+ */
+
+#ifndef __objc_blocks_runtime_INCLUDE_GNU
+#define __objc_blocks_runtime_INCLUDE_GNU
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The storage of the classes of blocks on the stack, of blocks without captures, and of copies of
+   blocks made on the heap.  */
+
+extern void *_NSConcreteStackBlock[32];
+extern void *_NSConcreteGlobalBlock[32];
+extern void *_NSConcreteMallocBlock[32];
+
+/* Copies a block on the stack to the heap, or retains a block already there.  */
+
+void *_Block_copy(const void *block);
+void _Block_release(const void *block);
+
+/* Called by the copy and dispose helpers the compiler emits.  */
+
+void _Block_object_assign(void *destination, const void *object, const int flags);
+void _Block_object_dispose(const void *object, const int flags);
+
+#define Block_copy(block) ((__typeof(block))_Block_copy((const void *)(block)))
+#define Block_release(block) _Block_release((const void *)(block))
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,64 @@
+/*
+ * Entry points called by code compiled with automatic reference counting.  The messages are
+ * sent to objects whose classes implement retain, release and autorelease, and the references
+ * of other objects are counted by the runtime.
+ *
+ * The declarations are checked against the exports of the runtime by tests/headers.rs.
+ */
+
+#ifndef __objc_arc_INCLUDE_GNU
+#define __objc_arc_INCLUDE_GNU
+
+#include "objc.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+id objc_retain(id object);
+void objc_release(id object);
+id objc_autorelease(id object);
+id objc_retainAutorelease(id object);
+
+/* Return values are autoreleased by the callees and retained by the callers.  */
+
+id objc_autoreleaseReturnValue(id object);
+id objc_retainAutoreleaseReturnValue(id object);
+id objc_retainAutoreleasedReturnValue(id object);
+
+/* Copies a block to the heap, for blocks stored to strong variables.  */
+
+id objc_retainBlock(id block);
+
+/* Autorelease pools, which @autoreleasepool pushes and pops.  Popping a pool pops those pushed
+   after it as well.  Objects autoreleased outside pools are released when the thread exits.  */
+
+void *objc_autoreleasePoolPush(void);
+void objc_autoreleasePoolPop(void *pool);
+
+/* Retains value, stores it to location and releases the object stored before.  */
+
+void objc_storeStrong(id *location, id value);
+
+/* Weak references, which are set to nil when the objects are deallocated.  Locations are
+   initialized with objc_initWeak and destroyed with objc_destroyWeak.  Foundations whose dealloc
+   does not call object_dispose call objc_delete_weak_refs instead.  */
+
+id objc_initWeak(id *location, id object);
+id objc_storeWeak(id *location, id object);
+id objc_loadWeak(id *location);
+id objc_loadWeakRetained(id *location);
+void objc_destroyWeak(id *location);
+void objc_copyWeak(id *destination, id *source);
+void objc_moveWeak(id *destination, id *source);
+BOOL objc_delete_weak_refs(id object);
+
+/* The number of references counted by the runtime, which is 1 for objects never retained.  */
+
+size_t object_getRetainCount_np(id object);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
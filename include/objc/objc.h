@@ -0,0 +1,56 @@
+/*
+ * Basic types of the Objective-C language, as provided by robjc.
+ *
+ * The declarations are checked against the exports of the runtime by tests/headers.rs.
+ */
+
+#ifndef __objc_INCLUDE_GNU
+#define __objc_INCLUDE_GNU
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef unsigned char BOOL;
+#define YES ((BOOL)1)
+#define NO ((BOOL)0)
+
+typedef const struct objc_selector *SEL;
+
+typedef struct objc_class *Class;
+
+typedef struct objc_object {
+  Class class_pointer;
+} *id;
+
+typedef id (*IMP)(id, SEL, ...);
+
+#define nil ((id)0)
+#define Nil ((Class)0)
+
+#ifdef __OBJC__
+@class Protocol;
+#else
+typedef struct objc_object Protocol;
+#endif
+
+const char *sel_getName(SEL selector);
+SEL sel_registerName(const char *name);
+
+Class objc_getClass(const char *name);
+
+struct objc_super {
+  id self;
+  Class super_class;
+};
+
+IMP objc_msg_lookup(id receiver, SEL op);
+IMP objc_msg_lookup_super(struct objc_super *super, SEL op);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,258 @@
+/*
+ * Runtime API of robjc.  Names and signatures follow the GNU Objective-C runtime, so that code
+ * written against GCC's libobjc compiles unchanged.
+ *
+ * The declarations are checked against the exports of the runtime by tests/headers.rs.
+ */
+
+#ifndef __objc_runtime_INCLUDE_GNU
+#define __objc_runtime_INCLUDE_GNU
+
+#include "objc.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct objc_ivar *Ivar;
+typedef struct objc_method *Method;
+typedef struct objc_property *objc_property_t;
+
+typedef struct {
+  const char *name;
+  const char *value;
+} objc_property_attribute_t;
+
+/* Type encodings. */
+
+#define _C_ID       '@'
+#define _C_CLASS    '#'
+#define _C_SEL      ':'
+#define _C_CHR      'c'
+#define _C_UCHR     'C'
+#define _C_SHT      's'
+#define _C_USHT     'S'
+#define _C_INT      'i'
+#define _C_UINT     'I'
+#define _C_LNG      'l'
+#define _C_ULNG     'L'
+#define _C_LNG_LNG  'q'
+#define _C_ULNG_LNG 'Q'
+#define _C_FLT      'f'
+#define _C_DBL      'd'
+#define _C_LNG_DBL  'D'
+#define _C_BFLD     'b'
+#define _C_BOOL     'B'
+#define _C_VOID     'v'
+#define _C_UNDEF    '?'
+#define _C_PTR      '^'
+#define _C_CHARPTR  '*'
+#define _C_ATOM     '%'
+#define _C_ARY_B    '['
+#define _C_ARY_E    ']'
+#define _C_UNION_B  '('
+#define _C_UNION_E  ')'
+#define _C_STRUCT_B '{'
+#define _C_STRUCT_E '}'
+#define _C_VECTOR   '!'
+#define _C_COMPLEX  'j'
+
+#define _C_CONST       'r'
+#define _C_IN          'n'
+#define _C_INOUT       'N'
+#define _C_OUT         'o'
+#define _C_BYCOPY      'O'
+#define _C_BYREF       'R'
+#define _C_ONEWAY      'V'
+#define _C_GCINVISIBLE '|'
+
+#define _F_CONST       0x01
+#define _F_IN          0x01
+#define _F_OUT         0x02
+#define _F_INOUT       0x03
+#define _F_BYCOPY      0x04
+#define _F_BYREF       0x08
+#define _F_ONEWAY      0x10
+#define _F_GCINVISIBLE 0x20
+
+/* Null or invalid types give sizes of 0, and NULL from the skip functions.  The layout of a
+   type which is not a structure or a union has no members.  */
+
+int objc_sizeof_type(const char *type);
+int objc_alignof_type(const char *type);
+int objc_aligned_size(const char *type);
+int objc_promoted_size(const char *type);
+const char *objc_skip_type_qualifiers(const char *type);
+const char *objc_skip_typespec(const char *type);
+const char *objc_skip_offset(const char *type);
+const char *objc_skip_argspec(const char *type);
+unsigned objc_get_type_qualifiers(const char *type);
+
+struct objc_struct_layout {
+  const char *original_type;
+  const char *type;
+  const char *prev_type;
+  unsigned int record_size;
+  unsigned int record_align;
+};
+
+void objc_layout_structure(const char *type, struct objc_struct_layout *layout);
+BOOL objc_layout_structure_next_member(struct objc_struct_layout *layout);
+void objc_layout_finish_structure(struct objc_struct_layout *layout, unsigned int *size,
+                                  unsigned int *align);
+void objc_layout_structure_get_info(struct objc_struct_layout *layout, unsigned int *offset,
+                                    unsigned int *align, const char **type);
+
+/* Selectors. */
+
+const char *sel_getTypeEncoding(SEL selector);
+SEL sel_getUid(const char *name);
+
+/* Objects. */
+
+id class_createInstance(Class class_, size_t extraBytes);
+id object_copy(id object, size_t extraBytes);
+id object_dispose(id object);
+Class object_getClass(id object);
+Class object_setClass(id object, Class class_);
+const char *object_getClassName(id object);
+Class object_getSuperclass(id object);
+BOOL object_isClass(id object);
+BOOL object_isMetaClass(id object);
+BOOL object_isKindOfClass(id object, Class class_);
+BOOL object_isMemberOfClass(id object, Class class_);
+
+/* Classes. */
+
+Class objc_get_class(const char *name);
+const char *class_getName(Class class_);
+BOOL class_isMetaClass(Class class_);
+Class class_getSuperclass(Class class_);
+BOOL class_isSubclassOfClass(Class class_, Class superclass);
+Method class_getInstanceMethod(Class class_, SEL selector);
+Method class_getClassMethod(Class class_, SEL selector);
+BOOL class_respondsToSelector(Class class_, SEL selector);
+size_t class_getInstanceSize(Class class_);
+int class_getVersion(Class class_);
+void class_setVersion(Class class_, int version);
+
+/* Returns a nil-terminated array of the subclasses, to be released with free, or NULL if there
+   are none.  Indirect subclasses are included if includeIndirect is YES.  */
+Class *class_copySubclassList(Class class_, BOOL includeIndirect,
+                              unsigned int *numberOfReturnedClasses);
+
+/* Methods. */
+
+IMP method_getImplementation(Method method);
+
+typedef union arglist {
+  char *arg_ptr;
+  char arg_regs[sizeof(char *)];
+} *arglist_t;
+
+int method_get_number_of_arguments(Method method);
+int method_get_sizeof_arguments(Method method);
+char *method_get_first_argument(Method method, arglist_t argframe, const char **type);
+char *method_get_next_argument(arglist_t argframe, const char **type);
+char *method_get_nth_argument(Method method, arglist_t argframe, int arg, const char **type);
+
+/* Tracing.
+
+   Message lookups are logged when the environment variable ROBJC_TRACE_MESSAGES is set to 1,
+   or after objc_setMessageTracing is called with YES.  Lookups are selected by the prefixes of
+   the names of the receiver classes and of the selectors, ROBJC_TRACE_CLASSES and
+   ROBJC_TRACE_SELECTORS, and logged to the file descriptor ROBJC_TRACE_FD, stderr by default.
+   NULL prefixes select everything and a negative descriptor stands for stderr.  */
+
+void objc_setMessageTracing(BOOL enabled, const char *classPrefix, const char *selectorPrefix,
+                            int fd);
+
+/* Dumping.
+
+   robjc_dump writes the loaded modules, the class tree, the methods of each class with the
+   categories adding them, and the classes, methods and categories not yet resolved, as text or
+   as JSON.  A negative descriptor stands for stderr.  Setting the environment variable
+   ROBJC_DUMP to 1 or json writes a dump to stderr after each module is loaded.  */
+
+void robjc_dump(int fd, BOOL json);
+
+/* Loading.
+
+   _objc_load_callback is called for each class loaded with a nil category, and for each
+   category with the class it extends.  Observers added with objc_addLoadObserver get the
+   classes, categories and selectors added by each load together.  A load is a module, or a class
+   registered at run time; selectors first registered by sel_registerName between loads, and the
+   classes the runtime registers itself, are not reported.  Classes are reported once their
+   superclasses are loaded, which may be with a later module.  Both are called after the runtime
+   is unlocked, so they can use the runtime.  */
+
+struct objc_category;
+
+extern void (*_objc_load_callback)(Class class_, struct objc_category *category);
+
+struct objc_loaded_category {
+  Class class_;
+  const char *category_name;
+};
+
+struct objc_load_info {
+  const char *module_name; /* NULL for classes registered at run time.  */
+  unsigned int class_count;
+  const Class *classes;
+  unsigned int category_count;
+  const struct objc_loaded_category *categories;
+  unsigned int selector_count;
+  const SEL *selectors;
+};
+
+void objc_addLoadObserver(void (*observer)(const struct objc_load_info *info, void *context),
+                          void *context);
+
+/* Images.
+
+   A bundle loaded with dlopen can be unloaded by closing it with objc_dlclose, which removes
+   its classes, categories and selectors and restores the methods its categories replaced.  It
+   fails with -1 without closing the bundle if known instances of its classes are alive or
+   classes of other images subclass them.  Known instances are those created with
+   class_createInstance and not yet disposed.  Alternatively, a bundle can include
+   OBJC_UNLOAD_ON_DLCLOSE in one of its sources, so that its destructor calls objc_unloadImage
+   when it is closed with dlclose, which can no longer fail and only reports known live
+   instances.  The runtime cannot install such a destructor itself, since dlclose only runs the
+   handlers registered for the hidden __dso_handle of the bundle.  Images are identified by the
+   link maps of glibc; elsewhere objc_dlclose fails.  */
+
+int objc_unloadImage(const void *address);
+int objc_dlclose(void *handle);
+
+#define OBJC_UNLOAD_ON_DLCLOSE                                                                  \
+  static void __attribute__((destructor)) __objc_unload_on_dlclose(void)                        \
+  {                                                                                             \
+    objc_unloadImage((const void *)__objc_unload_on_dlclose);                                   \
+  }
+
+/* Properties. */
+
+objc_property_t *class_copyPropertyList(Class class_, unsigned int *numberOfReturnedProperties);
+objc_property_t class_getProperty(Class class_, const char *propertyName);
+const char *property_getName(objc_property_t property);
+const char *property_getAttributes(objc_property_t property);
+char *property_copyAttributeValue(objc_property_t property, const char *attributeName);
+objc_property_attribute_t *property_copyAttributeList(objc_property_t property,
+                                                      unsigned int *numberOfReturnedAttributes);
+
+id objc_getProperty(id self, SEL _cmd, ptrdiff_t offset, BOOL is_atomic);
+void objc_setProperty(id self, SEL _cmd, ptrdiff_t offset, id new_value, BOOL is_atomic,
+                      BOOL should_copy);
+void objc_setProperty_nonatomic_copy(id self, SEL _cmd, id new_value, ptrdiff_t offset);
+void objc_getPropertyStruct(void *destination, const void *source, ptrdiff_t size,
+                            BOOL is_atomic, BOOL has_strong);
+void objc_setPropertyStruct(void *destination, const void *source, ptrdiff_t size,
+                            BOOL is_atomic, BOOL has_strong);
+void objc_copyStruct(void *destination, const void *source, ptrdiff_t size, BOOL is_atomic,
+                     BOOL has_strong);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,372 @@
+/*
+ * Safe Rust interface to the runtime.  The handles are thin wrappers of the runtime structures,
+ * which live as long as the process once loaded, so they can be copied around freely.  Absence is
+ * expressed with Option instead of nil pointers.
+ */
+
+use std::ffi::{CString, NulError};
+use std::fmt;
+use std::ptr::NonNull;
+
+use super::class::ObjcClass;
+use super::context::CONTEXT;
+use super::ivar::ObjcIvar;
+use super::method::ObjcMethod;
+use super::object::ObjcObject;
+use super::protocol::ObjcProtocol;
+use super::ptr::Ptr;
+use super::runtime;
+use super::selector::ObjcSelector;
+use super::str_ptr::StrPtr;
+
+pub type Imp = unsafe extern "C" fn();
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Class(NonNull<ObjcClass>);
+
+unsafe impl Send for Class {}
+unsafe impl Sync for Class {}
+
+impl Class {
+    pub fn named(name: &str) -> Option<Class> {
+        let ctx = CONTEXT.read().unwrap();
+        let name = CString::new(name).ok()?;
+        let name = unsafe { StrPtr::from_ptr(name.as_ptr()) };
+        ctx.get_class_entry(&name)
+            .map(|entry| Class(NonNull::from(entry.class().as_ref())))
+    }
+
+    pub(crate) fn from_ptr(class: Ptr<ObjcClass>) -> Class {
+        Class(NonNull::from(class.as_ref()))
+    }
+
+    pub(crate) fn name_ptr(&self) -> StrPtr {
+        self.inner().get_name().clone()
+    }
+
+    fn inner(&self) -> &'static ObjcClass {
+        unsafe { &*self.0.as_ptr() }
+    }
+
+    pub fn as_ptr(&self) -> *mut ObjcClass {
+        self.0.as_ptr()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.inner().get_name().as_ref().unwrap_or("")
+    }
+
+    pub fn superclass(&self) -> Option<Class> {
+        self.inner()
+            .super_pointer()
+            .as_ref()
+            .map(|class| Class(NonNull::from(class.as_ref())))
+    }
+
+    /*
+     * The class of a class object.  Metaclasses have no metaclass of their own.
+     */
+    pub fn metaclass(&self) -> Option<Class> {
+        if self.is_metaclass() {
+            return None;
+        }
+        Some(Class(NonNull::from(self.inner().class_pointer().as_ref())))
+    }
+
+    pub fn is_metaclass(&self) -> bool {
+        self.inner().is_meta()
+    }
+
+    /*
+     * Whether the class is the other one or inherits from it.
+     */
+    pub fn is_subclass_of(&self, superclass: Class) -> bool {
+        self.inner().is_subclass_of(superclass.inner())
+    }
+
+    pub fn instance_size(&self) -> usize {
+        self.inner().get_instance_size()
+    }
+
+    pub fn instance_method(&self, selector: Sel) -> Option<Method> {
+        self.inner()
+            .lookup_method(selector.inner())
+            .map(|method| Method(NonNull::from(method.as_ref())))
+    }
+
+    pub fn class_method(&self, selector: Sel) -> Option<Method> {
+        self.metaclass()?.instance_method(selector)
+    }
+
+    /*
+     * Whether the class or one of its superclasses adopts the protocol.
+     */
+    pub fn conforms_to(&self, protocol: Protocol) -> bool {
+        let name = protocol.inner().name();
+        let mut class = Some(*self);
+        while let Some(c) = class {
+            if c.inner()
+                .protocols()
+                .is_some_and(|list| list.iter().any(|p| p.conforms_to(name)))
+            {
+                return true;
+            }
+            class = c.superclass();
+        }
+        false
+    }
+
+    /*
+     * Methods registered to the class itself, including those added by categories, but not
+     * inherited ones.
+     */
+    pub fn methods(&self) -> Vec<Method> {
+        let _ctx = CONTEXT.read().unwrap();
+        self.inner()
+            .iter_registered_method()
+            .map(|method| Method(NonNull::from(method.as_ref())))
+            .collect()
+    }
+
+    /*
+     * The direct subclasses which are loaded, most recently loaded first.
+     */
+    pub fn subclasses(&self) -> Vec<Class> {
+        let _ctx = CONTEXT.read().unwrap();
+        self.inner().iter_subclass().map(Class::from_ptr).collect()
+    }
+
+    /*
+     * The subclasses of all depths which are loaded, in depth-first order.
+     */
+    pub fn all_subclasses(&self) -> Vec<Class> {
+        let _ctx = CONTEXT.read().unwrap();
+        self.inner()
+            .iter_all_subclass()
+            .map(Class::from_ptr)
+            .collect()
+    }
+
+    pub fn ivars(&self) -> impl Iterator<Item = Ivar> {
+        self.inner()
+            .ivars()
+            .into_iter()
+            .flat_map(|list| list.as_ref().iter())
+            .map(|ivar| Ivar(NonNull::from(ivar)))
+    }
+
+    /*
+     * Allocates a zero-filled instance.  The object is not initialized by any message.
+     */
+    pub fn create_instance(&self) -> NonNull<Object> {
+        let class = super::Class(super::NilablePtr::new(unsafe {
+            super::Ptr::new(self.as_ptr())
+        }));
+        let object = runtime::class_createInstance(class, 0);
+        let object = object.0.as_ref().expect("failed to allocate an instance");
+        NonNull::from(object.as_ref()).cast()
+    }
+}
+
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Class({:?} @ {:p})", self.name(), self.0)
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sel(NonNull<ObjcSelector>);
+
+unsafe impl Send for Sel {}
+unsafe impl Sync for Sel {}
+
+impl Sel {
+    /*
+     * Selectors registered already are found under the read lock.
+     */
+    pub fn register(name: &str) -> Result<Sel, NulError> {
+        let c_name = CString::new(name)?;
+        let registered = CONTEXT
+            .read()
+            .unwrap()
+            .get_selector(&unsafe { StrPtr::from_ptr(c_name.as_ptr()) })
+            .cloned();
+        let selector =
+            registered.unwrap_or_else(|| CONTEXT.write().unwrap().register_selector_name(name));
+        Ok(Sel::from_ptr(selector))
+    }
+
+    pub(crate) fn from_ptr(selector: Ptr<ObjcSelector>) -> Sel {
+        Sel(NonNull::from(selector.as_ref()))
+    }
+
+    fn inner(&self) -> &'static ObjcSelector {
+        unsafe { &*self.0.as_ptr() }
+    }
+
+    pub fn as_ptr(&self) -> *mut ObjcSelector {
+        self.0.as_ptr()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.inner().get_id().as_ref().unwrap_or("")
+    }
+
+    pub fn types(&self) -> Option<&'static str> {
+        self.inner().get_types().as_ref()
+    }
+}
+
+impl fmt::Debug for Sel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Sel({:?})", self.name())
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Protocol(NonNull<ObjcProtocol>);
+
+unsafe impl Send for Protocol {}
+unsafe impl Sync for Protocol {}
+
+impl Protocol {
+    /*
+     * Only protocols adopted by loaded classes or categories are found.
+     */
+    pub fn named(name: &str) -> Option<Protocol> {
+        let ctx = CONTEXT.read().unwrap();
+        let name = CString::new(name).ok()?;
+        let name = unsafe { StrPtr::from_ptr(name.as_ptr()) };
+        ctx.get_protocol(&name)
+            .map(|protocol| Protocol(NonNull::from(protocol.as_ref())))
+    }
+
+    fn inner(&self) -> &'static ObjcProtocol {
+        unsafe { &*self.0.as_ptr() }
+    }
+
+    pub fn as_ptr(&self) -> *mut ObjcProtocol {
+        self.0.as_ptr()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.inner().name().as_ref().unwrap_or("")
+    }
+}
+
+impl fmt::Debug for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Protocol({:?})", self.name())
+    }
+}
+
+/*
+ * An object is only handled by reference, since its size depends on the class.
+ */
+#[repr(transparent)]
+pub struct Object(ObjcObject);
+
+impl Object {
+    pub fn class(&self) -> Class {
+        Class(NonNull::from(self.0.get_class_pointer().as_ref()))
+    }
+
+    pub fn is_kind_of(&self, class: Class) -> bool {
+        self.class().is_subclass_of(class)
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Object({:?} @ {:p})", self.class().name(), self)
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Method(NonNull<ObjcMethod>);
+
+unsafe impl Send for Method {}
+unsafe impl Sync for Method {}
+
+impl Method {
+    fn inner(&self) -> &'static ObjcMethod {
+        unsafe { &*self.0.as_ptr() }
+    }
+
+    pub fn name(&self) -> Sel {
+        Sel(NonNull::from(self.inner().name().as_ref()))
+    }
+
+    pub fn types(&self) -> Option<&'static str> {
+        self.inner().types().as_ref()
+    }
+
+    pub fn imp(&self) -> Imp {
+        unsafe { std::mem::transmute::<*mut _, Imp>(self.inner().imp().as_ptr()) }
+    }
+}
+
+impl fmt::Debug for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Method({:?}, {:?})", self.name().name(), self.types())
+    }
+}
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ivar(NonNull<ObjcIvar>);
+
+unsafe impl Send for Ivar {}
+unsafe impl Sync for Ivar {}
+
+impl Ivar {
+    fn inner(&self) -> &'static ObjcIvar {
+        unsafe { &*self.0.as_ptr() }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.inner().name().as_ref().unwrap_or("")
+    }
+
+    pub fn type_encoding(&self) -> &'static str {
+        self.inner().ivar_type().as_ref().unwrap_or("")
+    }
+
+    pub fn offset(&self) -> isize {
+        self.inner().offset()
+    }
+}
+
+impl fmt::Debug for Ivar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Ivar({:?}, {:?}, {})",
+            self.name(),
+            self.type_encoding(),
+            self.offset()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Class, Sel};
+
+    #[test]
+    fn selector() {
+        let sel = Sel::register("api:test:").unwrap();
+        assert_eq!(sel.name(), "api:test:");
+        assert_eq!(Sel::register("api:test:").unwrap(), sel);
+        assert!(Sel::register("api\0test").is_err());
+    }
+
+    #[test]
+    fn missing_class() {
+        assert!(Class::named("RobjcApiMissingClass").is_none());
+    }
+}
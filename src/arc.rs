@@ -0,0 +1,361 @@
+/*
+ * Reference counting entry points, which code compiled with ARC calls in place of sending
+ * retain, release and autorelease.  The messages are sent to objects whose classes implement
+ * them, e.g. those of a Foundation, and the references of other objects are counted in a side
+ * table, so that the last release deallocates the object with -dealloc or object_dispose.
+ * Whether a class implements the messages is cached with its methods, so that counted objects
+ * are not looked up on each call.
+ *
+ * Class objects and static instances such as string literals are never deallocated.  The last
+ * release sets the weak references to the object to nil before deallocating it.
+ */
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
+
+use super::autorelease;
+use super::message::send_message;
+use super::object::ObjcObject;
+use super::ptr::{NilablePtr, Ptr};
+use super::runtime::object_dispose;
+use super::selector::StaticSelector;
+use super::weak;
+use super::Id;
+
+pub(crate) static RETAIN: StaticSelector = StaticSelector::new(b"retain\0");
+pub(crate) static RELEASE: StaticSelector = StaticSelector::new(b"release\0");
+pub(crate) static AUTORELEASE: StaticSelector = StaticSelector::new(b"autorelease\0");
+static DEALLOC: StaticSelector = StaticSelector::new(b"dealloc\0");
+
+/*
+ * The count of objects which are never deallocated.
+ */
+const IMMORTAL: usize = usize::MAX;
+
+const SHARDS: usize = 64;
+
+lazy_static! {
+    /*
+     * The references to each object beyond the first, keyed by the address, in shards picked by
+     * the address so that objects counted by different threads rarely share a lock.  Objects
+     * which are not in the table have a single reference.
+     */
+    static ref REFCOUNTS: Vec<Mutex<HashMap<usize, usize>>> =
+        (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect();
+}
+
+fn key(object: &Ptr<ObjcObject>) -> usize {
+    object.as_ptr() as usize
+}
+
+fn refcounts(object: &Ptr<ObjcObject>) -> MutexGuard<'static, HashMap<usize, usize>> {
+    REFCOUNTS[(key(object) >> 4) % SHARDS].lock().unwrap()
+}
+
+fn is_class(object: &Ptr<ObjcObject>) -> bool {
+    object.get_class_pointer().is_meta()
+}
+
+pub fn make_immortal(object: &Ptr<ObjcObject>) {
+    refcounts(object).insert(key(object), IMMORTAL);
+}
+
+/*
+ * Drops the counts of the objects in an unloaded image, and the weak references to and from it.
+ */
+pub fn forget(unloaded: impl Fn(usize) -> bool) {
+    weak::lock().forget(&unloaded);
+    for shard in REFCOUNTS.iter() {
+        shard
+            .lock()
+            .unwrap()
+            .retain(|&address, _| !unloaded(address));
+    }
+}
+
+pub fn dispose(object: &Ptr<ObjcObject>) {
+    let mut weak = weak::lock();
+    weak.clear(key(object));
+    refcounts(object).remove(&key(object));
+}
+
+pub fn retain_count(object: &Ptr<ObjcObject>) -> usize {
+    match refcounts(object).get(&key(object)) {
+        Some(&IMMORTAL) => IMMORTAL,
+        Some(count) => count + 1,
+        None => 1,
+    }
+}
+
+/*
+ * The paths of retain, release and autorelease for objects whose classes do not implement the
+ * messages, which classes implementing them in terms of the runtime call directly as well.
+ */
+pub fn retain_counted(object: &Ptr<ObjcObject>) {
+    if is_class(object) {
+        return;
+    }
+    let mut refcounts = refcounts(object);
+    let count = refcounts.entry(key(object)).or_insert(0);
+    if *count != IMMORTAL {
+        *count += 1;
+    }
+}
+
+/*
+ * Returns whether the reference released was the last one, without removing it.
+ */
+fn release_reference(object: &Ptr<ObjcObject>) -> bool {
+    match refcounts(object).entry(key(object)) {
+        Entry::Occupied(entry) if *entry.get() == IMMORTAL => false,
+        Entry::Occupied(mut entry) => {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+            false
+        }
+        Entry::Vacant(_) => true,
+    }
+}
+
+/*
+ * Retains the object in the runtime's count unless its class implements retain, which is left to
+ * the caller to send, e.g. after unlocking the weak references.  Returns whether the object needs
+ * no message.
+ */
+pub fn try_retain_counted(object: &Ptr<ObjcObject>) -> bool {
+    if is_class(object) {
+        return true;
+    }
+    if object.get_class_pointer().overrides_reference_counting() {
+        return false;
+    }
+    retain_counted(object);
+    true
+}
+
+/*
+ * Registers the selectors of overrides_reference_counting, which locks the context the first time,
+ * so that it can be called with the weak references locked.
+ */
+pub fn register_selectors() {
+    for selector in [&RETAIN, &RELEASE, &AUTORELEASE] {
+        selector.get();
+    }
+}
+
+/*
+ * The last reference is released again under the lock of the weak references, which
+ * objc_loadWeakRetained retains objects under, so that an object is either retained by a weak
+ * load or has its weak references cleared before it is deallocated.
+ */
+pub fn release_counted(object: &Ptr<ObjcObject>) {
+    if is_class(object) || !release_reference(object) {
+        return;
+    }
+    let mut weak = weak::lock();
+    let last = release_reference(object);
+    if last {
+        weak.clear(key(object));
+    }
+    drop(weak);
+    if last && send_message(object, &DEALLOC).is_none() {
+        object_dispose(Id(NilablePtr::new(object.clone())));
+    }
+}
+
+pub fn autorelease_pooled(object: &Ptr<ObjcObject>) {
+    if !is_class(object) {
+        autorelease::add(object);
+    }
+}
+
+/*
+ * Whether the message is to be sent rather than counted by the runtime.
+ */
+fn sends(object: &Ptr<ObjcObject>, selector: &StaticSelector) -> bool {
+    object.get_class_pointer().overrides_reference_counting()
+        && send_message(object, selector).is_some()
+}
+
+pub fn retain(object: &Ptr<ObjcObject>) {
+    if is_class(object) || sends(object, &RETAIN) {
+        return;
+    }
+    retain_counted(object);
+}
+
+pub fn release(object: &Ptr<ObjcObject>) {
+    if is_class(object) || sends(object, &RELEASE) {
+        return;
+    }
+    release_counted(object);
+}
+
+pub fn autorelease(object: &Ptr<ObjcObject>) {
+    if is_class(object) || sends(object, &AUTORELEASE) {
+        return;
+    }
+    autorelease_pooled(object);
+}
+
+#[no_mangle]
+pub extern "C" fn objc_retain(object: Id) -> Id {
+    if let Some(object) = object.0.as_ref() {
+        retain(object);
+    }
+    object
+}
+
+#[no_mangle]
+pub extern "C" fn objc_release(object: Id) {
+    if let Some(object) = object.0.as_ref() {
+        release(object);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn objc_autorelease(object: Id) -> Id {
+    if let Some(object) = object.0.as_ref() {
+        autorelease(object);
+    }
+    object
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_retainAutorelease(object: Id) -> Id {
+    objc_autorelease(objc_retain(object))
+}
+
+/*
+ * The return values are autoreleased and retained again by the callers, without skipping the
+ * pool as other runtimes do when both sides are compiled with ARC.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_autoreleaseReturnValue(object: Id) -> Id {
+    objc_autorelease(object)
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_retainAutoreleaseReturnValue(object: Id) -> Id {
+    objc_autoreleaseReturnValue(objc_retain(object))
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_retainAutoreleasedReturnValue(object: Id) -> Id {
+    objc_retain(object)
+}
+
+/*
+ * Retains the new value and releases the old one, in that order, so that storing the value a
+ * location already holds keeps the object alive.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_storeStrong(location: Option<&mut Id>, value: Id) {
+    let location = match location {
+        Some(location) => location,
+        None => return,
+    };
+    if location.0.as_ref() == value.0.as_ref() {
+        return;
+    }
+    let value = objc_retain(value);
+    let old = mem::replace(location, value);
+    objc_release(old);
+}
+
+/*
+ * The number of references to an object, counted by the runtime.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_getRetainCount_np(object: Id) -> usize {
+    object.0.as_ref().map_or(0, retain_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{objc_release, objc_retain, objc_storeStrong, object_getRetainCount_np};
+    use crate::api::{Class, Object, Sel};
+    use crate::context::CONTEXT;
+    use crate::ptr::{NilablePtr, Ptr};
+    use crate::testing::{self, id};
+    use crate::Id;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DEALLOCATED: AtomicUsize = AtomicUsize::new(0);
+    static RETAINED: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn dealloc(this: *mut Object, _selector: Sel) {
+        DEALLOCATED.fetch_add(1, Ordering::SeqCst);
+        crate::runtime::object_dispose(id(this));
+    }
+
+    extern "C" fn retain(this: *mut Object, _selector: Sel) -> *mut Object {
+        RETAINED.fetch_add(1, Ordering::SeqCst);
+        this
+    }
+
+    #[test]
+    fn refcount() {
+        let counted = testing::declare("RobjcArcCounted", None, |decl| {
+            testing::add_method(decl, "dealloc", dealloc as extern "C" fn(_, _));
+        });
+        let object = counted.create_instance().as_ptr();
+
+        assert_eq!(object_getRetainCount_np(objc_retain(id(object))), 2);
+        let mut strong = Id(NilablePtr::nil());
+        objc_storeStrong(Some(&mut strong), id(object));
+        assert_eq!(object_getRetainCount_np(id(object)), 3);
+        objc_storeStrong(Some(&mut strong), Id(NilablePtr::nil()));
+        objc_release(id(object));
+        assert_eq!(object_getRetainCount_np(id(object)), 1);
+        assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 0);
+        objc_release(id(object));
+        assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 1);
+
+        /* Classes are not counted. */
+        let class = counted.as_ptr() as *mut Object;
+        objc_release(id(class));
+        assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 1);
+        assert_eq!(Class::named("RobjcArcCounted"), Some(counted));
+    }
+
+    #[test]
+    fn overridden() {
+        let overridden = testing::declare("RobjcArcOverridden", None, |decl| {
+            testing::add_method(decl, "retain", retain as extern "C" fn(_, _) -> _);
+        });
+        let object = overridden.create_instance().as_ptr();
+
+        objc_retain(id(object));
+        assert_eq!(RETAINED.load(Ordering::SeqCst), 1);
+        assert_eq!(object_getRetainCount_np(id(object)), 1);
+
+        /* Adding retain to a counted class, as a category does, sends it from then on. */
+        let counted = testing::declare("RobjcArcLater", None, |_| {});
+        let object = counted.create_instance().as_ptr();
+        objc_retain(id(object));
+        assert_eq!(object_getRetainCount_np(id(object)), 2);
+        let selector = Sel::register("retain").unwrap();
+        {
+            let _ctx = CONTEXT.write().unwrap();
+            let method = unsafe { Ptr::new(overridden.as_ptr()) }
+                .lookup_method(unsafe { &*selector.as_ptr() })
+                .unwrap();
+            unsafe { Ptr::new(counted.as_ptr()) }
+                .register_method(unsafe { Ptr::new(selector.as_ptr()) }, method);
+        }
+        objc_retain(id(object));
+        assert_eq!(RETAINED.load(Ordering::SeqCst), 2);
+        assert_eq!(object_getRetainCount_np(id(object)), 2);
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Autorelease pools.  Each thread has a stack of the objects autoreleased by objc_autorelease,
+ * and objc_autoreleasePoolPop releases those autoreleased since the matching push, including
+ * those of pools pushed later and not popped yet.  What is left when the thread exits is
+ * released then.
+ *
+ * If a Foundation provides NSAutoreleasePool, pools are its instances and objects are added to
+ * them with +addObject:, unless the class answers +_ARCCompatibleAutoreleasePool to leave the
+ * pools to the runtime.  The class is looked up after each load rather than on each call.
+ *
+ * Pools pushed before a Foundation is loaded stay the runtime's: their tokens still pop the
+ * objects autoreleased to the runtime since the push, and the pools pushed within them are
+ * NSAutoreleasePool instances, which get the objects autoreleased from then on.  So popping an
+ * outer token of the runtime releases the objects autoreleased before the Foundation was loaded,
+ * and leaves the instances to be popped, as they are, by their own tokens.
+ */
+
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use super::arc;
+use super::context::Context;
+use super::message::send_message;
+use super::object::ObjcObject;
+use super::ptr::Ptr;
+use super::selector::{ObjcSelector, StaticSelector};
+use super::str_ptr::StrPtr;
+
+struct Pool {
+    objects: Vec<Ptr<ObjcObject>>,
+}
+
+impl Pool {
+    fn pop_object(&mut self, depth: usize) -> Option<Ptr<ObjcObject>> {
+        if self.objects.len() > depth {
+            self.objects.pop()
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        /* Releasing an object may autorelease others, which are added after the thread's pool
+         * is gone and so are leaked. */
+        for object in self.objects.drain(..).rev() {
+            arc::release(&object);
+        }
+    }
+}
+
+thread_local! {
+    static POOL: RefCell<Pool> = const { RefCell::new(Pool { objects: Vec::new() }) };
+}
+
+/*
+ * The NSAutoreleasePool class of a Foundation, as an object to send class messages to, or null.
+ */
+static POOL_CLASS: AtomicPtr<ObjcObject> = AtomicPtr::new(ptr::null_mut());
+
+/*
+ * Called after each load and unload, with the context locked.  The class is used once it and its
+ * metaclass have their superclasses, so that inherited methods are found.
+ */
+pub fn resolve_pool_class(ctx: &Context) {
+    /* Not a StaticSelector, which would lock the context to register the selector. */
+    let arc_compatible = ctx.get_selector(&StrPtr::from_static(b"_ARCCompatibleAutoreleasePool\0"));
+    let class = ctx
+        .get_class_entry(&StrPtr::from_static(b"NSAutoreleasePool\0"))
+        .map(|entry| entry.class())
+        .filter(|class| class.is_resolved() && class.class_pointer().is_resolved())
+        .filter(|class| {
+            arc_compatible.map_or(true, |selector| {
+                class.class_pointer().lookup_method(selector).is_none()
+            })
+        })
+        .map_or(ptr::null_mut(), |class| class.as_ptr() as *mut ObjcObject);
+    POOL_CLASS.store(class, Ordering::Release);
+}
+
+fn foundation_pool_class() -> Option<Ptr<ObjcObject>> {
+    let class = POOL_CLASS.load(Ordering::Acquire);
+    if class.is_null() {
+        None
+    } else {
+        Some(unsafe { Ptr::new(class) })
+    }
+}
+
+pub fn add(object: &Ptr<ObjcObject>) {
+    if let Some(class) = foundation_pool_class() {
+        type AddObject = extern "C" fn(Ptr<ObjcObject>, Ptr<ObjcSelector>, Ptr<ObjcObject>);
+        static ADD_OBJECT: StaticSelector = StaticSelector::new(b"addObject:\0");
+        if let Some(method) = class.get_class_pointer().lookup_method(&ADD_OBJECT.get()) {
+            let add_object: AddObject = unsafe { mem::transmute(method.imp().as_ptr()) };
+            add_object(class.clone(), method.name().clone(), object.clone());
+            return;
+        }
+    }
+    let _ = POOL.try_with(|pool| pool.borrow_mut().objects.push(object.clone()));
+}
+
+/*
+ * Tokens of the runtime's pools are the depths of the stack, tagged with the lowest bit to tell
+ * them from NSAutoreleasePool instances.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_autoreleasePoolPush() -> *mut c_void {
+    if let Some(class) = foundation_pool_class() {
+        static NEW: StaticSelector = StaticSelector::new(b"new\0");
+        return send_message(&class, &NEW)
+            .and_then(|pool| pool.as_ref().map(|pool| pool.as_ptr() as *mut c_void))
+            .unwrap_or(ptr::null_mut());
+    }
+    let depth = POOL
+        .try_with(|pool| pool.borrow().objects.len())
+        .unwrap_or(0);
+    (depth << 1 | 1) as *mut c_void
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_autoreleasePoolPop(token: *mut c_void) {
+    if token.is_null() {
+        return;
+    }
+    if token as usize & 1 == 0 {
+        let pool = unsafe { Ptr::new(token as *const ObjcObject) };
+        static RELEASE: StaticSelector = StaticSelector::new(b"release\0");
+        send_message(&pool, &RELEASE);
+        return;
+    }
+    let depth = token as usize >> 1;
+    /* Objects released may autorelease others, which are released as well. */
+    while let Some(object) = POOL
+        .try_with(|pool| pool.borrow_mut().pop_object(depth))
+        .ok()
+        .flatten()
+    {
+        arc::release(&object);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{objc_autoreleasePoolPop, objc_autoreleasePoolPush};
+    use crate::api::{Object, Sel};
+    use crate::arc::{objc_autorelease, objc_retain, object_getRetainCount_np};
+    use crate::testing::{self, id};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    static DEALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn dealloc(this: *mut Object, _selector: Sel) {
+        DEALLOCATED.fetch_add(1, Ordering::SeqCst);
+        crate::runtime::object_dispose(id(this));
+    }
+
+    /*
+     * Runs on a thread of its own, so that the pools of other tests are not popped.  Pools of a
+     * Foundation are tested in tests/foundation_pool.rs, as its NSAutoreleasePool would take
+     * the objects autoreleased by the other tests.
+     */
+    #[test]
+    fn pools() {
+        let class = testing::declare("RobjcAutoreleased", None, |decl| {
+            testing::add_method(decl, "dealloc", dealloc as extern "C" fn(_, _));
+        });
+
+        thread::spawn(move || {
+            let outer = objc_autoreleasePoolPush();
+            let kept = class.create_instance().as_ptr();
+            objc_autorelease(objc_retain(id(kept)));
+            assert_eq!(object_getRetainCount_np(id(kept)), 2);
+
+            let inner = objc_autoreleasePoolPush();
+            objc_autorelease(id(class.create_instance().as_ptr()));
+            let _innermost = objc_autoreleasePoolPush();
+            objc_autorelease(id(class.create_instance().as_ptr()));
+
+            /* Popping the inner pool pops the innermost one too. */
+            objc_autoreleasePoolPop(inner);
+            assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 2);
+            assert_eq!(object_getRetainCount_np(id(kept)), 2);
+            objc_autoreleasePoolPop(outer);
+            assert_eq!(object_getRetainCount_np(id(kept)), 1);
+
+            /* Objects autoreleased outside pools are released when the thread exits. */
+            objc_autorelease(id(kept));
+        })
+        .join()
+        .unwrap();
+        assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,504 @@
+/*
+ * The blocks runtime.  Clang emits blocks as structures whose isa points to
+ * _NSConcreteStackBlock or _NSConcreteGlobalBlock, and copies of stack blocks made by _Block_copy
+ * get _NSConcreteMallocBlock.  The symbols are the storage of classes registered when the runtime
+ * is initialized, so blocks are objects which respond to -copy, -retain and -release.
+ *
+ * Heap blocks and __block variables count their references in the low bits of their flags, as
+ * in the ABI of other blocks runtimes.
+ */
+
+use std::ffi::c_void;
+use std::mem;
+use std::os::raw;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use super::api::{Object, Sel};
+use super::arc;
+use super::class::ObjcClass;
+use super::context::Context;
+use super::declare::ClassDecl;
+use super::ptr::{NilablePtr, Ptr};
+use super::Id;
+
+/*
+ * Large enough for a class, as the arrays of other blocks runtimes.
+ */
+type ClassStorage = [usize; 32];
+
+const _: () = assert!(mem::size_of::<ObjcClass>() <= mem::size_of::<ClassStorage>());
+
+#[allow(non_upper_case_globals)]
+#[no_mangle]
+pub static mut _NSConcreteStackBlock: ClassStorage = [0; 32];
+#[allow(non_upper_case_globals)]
+#[no_mangle]
+pub static mut _NSConcreteGlobalBlock: ClassStorage = [0; 32];
+#[allow(non_upper_case_globals)]
+#[no_mangle]
+pub static mut _NSConcreteMallocBlock: ClassStorage = [0; 32];
+
+const ROOT_CLASS_NAME: &str = "__NSBlock";
+
+const BLOCK_DEALLOCATING: i32 = 0x1;
+const BLOCK_REFCOUNT_MASK: i32 = 0xfffe;
+const BLOCK_NEEDS_FREE: i32 = 1 << 24;
+const BLOCK_HAS_COPY_DISPOSE: i32 = 1 << 25;
+const BLOCK_IS_GLOBAL: i32 = 1 << 28;
+
+const BLOCK_BYREF_NEEDS_FREE: i32 = 1 << 24;
+const BLOCK_BYREF_HAS_COPY_DISPOSE: i32 = 1 << 25;
+
+const BLOCK_FIELD_IS_OBJECT: raw::c_int = 3;
+const BLOCK_FIELD_IS_BLOCK: raw::c_int = 7;
+const BLOCK_FIELD_IS_BYREF: raw::c_int = 8;
+const BLOCK_FIELD_IS_WEAK: raw::c_int = 16;
+const BLOCK_BYREF_CALLER: raw::c_int = 128;
+
+#[repr(C)]
+struct BlockDescriptor {
+    reserved: raw::c_ulong,
+    size: raw::c_ulong,
+    /*
+     * Only present if the block has BLOCK_HAS_COPY_DISPOSE.
+     */
+    copy: extern "C" fn(destination: *mut c_void, source: *const c_void),
+    dispose: extern "C" fn(block: *const c_void),
+}
+
+#[repr(C)]
+struct Block {
+    isa: *mut ObjcClass,
+    flags: AtomicI32,
+    reserved: i32,
+    invoke: *const c_void,
+    descriptor: *const BlockDescriptor,
+}
+
+/*
+ * A __block variable, which blocks refer to through the forwarding pointer once it is moved to
+ * the heap.
+ */
+#[repr(C)]
+struct Byref {
+    isa: *mut c_void,
+    forwarding: *mut Byref,
+    flags: AtomicI32,
+    size: u32,
+}
+
+/*
+ * Follows the header of variables with BLOCK_BYREF_HAS_COPY_DISPOSE.
+ */
+#[repr(C)]
+struct ByrefHelpers {
+    keep: extern "C" fn(destination: *mut Byref, source: *mut Byref),
+    destroy: extern "C" fn(byref: *mut Byref),
+}
+
+fn class_storage(storage: *mut ClassStorage) -> *mut ObjcClass {
+    storage as *mut ObjcClass
+}
+
+/*
+ * Increments the count in the flags, which stays at the maximum once it gets there.
+ */
+fn retain_flags(flags: &AtomicI32) {
+    let _ = flags.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+        if old & BLOCK_REFCOUNT_MASK == BLOCK_REFCOUNT_MASK {
+            None
+        } else {
+            Some(old + 2)
+        }
+    });
+}
+
+/*
+ * Decrements the count in the flags.  Returns true when the last reference is released.
+ */
+fn release_flags(flags: &AtomicI32) -> bool {
+    let result = flags.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+        match old & BLOCK_REFCOUNT_MASK {
+            BLOCK_REFCOUNT_MASK | 0 => None,
+            2 => Some((old - 2) | BLOCK_DEALLOCATING),
+            _ => Some(old - 2),
+        }
+    });
+    matches!(result, Ok(old) if old & BLOCK_REFCOUNT_MASK == 2)
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn _Block_copy(block: *const c_void) -> *mut c_void {
+    let block = block as *mut Block;
+    if block.is_null() {
+        return ptr::null_mut();
+    }
+    let source = unsafe { &*block };
+    let flags = source.flags.load(Ordering::SeqCst);
+    if flags & BLOCK_NEEDS_FREE != 0 {
+        retain_flags(&source.flags);
+        return block as *mut c_void;
+    }
+    if flags & BLOCK_IS_GLOBAL != 0 {
+        return block as *mut c_void;
+    }
+    let descriptor = unsafe { &*source.descriptor };
+    let copy = unsafe { libc::malloc(descriptor.size as usize) } as *mut Block;
+    if copy.is_null() {
+        return ptr::null_mut();
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(
+            block as *const u8,
+            copy as *mut u8,
+            descriptor.size as usize,
+        );
+        let flags = flags & !(BLOCK_REFCOUNT_MASK | BLOCK_DEALLOCATING);
+        (*copy).flags = AtomicI32::new(flags | BLOCK_NEEDS_FREE | 2);
+        (*copy).isa = class_storage(ptr::addr_of_mut!(_NSConcreteMallocBlock));
+        if flags & BLOCK_HAS_COPY_DISPOSE != 0 {
+            (descriptor.copy)(copy as *mut c_void, block as *const c_void);
+        }
+    }
+    copy as *mut c_void
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn _Block_release(block: *const c_void) {
+    let block = block as *mut Block;
+    if block.is_null() {
+        return;
+    }
+    let source = unsafe { &*block };
+    let flags = source.flags.load(Ordering::SeqCst);
+    if flags & BLOCK_NEEDS_FREE == 0 || !release_flags(&source.flags) {
+        return;
+    }
+    unsafe {
+        if flags & BLOCK_HAS_COPY_DISPOSE != 0 {
+            ((*source.descriptor).dispose)(block as *const c_void);
+        }
+        libc::free(block as *mut c_void);
+    }
+}
+
+/*
+ * Moves a __block variable to the heap when the first block referring to it is copied.
+ */
+fn byref_copy(byref: *mut Byref) -> *mut Byref {
+    let source = unsafe { &mut *(*byref).forwarding };
+    let flags = source.flags.load(Ordering::SeqCst);
+    if flags & BLOCK_BYREF_NEEDS_FREE != 0 {
+        retain_flags(&source.flags);
+        return source;
+    }
+    let size = source.size as usize;
+    let copy = unsafe { libc::malloc(size) } as *mut Byref;
+    if copy.is_null() {
+        return ptr::null_mut();
+    }
+    unsafe {
+        /* One reference for the copied block and one for the variable on the stack. */
+        ptr::write(
+            copy,
+            Byref {
+                isa: ptr::null_mut(),
+                forwarding: copy,
+                flags: AtomicI32::new(flags | BLOCK_BYREF_NEEDS_FREE | 4),
+                size: source.size,
+            },
+        );
+        source.forwarding = copy;
+        if flags & BLOCK_BYREF_HAS_COPY_DISPOSE != 0 {
+            let helpers = &*(source as *mut Byref).add(1).cast::<ByrefHelpers>();
+            ptr::write(
+                copy.add(1).cast::<ByrefHelpers>(),
+                ByrefHelpers {
+                    keep: helpers.keep,
+                    destroy: helpers.destroy,
+                },
+            );
+            (helpers.keep)(copy, source);
+        } else {
+            let header = mem::size_of::<Byref>();
+            ptr::copy_nonoverlapping(
+                (source as *mut Byref as *const u8).add(header),
+                (copy as *mut u8).add(header),
+                size - header,
+            );
+        }
+    }
+    copy
+}
+
+fn byref_release(byref: *mut Byref) {
+    let byref = unsafe { (*byref).forwarding };
+    let variable = unsafe { &*byref };
+    let flags = variable.flags.load(Ordering::SeqCst);
+    if flags & BLOCK_BYREF_NEEDS_FREE == 0 || !release_flags(&variable.flags) {
+        return;
+    }
+    unsafe {
+        if flags & BLOCK_BYREF_HAS_COPY_DISPOSE != 0 {
+            let helpers = &*byref.add(1).cast::<ByrefHelpers>();
+            (helpers.destroy)(byref);
+        }
+        libc::free(byref as *mut c_void);
+    }
+}
+
+/*
+ * Called by the copy helpers of blocks and __block variables for each captured object, block and
+ * variable.  Objects captured by __block variables are not retained, as with other runtimes.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn _Block_object_assign(
+    destination: *mut c_void,
+    object: *const c_void,
+    flags: raw::c_int,
+) {
+    let destination = destination as *mut *const c_void;
+    let value = match flags {
+        BLOCK_FIELD_IS_OBJECT => {
+            arc::objc_retain(id(object));
+            object
+        }
+        BLOCK_FIELD_IS_BLOCK => _Block_copy(object),
+        _ if flags & !BLOCK_FIELD_IS_WEAK == BLOCK_FIELD_IS_BYREF => {
+            byref_copy(object as *mut Byref) as *const c_void
+        }
+        _ if flags & BLOCK_BYREF_CALLER != 0 => object,
+        _ => return,
+    };
+    unsafe { *destination = value };
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn _Block_object_dispose(object: *const c_void, flags: raw::c_int) {
+    match flags {
+        BLOCK_FIELD_IS_OBJECT => arc::objc_release(id(object)),
+        BLOCK_FIELD_IS_BLOCK => _Block_release(object),
+        _ if flags & !BLOCK_FIELD_IS_WEAK == BLOCK_FIELD_IS_BYREF => {
+            byref_release(object as *mut Byref)
+        }
+        _ => {}
+    }
+}
+
+/*
+ * Called by ARC for blocks stored to strong variables.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_retainBlock(block: Id) -> Id {
+    let block = block
+        .0
+        .as_ref()
+        .map_or(ptr::null(), |block| block.as_ptr() as *const c_void);
+    id(_Block_copy(block))
+}
+
+fn id(object: *const c_void) -> Id {
+    Id(NilablePtr::from(if object.is_null() {
+        None
+    } else {
+        Some(unsafe { Ptr::new(object as *const _) })
+    }))
+}
+
+extern "C" fn copy(this: *mut Object, _selector: Sel) -> *mut Object {
+    _Block_copy(this as *const c_void) as *mut Object
+}
+
+/*
+ * Retaining a stack block does not copy it, so only heap blocks are counted.
+ */
+extern "C" fn retain(this: *mut Object, _selector: Sel) -> *mut Object {
+    let block = unsafe { &*(this as *const Block) };
+    if block.flags.load(Ordering::SeqCst) & BLOCK_NEEDS_FREE != 0 {
+        retain_flags(&block.flags);
+    }
+    this
+}
+
+extern "C" fn release(this: *mut Object, _selector: Sel) {
+    _Block_release(this as *const c_void)
+}
+
+pub fn register(ctx: &mut Context) {
+    let mut root = ClassDecl::with_superclass(ROOT_CLASS_NAME, None).unwrap();
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = copy;
+    root.add_method(Sel::from_ptr(ctx.register_selector_name("copy")), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = retain;
+    root.add_method(Sel::from_ptr(ctx.register_selector_name("retain")), f);
+    let f: extern "C" fn(*mut Object, Sel) = release;
+    root.add_method(Sel::from_ptr(ctx.register_selector_name("release")), f);
+    let root = root.register_in(ctx).unwrap();
+
+    let storages = [
+        (
+            "_NSConcreteStackBlock",
+            ptr::addr_of_mut!(_NSConcreteStackBlock),
+        ),
+        (
+            "_NSConcreteGlobalBlock",
+            ptr::addr_of_mut!(_NSConcreteGlobalBlock),
+        ),
+        (
+            "_NSConcreteMallocBlock",
+            ptr::addr_of_mut!(_NSConcreteMallocBlock),
+        ),
+    ];
+    for (name, storage) in storages {
+        let mut decl = ClassDecl::with_superclass(name, Some(root)).unwrap();
+        unsafe { decl.place_at(class_storage(storage)) };
+        decl.register_in(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        _Block_copy, _Block_object_assign, _Block_object_dispose, _Block_release,
+        _NSConcreteMallocBlock, _NSConcreteStackBlock, class_storage, Block, BlockDescriptor,
+        Byref, BLOCK_FIELD_IS_BYREF, BLOCK_FIELD_IS_OBJECT, BLOCK_HAS_COPY_DISPOSE,
+    };
+    use crate::api::{Class, Object};
+    use crate::arc::object_getRetainCount_np;
+    use crate::msg_send;
+    use crate::testing::{self, id};
+    use std::ffi::c_void;
+    use std::mem;
+    use std::ptr;
+    use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+
+    static DISPOSED: AtomicUsize = AtomicUsize::new(0);
+
+    /*
+     * A block capturing an object and a __block int.
+     */
+    #[repr(C)]
+    struct CapturingBlock {
+        block: Block,
+        object: *const c_void,
+        counter: *mut Byref,
+    }
+
+    #[repr(C)]
+    struct IntByref {
+        byref: Byref,
+        value: i32,
+    }
+
+    extern "C" fn invoke(block: *const CapturingBlock) -> i32 {
+        unsafe {
+            let counter = (*(*block).counter).forwarding as *mut IntByref;
+            (*counter).value += 1;
+            (*counter).value
+        }
+    }
+
+    extern "C" fn copy_helper(destination: *mut c_void, source: *const c_void) {
+        let destination = destination as *mut CapturingBlock;
+        let source = source as *const CapturingBlock;
+        unsafe {
+            _Block_object_assign(
+                ptr::addr_of_mut!((*destination).object) as *mut c_void,
+                (*source).object,
+                BLOCK_FIELD_IS_OBJECT,
+            );
+            _Block_object_assign(
+                ptr::addr_of_mut!((*destination).counter) as *mut c_void,
+                (*source).counter as *const c_void,
+                BLOCK_FIELD_IS_BYREF,
+            );
+        }
+    }
+
+    extern "C" fn dispose_helper(block: *const c_void) {
+        let block = block as *const CapturingBlock;
+        DISPOSED.fetch_add(1, Ordering::SeqCst);
+        unsafe {
+            _Block_object_dispose((*block).object, BLOCK_FIELD_IS_OBJECT);
+            _Block_object_dispose((*block).counter as *const c_void, BLOCK_FIELD_IS_BYREF);
+        }
+    }
+
+    static DESCRIPTOR: BlockDescriptor = BlockDescriptor {
+        reserved: 0,
+        size: mem::size_of::<CapturingBlock>() as _,
+        copy: copy_helper,
+        dispose: dispose_helper,
+    };
+
+    #[test]
+    fn blocks() {
+        let captured = testing::declare("RobjcBlockCaptured", None, |_| {})
+            .create_instance()
+            .as_ptr() as *const c_void;
+
+        let mut counter = IntByref {
+            byref: Byref {
+                isa: ptr::null_mut(),
+                forwarding: ptr::null_mut(),
+                flags: AtomicI32::new(0),
+                size: mem::size_of::<IntByref>() as u32,
+            },
+            value: 0,
+        };
+        counter.byref.forwarding = &mut counter.byref;
+        let stack = CapturingBlock {
+            block: Block {
+                isa: class_storage(ptr::addr_of_mut!(_NSConcreteStackBlock)),
+                flags: AtomicI32::new(BLOCK_HAS_COPY_DISPOSE),
+                reserved: 0,
+                invoke: invoke as *const c_void,
+                descriptor: &DESCRIPTOR,
+            },
+            object: captured,
+            counter: &mut counter.byref,
+        };
+
+        /* Stack blocks are objects of a registered class. */
+        let stack_block = &stack as *const CapturingBlock as *mut Object;
+        let stack_class = Class::named("_NSConcreteStackBlock").unwrap();
+        assert_eq!(stack_class.as_ptr(), stack.block.isa);
+        assert_eq!(stack_class.superclass().unwrap().name(), "__NSBlock");
+
+        let heap: *mut Object = unsafe { msg_send![stack_block, copy] };
+        let heap_block = heap as *const CapturingBlock;
+        unsafe {
+            assert_eq!(
+                (*heap_block).block.isa,
+                class_storage(ptr::addr_of_mut!(_NSConcreteMallocBlock))
+            );
+        }
+        assert_eq!(object_getRetainCount_np(id(captured)), 2);
+
+        /* The __block variable is moved to the heap, and shared with the stack frame. */
+        assert_eq!(invoke(heap_block), 1);
+        assert_eq!(invoke(&stack), 2);
+        let stack_byref = ptr::addr_of_mut!(counter.byref);
+        assert_ne!(counter.byref.forwarding, stack_byref);
+
+        assert_eq!(_Block_copy(heap as *const c_void), heap as *mut c_void);
+        unsafe {
+            let () = msg_send![heap, release];
+        }
+        assert_eq!(DISPOSED.load(Ordering::SeqCst), 0);
+        _Block_release(heap as *const c_void);
+        assert_eq!(DISPOSED.load(Ordering::SeqCst), 1);
+        assert_eq!(object_getRetainCount_np(id(captured)), 1);
+
+        /* The stack frame releases its reference to the __block variable. */
+        _Block_object_dispose(
+            &mut counter.byref as *mut Byref as *const c_void,
+            BLOCK_FIELD_IS_BYREF,
+        );
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use super::context::Context;
 use super::method::ObjcMethodList;
+use super::property::ObjcPropertyList;
+use super::protocol::ObjcProtocolList;
 use super::ptr::Ptr;
 use super::str_ptr::StrPtr;
 
@@ -12,12 +14,40 @@ pub struct ObjcCategory {
     class_name: StrPtr,
     instance_methods: Option<Ptr<ObjcMethodList>>,
     class_methods: Option<Ptr<ObjcMethodList>>,
-    protocols: Option<Ptr<()>>,
+    protocols: Option<Ptr<ObjcProtocolList>>,
+    /*
+     * Only present in modules compiled with the GNUstep ABIs.
+     */
+    properties: Option<Ptr<ObjcPropertyList>>,
 }
 
 impl ObjcCategory {
     pub fn initialize(&mut self, _ctx: &mut Context) {}
 
+    pub fn category_name(&self) -> &StrPtr {
+        &self.category_name
+    }
+
+    pub fn class_name(&self) -> &StrPtr {
+        &self.class_name
+    }
+
+    pub fn instance_methods(&self) -> Option<&Ptr<ObjcMethodList>> {
+        self.instance_methods.as_ref()
+    }
+
+    pub fn class_methods(&self) -> Option<&Ptr<ObjcMethodList>> {
+        self.class_methods.as_ref()
+    }
+
+    pub fn protocols(&self) -> Option<&Ptr<ObjcProtocolList>> {
+        self.protocols.as_ref()
+    }
+
+    pub fn properties(&self) -> Option<&Ptr<ObjcPropertyList>> {
+        self.properties.as_ref()
+    }
+
     pub fn defer_resolving_methods(&self, ctx: &mut Context) -> bool {
         let (class, meta_class) = match ctx.get_class_entry(&self.class_name) {
             Some(entry) => (entry.class().clone(), entry.meta_class().clone()),
@@ -1,17 +1,52 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
 
+use super::arc;
 use super::context::Context;
 use super::ivar::ObjcIvarList;
 use super::method::ObjcMethod;
 use super::method::ObjcMethodList;
+use super::property::ObjcPropertyList;
+use super::protocol::ObjcProtocolList;
 use super::ptr::Ptr;
 use super::selector::ObjcSelector;
 use super::str_ptr::StrPtr;
 use super::Long;
 use super::ULong;
 
+/*
+ * The methods registered to a class itself and a cache of the methods found by lookups, which
+ * include those inherited from superclasses.  Methods replaced by categories are kept, so that
+ * they are restored when the categories are unloaded.  Methods are keyed by ObjcSelector::key.
+ */
+#[derive(Debug, Default)]
+struct DispatchTable {
+    methods: HashMap<usize, Ptr<ObjcMethod>>,
+    overridden: HashMap<usize, Vec<Ptr<ObjcMethod>>>,
+    cache: RwLock<HashMap<usize, Ptr<ObjcMethod>>>,
+    /*
+     * Counts the clears of the cache, so that a method found by a lookup racing with a change
+     * of the methods is not cached after the clear.
+     */
+    generation: AtomicUsize,
+    instances: AtomicUsize,
+    /*
+     * Whether the class implements retain, release or autorelease, cached like the methods.  The
+     * low bits hold one of the RR_ states, and the others count invalidations, so that a state
+     * computed while the methods change is not stored.
+     */
+    reference_counting: AtomicUsize,
+}
+
+const RR_UNKNOWN: usize = 0;
+const RR_COUNTED: usize = 1;
+const RR_OVERRIDDEN: usize = 2;
+const RR_MASK: usize = 0b11;
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct ObjcClass {
@@ -23,14 +58,77 @@ pub struct ObjcClass {
     instance_size: Long,
     ivars: Option<Ptr<ObjcIvarList>>,
     methods: Option<Ptr<ObjcMethodList>>,
-    dtable: Option<Box<HashMap<Ptr<ObjcSelector>, Ptr<ObjcMethod>>>>,
-    subclass_list: Option<Ptr<()>>,
-    sibling_list: Option<Ptr<()>>,
-    protocols: Option<Ptr<()>>,
+    dtable: Option<Box<DispatchTable>>,
+    subclass_list: Option<Ptr<ObjcClass>>,
+    sibling_list: Option<Ptr<ObjcClass>>,
+    protocols: Option<Ptr<ObjcProtocolList>>,
     gc_object_type: Option<Ptr<()>>,
+    /*
+     * The following fields are only emitted for classes compiled with the GNUstep ABIs, which
+     * are marked with CLS_NEW_ABI.
+     */
+    abi_version: Long,
+    ivar_offsets: Option<Ptr<()>>,
+    properties: Option<Ptr<ObjcPropertyList>>,
 }
 
 impl ObjcClass {
+    /*
+     * Creates a class and its metaclass in the compiled form, where the superclass and the
+     * root class are referred to by name, so that they can be loaded like compiled classes.
+     */
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_pair(
+        name: StrPtr,
+        super_name: Option<StrPtr>,
+        root_name: StrPtr,
+        instance_size: usize,
+        ivars: Option<Ptr<ObjcIvarList>>,
+        methods: Option<Ptr<ObjcMethodList>>,
+        class_methods: Option<Ptr<ObjcMethodList>>,
+        protocols: Option<Ptr<ObjcProtocolList>>,
+    ) -> Ptr<ObjcClass> {
+        let as_class = |name: StrPtr| unsafe { mem::transmute::<StrPtr, Ptr<ObjcClass>>(name) };
+        let new = |class_pointer, info, instance_size, ivars, methods| ObjcClass {
+            class_pointer,
+            super_pointer: super_name.clone().map(as_class),
+            name: name.clone(),
+            version: 0,
+            info,
+            instance_size: instance_size as Long,
+            ivars,
+            methods,
+            dtable: None,
+            subclass_list: None,
+            sibling_list: None,
+            protocols: protocols.clone(),
+            gc_object_type: None,
+            abi_version: 0,
+            ivar_offsets: None,
+            properties: None,
+        };
+        let meta_class = new(
+            as_class(root_name),
+            0b10,
+            mem::size_of::<ObjcClass>(),
+            None,
+            class_methods,
+        );
+        let meta_class = unsafe { Ptr::new(Box::into_raw(Box::new(meta_class))) };
+        let class = new(meta_class, 0b1, instance_size, ivars, methods);
+        unsafe { Ptr::new(Box::into_raw(Box::new(class))) }
+    }
+
+    /*
+     * Moves a class created by new_pair to storage defined elsewhere, e.g. a symbol which
+     * compiled code refers to.  The storage must be large enough and outlive the class.
+     */
+    pub unsafe fn move_to(class: Ptr<ObjcClass>, storage: *mut ObjcClass) -> Ptr<ObjcClass> {
+        let class = Box::from_raw(class.as_ptr());
+        ptr::write(storage, *class);
+        Ptr::new(storage)
+    }
+
     pub fn class_pointer(&self) -> &Ptr<ObjcClass> {
         &self.class_pointer
     }
@@ -43,6 +141,39 @@ impl ObjcClass {
         &self.super_pointer
     }
 
+    /*
+     * The name of the superclass, which is all an unresolved class knows of it.
+     */
+    pub fn superclass_name(&self) -> Option<StrPtr> {
+        self.super_pointer.as_ref().map(|p| {
+            if self.is_resolved() {
+                p.name.clone()
+            } else {
+                unsafe { mem::transmute::<Ptr<ObjcClass>, StrPtr>(p.clone()) }
+            }
+        })
+    }
+
+    /*
+     * Whether the class is the other one or inherits from it.  The walk stops at an unresolved
+     * class, whose super pointer still holds the name of its superclass.
+     */
+    pub fn is_subclass_of(&self, other: &ObjcClass) -> bool {
+        let mut class = self;
+        loop {
+            if ptr::eq(class, other) {
+                return true;
+            }
+            if !class.is_resolved() {
+                return false;
+            }
+            match &class.super_pointer {
+                Some(superclass) => class = superclass.as_ref(),
+                None => return false,
+            }
+        }
+    }
+
     pub fn get_name(&self) -> &StrPtr {
         &self.name
     }
@@ -51,6 +182,65 @@ impl ObjcClass {
         self.instance_size as usize
     }
 
+    pub fn version(&self) -> Long {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: Long) {
+        self.version = version;
+    }
+
+    pub fn ivars(&self) -> Option<&Ptr<ObjcIvarList>> {
+        self.ivars.as_ref()
+    }
+
+    /*
+     * Iterates the methods registered to the class itself, including those added by categories.
+     */
+    pub fn iter_registered_method(&self) -> impl Iterator<Item = &Ptr<ObjcMethod>> {
+        self.dtable.iter().flat_map(|table| table.methods.values())
+    }
+
+    /*
+     * Iterates the direct subclasses, most recently loaded first.  Metaclasses list the
+     * metaclasses of the subclasses.
+     */
+    pub fn iter_subclass(&self) -> impl Iterator<Item = Ptr<ObjcClass>> {
+        let mut next = self.subclass_list.clone();
+        std::iter::from_fn(move || {
+            let class = next.take()?;
+            next = class.sibling_list.clone();
+            Some(class)
+        })
+    }
+
+    /*
+     * Iterates the subclasses of all depths in depth-first order.
+     */
+    pub fn iter_all_subclass(&self) -> impl Iterator<Item = Ptr<ObjcClass>> {
+        let mut stack: Vec<Ptr<ObjcClass>> = self.iter_subclass().collect();
+        stack.reverse();
+        std::iter::from_fn(move || {
+            let class = stack.pop()?;
+            let mut subclasses: Vec<_> = class.iter_subclass().collect();
+            subclasses.reverse();
+            stack.extend(subclasses);
+            Some(class)
+        })
+    }
+
+    pub fn protocols(&self) -> Option<&Ptr<ObjcProtocolList>> {
+        self.protocols.as_ref()
+    }
+
+    /*
+     * Protocols adopted by categories are chained in front of those of the class.
+     */
+    pub fn add_protocols(&mut self, mut protocols: Ptr<ObjcProtocolList>) {
+        protocols.set_next(self.protocols.take());
+        self.protocols = Some(protocols);
+    }
+
     pub fn is_class(&self) -> bool {
         self.info & 0b1 != 0
     }
@@ -59,25 +249,147 @@ impl ObjcClass {
         self.info & 0b10 != 0
     }
 
+    /*
+     * CLS_RESOLV of the GNU runtime, set once the superclass is linked.
+     */
+    pub fn is_resolved(&self) -> bool {
+        self.info & 0b1000 != 0
+    }
+
+    /*
+     * Set on metaclasses whose isa is linked to the root metaclass.  Compilers leave bits 8 to 15
+     * of the info clear.
+     */
+    pub fn is_isa_linked(&self) -> bool {
+        self.info & 0x100 != 0
+    }
+
+    pub fn is_new_abi(&self) -> bool {
+        self.info & 0b1_0000 != 0
+    }
+
+    pub fn properties(&self) -> Option<&Ptr<ObjcPropertyList>> {
+        if self.is_new_abi() {
+            self.properties.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn resolve_method(&self, selector: Ptr<ObjcSelector>) -> Option<Ptr<ObjcMethod>> {
-        let table = self.dtable.as_ref().expect("dtable is not initialized");
-        table
-            .get(&selector)
-            .map(|method| method.clone())
-            .or_else(|| {
-                self.super_pointer
-                    .as_ref()
-                    .and_then(|super_class| super_class.resolve_method(selector))
-            })
+        self.lookup_method(&selector)
+    }
+
+    /*
+     * Methods found are cached, but failed lookups are not, so that forwarding sees methods
+     * added later.  A miss does not wait for the cache held by other threads; the method is
+     * then cached by a later lookup.  The selector must be registered or belong to a loaded
+     * module.  Classes which are not initialized have no methods.
+     */
+    pub fn lookup_method(&self, selector: &ObjcSelector) -> Option<Ptr<ObjcMethod>> {
+        let key = selector.key();
+        let table = self.dtable.as_ref()?;
+        if let Some(method) = table.cache.read().unwrap().get(&key) {
+            return Some(method.clone());
+        }
+        let generation = table.generation.load(Ordering::Acquire);
+        let method = table.methods.get(&key).cloned().or_else(|| {
+            self.super_pointer
+                .as_ref()
+                .filter(|_| self.is_resolved())
+                .and_then(|super_class| super_class.lookup_method(selector))
+        })?;
+        if let Ok(mut cache) = table.cache.try_write() {
+            if table.generation.load(Ordering::Acquire) == generation {
+                cache.insert(key, method.clone());
+            }
+        }
+        Some(method)
+    }
+
+    /*
+     * Whether instances respond to retain, release or autorelease, which the ARC entry points
+     * otherwise count in the runtime without sending the messages.
+     */
+    pub fn overrides_reference_counting(&self) -> bool {
+        let table = match self.dtable.as_ref() {
+            Some(table) => table,
+            None => return false,
+        };
+        let state = table.reference_counting.load(Ordering::Acquire);
+        match state & RR_MASK {
+            RR_COUNTED => return false,
+            RR_OVERRIDDEN => return true,
+            _ => {}
+        }
+        let overrides = [&arc::RETAIN, &arc::RELEASE, &arc::AUTORELEASE]
+            .iter()
+            .any(|selector| self.lookup_method(&selector.get()).is_some());
+        let known = if overrides { RR_OVERRIDDEN } else { RR_COUNTED };
+        let _ = table.reference_counting.compare_exchange(
+            state,
+            state | known,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        overrides
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn is_cached(&self, selector: &ObjcSelector) -> bool {
+        self.dtable
+            .as_ref()
+            .is_some_and(|table| table.cache.read().unwrap().contains_key(&selector.key()))
+    }
+
+    /*
+     * The class among the class and its superclasses which has the method registered.  The
+     * superclasses of classes which are not resolved yet are not searched.
+     */
+    pub fn method_owner(&self, method: &Ptr<ObjcMethod>) -> Option<&ObjcClass> {
+        let mut class = Some(self);
+        while let Some(c) = class {
+            if c.iter_registered_method().any(|m| m == method) {
+                return Some(c);
+            }
+            class = c
+                .super_pointer
+                .as_ref()
+                .filter(|_| c.is_resolved())
+                .map(|p| p.as_ref());
+        }
+        None
+    }
+
+    /*
+     * Clears the caches of the class and its subclasses, which may hold methods the class now
+     * overrides.
+     */
+    pub fn invalidate_caches(&self) {
+        let classes = std::iter::once(unsafe { Ptr::new(self) }).chain(self.iter_all_subclass());
+        for class in classes {
+            if let Some(table) = class.dtable.as_ref() {
+                let mut cache = table.cache.write().unwrap();
+                cache.clear();
+                table.generation.fetch_add(1, Ordering::AcqRel);
+                drop(cache);
+                let _ = table.reference_counting.fetch_update(
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                    |state| Some((((state >> 2) + 1) << 2) | RR_UNKNOWN),
+                );
+            }
+        }
     }
 
     pub fn initialize(&mut self, ctx: &mut Context) {
-        self.dtable = Some(Box::new(HashMap::new()));
+        self.dtable = Some(Box::default());
         self.defer_resolving_methods(ctx);
     }
 
     pub fn initialize_super_pointer(&mut self, ctx: &mut Context) -> bool {
         if self.super_pointer.is_none() {
+            self.info |= 0b1000;
             return true;
         }
         let super_class_name: StrPtr =
@@ -89,21 +401,163 @@ impl ObjcClass {
                 entry.class()
             };
             self.super_pointer = Some(p.clone());
+            self.info |= 0b1000;
+            self.link_to_superclass();
             true
         } else {
             false
         }
     }
 
+    /*
+     * The isa of a metaclass is emitted as the name of the root class, and is linked to the root
+     * metaclass once the root class is registered.
+     */
+    pub fn initialize_class_pointer(&mut self, ctx: &Context) -> bool {
+        assert!(self.is_meta());
+        if self.is_isa_linked() {
+            return true;
+        }
+        let root_name =
+            unsafe { mem::transmute::<Ptr<ObjcClass>, StrPtr>(self.class_pointer.clone()) };
+        match ctx.get_class_entry(&root_name) {
+            Some(entry) => {
+                self.class_pointer = entry.meta_class().clone();
+                self.info |= 0x100;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /*
+     * Adds the class in front of the subclass list of its superclass, as the GNU runtime does.
+     */
+    fn link_to_superclass(&mut self) {
+        let this = unsafe { Ptr::new(self as *mut ObjcClass) };
+        let mut super_class = self
+            .super_pointer
+            .clone()
+            .expect("superclass is not resolved");
+        self.sibling_list = super_class.subclass_list.replace(this);
+        self.invalidate_caches();
+    }
+
+    /*
+     * Removes the class from the subclass list of its superclass, which must be resolved.
+     */
+    pub fn unlink_from_superclass(&mut self) {
+        let this = unsafe { Ptr::new(self as *mut ObjcClass) };
+        let next = self.sibling_list.take();
+        let mut super_class = self
+            .super_pointer
+            .clone()
+            .expect("superclass is not resolved");
+        if super_class.subclass_list.as_ref() == Some(&this) {
+            super_class.subclass_list = next;
+            return;
+        }
+        let mut current = super_class.subclass_list.clone();
+        while let Some(mut class) = current {
+            if class.sibling_list.as_ref() == Some(&this) {
+                class.sibling_list = next;
+                return;
+            }
+            current = class.sibling_list.clone();
+        }
+    }
+
     pub fn register_method(
         &mut self,
         selector: Ptr<ObjcSelector>,
         method: Ptr<ObjcMethod>,
     ) -> Option<Ptr<ObjcMethod>> {
+        let previous = self.add_method(selector, method);
+        self.invalidate_caches();
+        previous
+    }
+
+    /*
+     * Registers a method without clearing the caches, for loaders adding many methods, which
+     * call invalidate_caches once they are done.
+     */
+    pub fn add_method(
+        &mut self,
+        selector: Ptr<ObjcSelector>,
+        method: Ptr<ObjcMethod>,
+    ) -> Option<Ptr<ObjcMethod>> {
+        let key = selector.key();
+        let table = self.dtable.as_mut().expect("dtable is not initialized");
+        let previous = table.methods.insert(key, method);
+        if let Some(previous) = previous.clone() {
+            table.overridden.entry(key).or_default().push(previous);
+        }
+        previous
+    }
+
+    /*
+     * Removes the methods being unloaded, restoring the methods they replaced.
+     */
+    pub fn unregister_methods(&mut self, unloaded: impl Fn(&Ptr<ObjcMethod>) -> bool) {
+        let table = self.dtable.as_mut().expect("dtable is not initialized");
+        for methods in table.overridden.values_mut() {
+            methods.retain(|method| !unloaded(method));
+        }
+        let keys: Vec<usize> = table
+            .methods
+            .iter()
+            .filter(|(_, method)| unloaded(method))
+            .map(|(&key, _)| key)
+            .collect();
+        for key in keys {
+            table.methods.remove(&key);
+            if let Some(previous) = table.overridden.get_mut(&key).and_then(|m| m.pop()) {
+                table.methods.insert(key, previous);
+            }
+        }
+        table.overridden.retain(|_, methods| !methods.is_empty());
+        self.invalidate_caches();
+    }
+
+    /*
+     * Removes the protocol lists being unloaded, e.g. those added by categories, from the chain.
+     */
+    pub fn remove_protocol_lists(&mut self, unloaded: impl Fn(&Ptr<ObjcProtocolList>) -> bool) {
+        let mut lists = Vec::new();
+        let mut current = self.protocols.take();
+        while let Some(list) = current {
+            current = list.next().cloned();
+            if !unloaded(&list) {
+                lists.push(list);
+            }
+        }
+        for mut list in lists.into_iter().rev() {
+            list.set_next(self.protocols.take());
+            self.protocols = Some(list);
+        }
+    }
+
+    /*
+     * Instances are counted, so that classes are not unloaded while they are alive.
+     */
+    pub fn add_instance(&self) {
+        if let Some(table) = self.dtable.as_ref() {
+            table.instances.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn remove_instance(&self) {
+        if let Some(table) = self.dtable.as_ref() {
+            let _ = table
+                .instances
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        }
+    }
+
+    pub fn live_instances(&self) -> usize {
         self.dtable
-            .as_mut()
-            .expect("dtable is not initialized")
-            .insert(selector, method)
+            .as_ref()
+            .map_or(0, |table| table.instances.load(Ordering::Relaxed))
     }
 
     fn defer_resolving_methods(&mut self, ctx: &mut Context) {
@@ -125,7 +579,7 @@ impl fmt::Display for ObjcClass {
             self
         )?;
         writeln!(f, " name: {},", self.name)?;
-        if self.is_class() {
+        if self.is_class() || self.is_isa_linked() {
             writeln!(
                 f,
                 " class: {} ({:p}),",
@@ -139,12 +593,9 @@ impl fmt::Display for ObjcClass {
             unreachable!()
         }
         {
-            match self.super_pointer.as_ref() {
-                Some(p) => {
-                    let s = unsafe { mem::transmute::<Ptr<ObjcClass>, StrPtr>(p.clone()) };
-                    writeln!(f, " super: {} ({:p}),", s, p.as_ptr())?
-                }
-                None => writeln!(f, " super: null,")?,
+            match (self.super_pointer.as_ref(), self.superclass_name()) {
+                (Some(p), Some(name)) => writeln!(f, " super: {} ({:p}),", name, p.as_ptr())?,
+                _ => writeln!(f, " super: null,")?,
             }
         }
         writeln!(
@@ -159,25 +610,31 @@ impl fmt::Display for ObjcClass {
                 .as_ref()
                 .map_or("null".to_string(), |ivars| format!("{}", ivars.as_ref()))
         )?;
+        /* Once the dispatch table is built, the method names are linked to selectors. */
+        match (self.methods.as_ref(), self.dtable.as_ref()) {
+            (None, _) => writeln!(f, " methods: null,")?,
+            (Some(methods), None) => writeln!(f, " methods: {},", methods.as_ref())?,
+            (Some(_), Some(_)) => writeln!(f, " methods: linked,")?,
+        }
         writeln!(
             f,
-            " methods: {},",
-            self.methods
+            " dtable: {},",
+            self.dtable
                 .as_ref()
-                .map_or("null".to_string(), |methods| format!(
-                    "{}",
-                    methods.as_ref()
+                .map_or("null".to_string(), |table| format!(
+                    "{} methods",
+                    table.methods.len()
                 ))
         )?;
-        writeln!(f, " dtable: disabled,")?;
         writeln!(
             f,
             " subclass_list: {},",
             self.subclass_list
                 .as_ref()
                 .map_or("null".to_string(), |subclass_list| format!(
-                    "{:p}",
-                    subclass_list
+                    "{} ({:p})",
+                    subclass_list.name,
+                    subclass_list.as_ptr()
                 ))
         )?;
         writeln!(
@@ -186,8 +643,9 @@ impl fmt::Display for ObjcClass {
             self.sibling_list
                 .as_ref()
                 .map_or("null".to_string(), |sibling_list| format!(
-                    "{:p}",
-                    sibling_list
+                    "{} ({:p})",
+                    sibling_list.name,
+                    sibling_list.as_ptr()
                 ))
         )?;
         writeln!(
@@ -200,6 +658,189 @@ impl fmt::Display for ObjcClass {
                     gc_object_type
                 ))
         )?;
+        if self.is_new_abi() {
+            writeln!(
+                f,
+                " abi_version: {}, ivar_offsets: {},",
+                self.abi_version,
+                self.ivar_offsets
+                    .as_ref()
+                    .map_or("null".to_string(), |ivar_offsets| format!(
+                        "{:p}",
+                        ivar_offsets
+                    ))
+            )?;
+            writeln!(
+                f,
+                " properties: {},",
+                self.properties
+                    .as_ref()
+                    .map_or("null".to_string(), |properties| format!(
+                        "{}",
+                        properties.as_ref()
+                    ))
+            )?;
+        }
         write!(f, "]")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ObjcClass;
+    use crate::api::{Class, Object, Sel};
+    use crate::context::CONTEXT;
+    use crate::method::{ObjcMethod, Procedure};
+    use crate::ptr::Ptr;
+    use crate::selector::ObjcSelector;
+    use crate::str_ptr::StrPtr;
+    use crate::testing;
+
+    extern "C" fn one(_this: *mut Object, _selector: Sel) -> i32 {
+        1
+    }
+
+    extern "C" fn two(_this: *mut Object, _selector: Sel) -> i32 {
+        2
+    }
+
+    #[test]
+    fn hierarchy() {
+        let one_sel = Sel::register("robjcHierarchyOne").unwrap();
+        let two_sel = Sel::register("robjcHierarchyTwo").unwrap();
+        let base = testing::declare("RobjcHierarchyBase", None, |decl| {
+            testing::add_method(decl, "robjcHierarchyOne", one as extern "C" fn(_, _) -> _);
+            testing::add_method(decl, "robjcHierarchyTwo", two as extern "C" fn(_, _) -> _);
+        });
+        let declare = |name, superclass| testing::declare(name, Some(superclass), |_| {});
+        let sub = declare("RobjcHierarchySub", "RobjcHierarchyBase");
+        let sub_sub = declare("RobjcHierarchySubSub", "RobjcHierarchySub");
+        let sibling = declare("RobjcHierarchySibling", "RobjcHierarchyBase");
+
+        assert_eq!(base.subclasses(), vec![sibling, sub]);
+        assert_eq!(base.all_subclasses(), vec![sibling, sub, sub_sub]);
+        assert_eq!(sub.subclasses(), vec![sub_sub]);
+        assert!(sub_sub.subclasses().is_empty());
+        assert_eq!(
+            base.metaclass().unwrap().subclasses(),
+            vec![sibling.metaclass().unwrap(), sub.metaclass().unwrap()]
+        );
+
+        let lookup = |class: Class, selector: Sel| {
+            let class = unsafe { Ptr::new(class.as_ptr()) };
+            let selector = unsafe { Ptr::new(selector.as_ptr()) };
+            class.resolve_method(selector).unwrap()
+        };
+        let one_method = lookup(base, one_sel);
+        let two_method = lookup(base, two_sel);
+        assert!(lookup(sub_sub, one_sel) == one_method);
+
+        /* Overriding in the middle of the hierarchy invalidates the cache of the subclass. */
+        {
+            let _ctx = CONTEXT.write().unwrap();
+            let mut sub = unsafe { Ptr::new(sub.as_ptr()) };
+            sub.register_method(unsafe { Ptr::new(one_sel.as_ptr()) }, two_method.clone());
+        }
+        assert!(lookup(sub_sub, one_sel) == two_method);
+        assert!(lookup(sibling, one_sel) == one_method);
+        assert!(lookup(base, one_sel) == one_method);
+    }
+
+    #[test]
+    fn resolved() {
+        let base = testing::declare("RobjcResolvedBase", None, |_| {});
+        let sub = testing::declare("RobjcResolvedSub", Some("RobjcResolvedBase"), |_| {});
+        for class in [base, sub] {
+            let class = unsafe { Ptr::new(class.as_ptr()) };
+            let meta_class = class.class_pointer();
+            assert!(class.is_resolved() && meta_class.is_resolved());
+            assert!(!class.is_isa_linked() && meta_class.is_isa_linked());
+        }
+        let sub = unsafe { Ptr::new(sub.as_ptr()) };
+        assert_eq!(
+            sub.superclass_name(),
+            Some(StrPtr::from_static(b"RobjcResolvedBase\0"))
+        );
+    }
+
+    #[test]
+    fn unregister() {
+        let one_sel = Sel::register("robjcUnregisterOne").unwrap();
+        let two_sel = Sel::register("robjcUnregisterTwo").unwrap();
+        let base = testing::declare("RobjcUnregisterBase", None, |decl| {
+            testing::add_method(decl, "robjcUnregisterOne", one as extern "C" fn(_, _) -> _);
+            testing::add_method(decl, "robjcUnregisterTwo", two as extern "C" fn(_, _) -> _);
+        });
+        let sub = testing::declare("RobjcUnregisterSub", Some("RobjcUnregisterBase"), |_| {});
+
+        let _ctx = CONTEXT.write().unwrap();
+        let mut base_ptr = unsafe { Ptr::new(base.as_ptr()) };
+        let sub_ptr = unsafe { Ptr::new(sub.as_ptr()) };
+        let one_name = unsafe { Ptr::new(one_sel.as_ptr()) };
+        let two_name = unsafe { Ptr::new(two_sel.as_ptr()) };
+        let one_method = base_ptr.lookup_method(&one_name).unwrap();
+        let two_method = base_ptr.lookup_method(&two_name).unwrap();
+
+        /* A category replacing a method, and unloading it. */
+        base_ptr.register_method(unsafe { Ptr::new(one_sel.as_ptr()) }, two_method.clone());
+        assert!(sub_ptr.lookup_method(&one_name) == Some(two_method.clone()));
+        base_ptr.unregister_methods(|method| method == &two_method);
+        assert!(sub_ptr.lookup_method(&one_name) == Some(one_method));
+        assert!(sub_ptr.lookup_method(&two_name).is_none());
+
+        unsafe { Ptr::new(sub.as_ptr()) }.unlink_from_superclass();
+        assert_eq!(base_ptr.iter_subclass().count(), 0);
+    }
+
+    /*
+     * Classes which are not loaded yet, e.g. a class pair messaged before it is registered, do
+     * not find methods instead of panicking.
+     */
+    #[test]
+    fn uninitialized() {
+        let class = ObjcClass::new_pair(
+            StrPtr::from_static(b"RobjcUninitialized\0"),
+            Some(StrPtr::from_static(b"RobjcUninitializedBase\0")),
+            StrPtr::from_static(b"RobjcUninitializedBase\0"),
+            0,
+            None,
+            None,
+            None,
+            None,
+        );
+        let selector = Sel::register("robjcUninitialized").unwrap();
+        assert!(class
+            .lookup_method(unsafe { &*selector.as_ptr() })
+            .is_none());
+
+        /* The superclass is still a name, which tracing does not follow. */
+        let method = ObjcMethod::new(
+            StrPtr::from_static(b"robjcUninitialized\0"),
+            StrPtr::null(),
+            Procedure::new_null_procedure(),
+        );
+        assert!(class.method_owner(&unsafe { Ptr::new(&method) }).is_none());
+    }
+
+    /*
+     * A module's own selector finds the methods registered with the selector of the same name
+     * once it is given the registered name, as loading the module does.
+     */
+    #[test]
+    fn selector_key() {
+        let class = testing::declare("RobjcSelectorKey", None, |decl| {
+            testing::add_method(decl, "robjcSelectorKey", one as extern "C" fn(_, _) -> _);
+        });
+        let class = unsafe { Ptr::new(class.as_ptr()) };
+
+        let mut selector =
+            ObjcSelector::new(StrPtr::from_static(b"robjcSelectorKey\0"), StrPtr::null());
+        assert!(class.lookup_method(&selector).is_none());
+        let registered = CONTEXT
+            .write()
+            .unwrap()
+            .register_selector(selector.get_id(), selector.get_types());
+        selector.set_id(registered.get_id().clone());
+        assert!(class.lookup_method(&selector).is_some());
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ * NXConstantString, the class of string literals compiled for the GNU runtime.  Literals are
+ * emitted as static instances of the class named by -fconstant-string-class=, which is this
+ * class by default, and get their class pointer when the class is loaded.  A Foundation may
+ * register its own NXConstantString, which replaces this one for the literals loaded after it.
+ */
+
+#[cfg(feature = "root-object")]
+use std::mem;
+use std::os::raw;
+#[cfg(feature = "root-object")]
+use std::ptr::{self, NonNull};
+
+#[cfg(feature = "root-object")]
+use super::api::Class;
+use super::api::{Object, Sel};
+use super::context::Context;
+use super::declare::ClassDecl;
+
+pub const CLASS_NAME: &str = "NXConstantString";
+
+#[repr(C)]
+struct ConstantString {
+    class_pointer: *const Object,
+    c_string: *const raw::c_char,
+    len: raw::c_uint,
+}
+
+extern "C" fn c_string(zelf: *mut Object, _cmd: Sel) -> *const raw::c_char {
+    unsafe { (*(zelf as *const ConstantString)).c_string }
+}
+
+extern "C" fn length(zelf: *mut Object, _cmd: Sel) -> raw::c_uint {
+    unsafe { (*(zelf as *const ConstantString)).len }
+}
+
+pub fn register(ctx: &mut Context) {
+    let mut decl = ClassDecl::with_superclass(CLASS_NAME, None).unwrap();
+    decl.add_ivar::<*const raw::c_char>("c_string");
+    decl.add_ivar::<raw::c_uint>("len");
+    let f: extern "C" fn(*mut Object, Sel) -> *const raw::c_char = c_string;
+    decl.add_method(Sel::from_ptr(ctx.register_selector_name("cString")), f);
+    let f: extern "C" fn(*mut Object, Sel) -> raw::c_uint = length;
+    decl.add_method(Sel::from_ptr(ctx.register_selector_name("length")), f);
+    decl.register_in(ctx);
+}
+
+/*
+ * The offsets of the characters and the length in instances of the class.  A Foundation may
+ * register its own NXConstantString in place of the built-in one, so they are looked up in the
+ * ivars: the first ones must be a char * and an unsigned int following the isa, as compilers lay
+ * out string literals.
+ */
+#[cfg(feature = "root-object")]
+fn layout(class: Class) -> Option<(usize, usize)> {
+    let mut ivars: Vec<_> = std::iter::successors(Some(class), |class| class.superclass())
+        .flat_map(|class| class.ivars())
+        .collect();
+    ivars.sort_by_key(|ivar| ivar.offset());
+    let pointer_size = mem::size_of::<*const Object>() as isize;
+    match &ivars[..] {
+        [c_string, len, ..]
+            if c_string.type_encoding() == "*"
+                && c_string.offset() == pointer_size
+                && len.type_encoding() == "I"
+                && len.offset() == 2 * pointer_size =>
+        {
+            Some((c_string.offset() as usize, len.offset() as usize))
+        }
+        _ => None,
+    }
+}
+
+/*
+ * Creates a string holding a copy of the text in the extra bytes of the instance, for strings
+ * made at run time.  It is not a static instance, so it is deallocated by its last release.
+ * Returns None if the registered NXConstantString is not laid out as string literals are.
+ */
+#[cfg(feature = "root-object")]
+pub fn create(text: &str) -> Option<NonNull<Object>> {
+    create_with(Class::named(CLASS_NAME)?, text)
+}
+
+#[cfg(feature = "root-object")]
+fn create_with(class: Class, text: &str) -> Option<NonNull<Object>> {
+    let (c_string_offset, len_offset) = layout(class)?;
+    let len = text.len();
+    let size = class.instance_size();
+    let class = super::Class(super::NilablePtr::new(unsafe {
+        super::Ptr::new(class.as_ptr())
+    }));
+    let object = super::runtime::class_createInstance(class, len + 1);
+    let object = object.0.as_ref()?;
+    unsafe {
+        let string = object.as_ptr() as *mut u8;
+        let c_string = string.add(size);
+        ptr::copy_nonoverlapping(text.as_ptr(), c_string, len);
+        *(string.add(c_string_offset) as *mut *const raw::c_char) = c_string as *const raw::c_char;
+        *(string.add(len_offset) as *mut raw::c_uint) = len as raw::c_uint;
+    }
+    Some(NonNull::from(object.as_ref()).cast())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::os::raw;
+
+    use super::{ConstantString, CLASS_NAME};
+    use crate::api::{Class, Object};
+    use crate::msg_send;
+
+    #[test]
+    fn constant_string() {
+        let class = Class::named(CLASS_NAME).unwrap();
+        let mut string = ConstantString {
+            class_pointer: class.as_ptr() as *const Object,
+            c_string: b"literal\0".as_ptr() as *const raw::c_char,
+            len: 7,
+        };
+        let object = &mut string as *mut ConstantString as *mut Object;
+        unsafe {
+            let c_string: *const raw::c_char = msg_send![object, cString];
+            assert_eq!(CStr::from_ptr(c_string).to_str(), Ok("literal"));
+            let length: raw::c_uint = msg_send![object, length];
+            assert_eq!(length, 7);
+        }
+    }
+
+    #[cfg(feature = "root-object")]
+    #[test]
+    fn foundation_layout() {
+        use super::{create_with, layout};
+        use crate::declare::ClassDecl;
+
+        let pointer_size = std::mem::size_of::<*const Object>();
+        let class = Class::named(CLASS_NAME).unwrap();
+        assert_eq!(layout(class), Some((pointer_size, 2 * pointer_size)));
+
+        /* A Foundation's string class with the layout of literals, and one without. */
+        let mut decl = ClassDecl::new("RobjcFoundationString", CLASS_NAME).unwrap();
+        decl.add_ivar::<usize>("hash");
+        let subclass = decl.register().unwrap();
+        assert_eq!(layout(subclass), Some((pointer_size, 2 * pointer_size)));
+        let string = create_with(subclass, "text").unwrap().as_ptr();
+        unsafe {
+            let c_string: *const raw::c_char = msg_send![string, cString];
+            assert_eq!(CStr::from_ptr(c_string).to_str(), Ok("text"));
+            let length: raw::c_uint = msg_send![string, length];
+            assert_eq!(length, 4);
+        }
+
+        let mut decl = ClassDecl::root("RobjcReversedString").unwrap();
+        decl.add_ivar::<raw::c_uint>("len");
+        decl.add_ivar::<*const raw::c_char>("c_string");
+        let reversed = decl.register().unwrap();
+        assert_eq!(layout(reversed), None);
+        assert!(create_with(reversed, "text").is_none());
+    }
+}
@@ -1,23 +1,39 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString};
 use std::mem;
 use std::sync;
 
+use super::api;
+use super::arc;
+use super::autorelease;
+use super::blocks;
 use super::category::ObjcCategory;
 use super::class::ObjcClass;
+use super::constant_string;
+use super::image::Image;
+use super::load::Loaded;
 use super::method::ObjcMethod;
-use super::module::ObjcModule;
+use super::module::{ObjcModule, ObjcStaticInstances};
+use super::property::{ObjcProperty, ObjcPropertyList};
+use super::protocol::{ObjcProtocol, ObjcProtocolList};
 use super::ptr::Ptr;
 use super::selector::ObjcSelector;
 use super::str_ptr::StrPtr;
+use super::trace;
 
 pub struct ClassTableEntry {
     class: Ptr<ObjcClass>,
     meta_class: Ptr<ObjcClass>,
+    property_lists: Vec<Ptr<ObjcPropertyList>>,
 }
 
 impl ClassTableEntry {
     fn new(class: Ptr<ObjcClass>, meta_class: Ptr<ObjcClass>) -> ClassTableEntry {
-        ClassTableEntry { class, meta_class }
+        ClassTableEntry {
+            class,
+            meta_class,
+            property_lists: Vec::new(),
+        }
     }
 
     pub fn class(&self) -> &Ptr<ObjcClass> {
@@ -27,22 +43,44 @@ impl ClassTableEntry {
     pub fn meta_class(&self) -> &Ptr<ObjcClass> {
         &self.meta_class
     }
+
+    pub fn iter_property(&self) -> impl Iterator<Item = Ptr<ObjcProperty>> + '_ {
+        self.property_lists.iter().flat_map(|list| list.iter())
+    }
 }
 
 pub struct Context {
     class_table: HashMap<StrPtr, ClassTableEntry>,
+    selector_table: HashMap<StrPtr, Ptr<ObjcSelector>>,
+    property_attributes: HashMap<Ptr<ObjcProperty>, CString>,
+    protocol_table: HashMap<StrPtr, Ptr<ObjcProtocol>>,
+    modules: Vec<(Image, Ptr<ObjcModule>)>,
     orphan_classes: Vec<Ptr<ObjcClass>>,
+    loading_classes: Vec<Ptr<ObjcClass>>,
+    loaded: Loaded,
+    recording: bool,
+    unresolved_meta_classes: Vec<Ptr<ObjcClass>>,
     unresolved_methods: Vec<(Ptr<ObjcClass>, Ptr<ObjcMethod>)>,
-    _unresolved_categories: Vec<Ptr<ObjcCategory>>,
+    unresolved_static_instances: Vec<Ptr<ObjcStaticInstances>>,
+    unresolved_categories: Vec<(Ptr<ObjcCategory>, bool)>,
 }
 
 impl Context {
     fn new() -> Context {
         Context {
             class_table: HashMap::new(),
+            selector_table: HashMap::new(),
+            property_attributes: HashMap::new(),
+            protocol_table: HashMap::new(),
+            modules: Vec::new(),
             orphan_classes: Vec::new(),
+            loading_classes: Vec::new(),
+            loaded: Loaded::default(),
+            recording: false,
+            unresolved_meta_classes: Vec::new(),
             unresolved_methods: Vec::new(),
-            _unresolved_categories: Vec::new(),
+            unresolved_static_instances: Vec::new(),
+            unresolved_categories: Vec::new(),
         }
     }
 
@@ -50,6 +88,37 @@ impl Context {
         self.class_table.get(name)
     }
 
+    pub fn iter_class_entry(&self) -> impl Iterator<Item = &ClassTableEntry> {
+        self.class_table.values()
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &Ptr<ObjcModule>> {
+        self.modules.iter().map(|(_, module)| module)
+    }
+
+    /*
+     * Classes waiting for their superclasses to be loaded.
+     */
+    pub fn orphan_classes(&self) -> &[Ptr<ObjcClass>] {
+        &self.orphan_classes
+    }
+
+    /*
+     * Methods waiting for their selectors to be linked, with the classes they are added to.
+     */
+    pub fn unresolved_methods(&self) -> &[(Ptr<ObjcClass>, Ptr<ObjcMethod>)] {
+        &self.unresolved_methods
+    }
+
+    /*
+     * Categories waiting for the classes they extend to be loaded.
+     */
+    pub fn unresolved_categories(&self) -> impl Iterator<Item = &Ptr<ObjcCategory>> {
+        self.unresolved_categories
+            .iter()
+            .map(|(category, _)| category)
+    }
+
     fn register_class_pair(&mut self, class: Ptr<ObjcClass>) {
         assert!(class.is_class());
         let meta_class = class.class_pointer().clone();
@@ -58,6 +127,88 @@ impl Context {
         self.class_table.insert(name, entry);
     }
 
+    pub fn get_selector(&self, name: &StrPtr) -> Option<&Ptr<ObjcSelector>> {
+        self.selector_table.get(name)
+    }
+
+    /*
+     * Selectors are registered once per name, with a copy of the name which is never freed, so
+     * that the address of the name identifies them.  The types must outlive the selector, i.e.
+     * they point to module data or leaked strings, and are copied if their image is unloaded.
+     */
+    pub fn register_selector(&mut self, name: &StrPtr, types: &StrPtr) -> Ptr<ObjcSelector> {
+        if let Some(selector) = self.selector_table.get(name) {
+            return selector.clone();
+        }
+        let name = StrPtr::leak(CString::new(name.as_ref().unwrap_or("")).unwrap());
+        let selector = Box::new(ObjcSelector::new(name.clone(), types.clone()));
+        let selector = unsafe { Ptr::new(Box::into_raw(selector)) };
+        self.selector_table.insert(name, selector.clone());
+        if self.recording {
+            self.loaded
+                .selectors
+                .push(api::Sel::from_ptr(selector.clone()));
+        }
+        selector
+    }
+
+    pub fn register_selector_name(&mut self, name: &str) -> Ptr<ObjcSelector> {
+        let name = CString::new(name).expect("selector name contains a nul byte");
+        self.register_selector(&unsafe { StrPtr::from_ptr(name.as_ptr()) }, &StrPtr::null())
+    }
+
+    /*
+     * The selectors of the module are given the names of the registered selectors, so that
+     * messages sent with them find the methods registered with any selector of the same name.
+     */
+    fn register_module_selectors(&mut self, module: &ObjcModule) {
+        for mut selector in module.symtab().iter_selector() {
+            let registered = self.register_selector(selector.get_id(), selector.get_types());
+            selector.set_id(registered.get_id().clone());
+        }
+    }
+
+    pub fn get_property_attributes(&self, property: &Ptr<ObjcProperty>) -> Option<&CStr> {
+        self.property_attributes
+            .get(property)
+            .map(|attributes| attributes.as_c_str())
+    }
+
+    /*
+     * Attribute strings are built once here, so that property_getAttributes can hand out
+     * pointers which stay valid.
+     */
+    fn register_property_list(&mut self, class_name: &StrPtr, list: Ptr<ObjcPropertyList>) {
+        for property in list.iter() {
+            let attributes = CString::new(property.attributes()).unwrap();
+            self.property_attributes.insert(property, attributes);
+        }
+        if let Some(entry) = self.class_table.get_mut(class_name) {
+            entry.property_lists.push(list);
+        }
+    }
+
+    pub fn get_protocol(&self, name: &StrPtr) -> Option<&Ptr<ObjcProtocol>> {
+        self.protocol_table.get(name)
+    }
+
+    /*
+     * Protocols are not listed in modules, so they are collected from the classes and the
+     * categories adopting them.  The first definition of a name wins.
+     */
+    fn register_protocol_list(&mut self, list: &ObjcProtocolList) {
+        for protocol in list.iter() {
+            if self.protocol_table.contains_key(protocol.name()) {
+                continue;
+            }
+            self.protocol_table
+                .insert(protocol.name().clone(), protocol.clone());
+            if let Some(inherited) = protocol.protocols() {
+                self.register_protocol_list(inherited);
+            }
+        }
+    }
+
     pub fn append_unresolved_methods(&mut self, class: Ptr<ObjcClass>, method: Ptr<ObjcMethod>) {
         self.unresolved_methods.push((class, method));
     }
@@ -80,63 +231,366 @@ impl Context {
         }
     }
 
-    fn link_selectors_to_methods(&mut self, module: &mut ObjcModule) {
-        let selector_map = module.symtab().create_selector_map();
-        let mut num_unresolved_methods = self.unresolved_methods.len();
-        loop {
-            let mut unresolved_methods = Vec::new();
-            mem::swap(&mut self.unresolved_methods, &mut unresolved_methods);
-
-            for (mut class, mut method) in unresolved_methods {
-                let name =
-                    unsafe { mem::transmute::<Ptr<ObjcSelector>, StrPtr>(method.name().clone()) };
-                let _types = method.types().clone();
-                /*
-                 * // ToDo: currently, ignore types
-                 * if let Some(selector) = selector_map.get(&(name, _types)) {
-                 */
-                if let Some(selector) = selector_map.get(&name) {
-                    unsafe {
-                        method.link_to_selector(selector.clone());
-                    }
-                    class.register_method(selector.clone(), method);
-                } else {
-                    self.unresolved_methods.push((class, method))
-                }
+    /*
+     * Classes are reported as loaded once they and their metaclasses have their superclasses.
+     */
+    fn collect_loaded_classes(&mut self) {
+        let mut loading_classes = Vec::new();
+        mem::swap(&mut self.loading_classes, &mut loading_classes);
+        for class in loading_classes {
+            if self.orphan_classes.contains(&class)
+                || self.orphan_classes.contains(class.class_pointer())
+            {
+                self.loading_classes.push(class);
+            } else if self.recording {
+                self.loaded.classes.push(api::Class::from_ptr(class));
             }
+        }
+    }
 
-            let new_num_unresolved_methods = self.unresolved_methods.len();
-            if new_num_unresolved_methods == num_unresolved_methods {
-                break;
+    /*
+     * Starts recording what a module or a declared class adds.  Selectors registered by
+     * messages, and the classes the runtime registers itself, are not reported.
+     */
+    pub(crate) fn start_loading(&mut self) {
+        self.loaded = Loaded::default();
+        self.recording = true;
+    }
+
+    /*
+     * Takes what has been loaded since start_loading, to be notified after the context is
+     * unlocked.
+     */
+    pub(crate) fn take_loaded(&mut self, module_name: StrPtr) -> Loaded {
+        self.recording = false;
+        let mut loaded = mem::take(&mut self.loaded);
+        loaded.module_name = module_name;
+        loaded
+    }
+
+    /*
+     * Metaclasses of subclasses may be loaded before their root class.
+     */
+    fn resolve_meta_classes(&mut self) {
+        let mut unresolved_meta_classes = Vec::new();
+        mem::swap(
+            &mut self.unresolved_meta_classes,
+            &mut unresolved_meta_classes,
+        );
+        for mut meta_class in unresolved_meta_classes {
+            if !meta_class.initialize_class_pointer(self) {
+                self.unresolved_meta_classes.push(meta_class);
             }
-            num_unresolved_methods = new_num_unresolved_methods;
         }
     }
 
-    pub fn load_module(&mut self, module: &mut ObjcModule) {
-        let symtab = module.symtab_mut();
-        for mut class in symtab.iter_class() {
-            class.initialize(self);
-            if !class.initialize_super_pointer(self) {
-                self.orphan_classes.push(class.clone());
+    fn link_selectors_to_methods(&mut self) {
+        let mut unresolved_methods = Vec::new();
+        mem::swap(&mut self.unresolved_methods, &mut unresolved_methods);
+
+        let mut classes: Vec<Ptr<ObjcClass>> = Vec::new();
+        let mut added = HashSet::new();
+        for (mut class, mut method) in unresolved_methods {
+            let name =
+                unsafe { mem::transmute::<Ptr<ObjcSelector>, StrPtr>(method.name().clone()) };
+            // ToDo: currently, ignore types
+            let selector = self.register_selector(&name, method.types());
+            unsafe {
+                method.link_to_selector(selector.clone());
+            }
+            class.add_method(selector, method);
+            if added.insert(class.as_ptr()) {
+                classes.push(class);
+            }
+        }
+
+        /* The caches are cleared once per class, and not for classes whose superclasses are
+         * cleared with their subclasses. */
+        for class in &classes {
+            let mut ancestor = class
+                .super_pointer()
+                .as_ref()
+                .filter(|_| class.is_resolved());
+            let mut covered = false;
+            while let Some(super_class) = ancestor {
+                if added.contains(&super_class.as_ptr()) {
+                    covered = true;
+                    break;
+                }
+                ancestor = super_class
+                    .super_pointer()
+                    .as_ref()
+                    .filter(|_| super_class.is_resolved());
+            }
+            if !covered {
+                class.invalidate_caches();
             }
+        }
+    }
 
-            let meta_class = class.class_pointer_mut();
-            meta_class.initialize(self);
-            if !meta_class.initialize_super_pointer(self) {
-                self.orphan_classes.push(meta_class.clone());
+    /*
+     * Static instances get their class when the class of the name is loaded, which may be in a
+     * later module.
+     */
+    fn resolve_static_instances(&mut self) {
+        let mut unresolved_static_instances = Vec::new();
+        mem::swap(
+            &mut self.unresolved_static_instances,
+            &mut unresolved_static_instances,
+        );
+        for statics in unresolved_static_instances {
+            match self.class_table.get(statics.class_name()) {
+                Some(entry) => {
+                    for mut instance in statics.iter_instance() {
+                        instance.initialize(entry.class().clone());
+                        arc::make_immortal(&instance);
+                    }
+                }
+                None => self.unresolved_static_instances.push(statics),
             }
+        }
+    }
+
+    fn load_class(&mut self, mut class: Ptr<ObjcClass>) {
+        class.initialize(self);
+        if !class.initialize_super_pointer(self) {
+            self.orphan_classes.push(class.clone());
+        }
+
+        let meta_class = class.class_pointer_mut();
+        meta_class.initialize(self);
+        if !meta_class.initialize_super_pointer(self) {
+            self.orphan_classes.push(meta_class.clone());
+        }
+
+        self.register_class_pair(class.clone());
+        self.loading_classes.push(class.clone());
+        let mut meta_class = class.class_pointer().clone();
+        if !meta_class.initialize_class_pointer(self) {
+            self.unresolved_meta_classes.push(meta_class);
+        }
+        if let Some(properties) = class.properties() {
+            self.register_property_list(class.get_name(), properties.clone());
+        }
+        if let Some(protocols) = class.protocols() {
+            self.register_protocol_list(protocols);
+        }
+    }
+
+    /*
+     * Loads a class pair created at run time, which is in the same form as compiled classes.
+     */
+    pub fn load_class_pair(&mut self, class: Ptr<ObjcClass>) {
+        self.load_class(class);
+        self.resolve_categories();
+        self.resolve_orphan_classes();
+        self.collect_loaded_classes();
+        self.resolve_meta_classes();
+        self.link_selectors_to_methods();
+        self.resolve_static_instances();
+        autorelease::resolve_pool_class(self);
+    }
 
-            self.register_class_pair(class.clone());
+    pub fn load_module(&mut self, module: &mut ObjcModule) {
+        if let Some(image) = Image::of(module as *const ObjcModule) {
+            self.modules.push((image, unsafe { Ptr::new(module) }));
+        }
+        self.register_module_selectors(module);
+
+        let has_properties = module.has_category_properties();
+        let symtab = module.symtab_mut();
+        for class in symtab.iter_class() {
+            self.load_class(class);
         }
 
+        self.resolve_categories();
+
         for mut category in symtab.iter_category() {
             category.as_mut().initialize(self);
-            category.as_mut().defer_resolving_methods(self);
+            if let Some(protocols) = category.protocols() {
+                self.register_protocol_list(protocols);
+            }
+            if !self.attach_category(&category, has_properties) {
+                self.unresolved_categories.push((category, has_properties));
+            }
         }
 
+        self.unresolved_static_instances
+            .extend(module.symtab().iter_static_instances());
+
         self.resolve_orphan_classes();
-        self.link_selectors_to_methods(module);
+        self.collect_loaded_classes();
+        self.resolve_meta_classes();
+        self.link_selectors_to_methods();
+        self.resolve_static_instances();
+        autorelease::resolve_pool_class(self);
+    }
+
+    /*
+     * Adds the methods, protocols and properties of a category to its class.  Returns false if
+     * the class is not loaded yet.
+     */
+    fn attach_category(&mut self, category: &Ptr<ObjcCategory>, has_properties: bool) -> bool {
+        if !category.defer_resolving_methods(self) {
+            return false;
+        }
+        let mut class = self.class_table[category.class_name()].class().clone();
+        if let Some(protocols) = category.protocols() {
+            class.add_protocols(protocols.clone());
+        }
+        if has_properties {
+            if let Some(properties) = category.properties() {
+                self.register_property_list(category.class_name(), properties.clone());
+            }
+        }
+        if self.recording {
+            self.loaded
+                .categories
+                .push((api::Class::from_ptr(class), category.as_ptr()));
+        }
+        true
+    }
+
+    /*
+     * Categories are attached when their classes are loaded, which may be in a later module or
+     * declared at run time.
+     */
+    fn resolve_categories(&mut self) {
+        let mut unresolved_categories = Vec::new();
+        mem::swap(&mut self.unresolved_categories, &mut unresolved_categories);
+        for (category, has_properties) in unresolved_categories {
+            if !self.attach_category(&category, has_properties) {
+                self.unresolved_categories.push((category, has_properties));
+            }
+        }
+    }
+
+    fn classes_of(&self, image: Image) -> Vec<Ptr<ObjcClass>> {
+        self.modules
+            .iter()
+            .filter(|(i, _)| *i == image)
+            .flat_map(|(_, module)| module.symtab().iter_class())
+            .collect()
+    }
+
+    /*
+     * Lists the reasons why the modules of the image cannot be unloaded safely.
+     */
+    pub fn check_unloading(&self, image: Image) -> Vec<String> {
+        let mut problems = Vec::new();
+        for class in self.classes_of(image) {
+            let instances = class.live_instances();
+            if instances > 0 {
+                problems.push(format!(
+                    "{} has {} known live instances",
+                    class.get_name(),
+                    instances
+                ));
+            }
+            for subclass in class.iter_subclass() {
+                if !image.contains(subclass.as_ptr()) {
+                    problems.push(format!(
+                        "{} is subclassed by {} in another image",
+                        class.get_name(),
+                        subclass.get_name()
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /*
+     * Removes the classes, category methods, selectors, properties and protocols of the modules
+     * of the image, while the image is still mapped.  Methods replaced by the categories are
+     * restored.  Returns the number of modules unloaded.
+     */
+    pub fn unload_image(&mut self, image: Image) -> usize {
+        let count = self.modules.iter().filter(|(i, _)| *i == image).count();
+        if count == 0 {
+            return 0;
+        }
+        let unloaded = |p: *const u8| image.contains(p);
+
+        for class in self.classes_of(image) {
+            if self
+                .class_table
+                .get(class.get_name())
+                .is_some_and(|entry| entry.class == class)
+            {
+                self.class_table.remove(class.get_name());
+            }
+            for mut class in [class.clone(), class.class_pointer().clone()] {
+                if class.is_resolved() && class.super_pointer().is_some() {
+                    class.unlink_from_superclass();
+                }
+            }
+        }
+        self.orphan_classes
+            .retain(|class| !unloaded(class.as_ptr() as *const u8));
+        self.unresolved_meta_classes
+            .retain(|class| !unloaded(class.as_ptr() as *const u8));
+        self.unresolved_methods.retain(|(class, method)| {
+            !unloaded(class.as_ptr() as *const u8) && !unloaded(method.as_ptr() as *const u8)
+        });
+        self.unresolved_static_instances
+            .retain(|statics| !unloaded(statics.as_ptr() as *const u8));
+        arc::forget(|address| unloaded(address as *const u8));
+        self.loading_classes
+            .retain(|class| !unloaded(class.as_ptr() as *const u8));
+        self.unresolved_categories
+            .retain(|(category, _)| !unloaded(category.as_ptr() as *const u8));
+        self.loaded
+            .classes
+            .retain(|class| !unloaded(class.as_ptr() as *const u8));
+        self.loaded
+            .categories
+            .retain(|(_, category)| !unloaded(*category as *const u8));
+        self.loaded
+            .selectors
+            .retain(|selector| !unloaded(selector.as_ptr() as *const u8));
+
+        /*
+         * Registered selectors and their names are owned by the runtime, but their types may be
+         * in the image.
+         */
+        for selector in self.selector_table.values_mut() {
+            if unloaded(selector.get_types().as_ptr() as *const u8) {
+                let types = CString::new(selector.get_types().as_ref().unwrap_or("")).unwrap();
+                selector.set_types(StrPtr::leak(types));
+            }
+        }
+
+        for entry in self.class_table.values_mut() {
+            for mut class in [entry.class.clone(), entry.meta_class.clone()] {
+                class.unregister_methods(|method| unloaded(method.as_ptr() as *const u8));
+                class.remove_protocol_lists(|list| unloaded(list.as_ptr() as *const u8));
+            }
+            entry
+                .property_lists
+                .retain(|list| !unloaded(list.as_ptr() as *const u8));
+        }
+        self.property_attributes
+            .retain(|property, _| !unloaded(property.as_ptr() as *const u8));
+
+        /*
+         * Protocols are registered again from the remaining classes, which may have their own
+         * copies of the protocols removed.
+         */
+        self.protocol_table
+            .retain(|_, protocol| !unloaded(protocol.as_ptr() as *const u8));
+        let lists: Vec<_> = self
+            .class_table
+            .values()
+            .filter_map(|entry| entry.class.protocols().cloned())
+            .collect();
+        for list in lists {
+            self.register_protocol_list(&list);
+        }
+
+        self.modules.retain(|(i, _)| *i != image);
+        autorelease::resolve_pool_class(self);
+        count
     }
 }
 
@@ -144,5 +598,15 @@ unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
 lazy_static! {
-    pub static ref CONTEXT: sync::RwLock<Context> = sync::RwLock::new(Context::new());
+    pub static ref CONTEXT: sync::RwLock<Context> = {
+        trace::configure_from_env();
+        #[cfg(feature = "stats")]
+        super::stats::register_report();
+        let mut ctx = Context::new();
+        constant_string::register(&mut ctx);
+        blocks::register(&mut ctx);
+        #[cfg(feature = "root-object")]
+        super::root_object::register(&mut ctx);
+        sync::RwLock::new(ctx)
+    };
 }
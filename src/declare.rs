@@ -0,0 +1,379 @@
+/*
+ * Declaring classes from Rust.  A ClassDecl collects ivars, methods and protocols, and
+ * registering it creates a class pair in the form the compiler emits, which is loaded like the
+ * classes of a module.  Objective-C code can then look up and subclass the class by name.
+ */
+
+use std::ffi::CString;
+use std::mem;
+use std::os::raw;
+
+use super::api::{Class, Imp, Object, Protocol, Sel};
+use super::class::ObjcClass;
+use super::context::{Context, CONTEXT};
+use super::encoding;
+use super::ivar::{ObjcIvar, ObjcIvarList};
+use super::load;
+use super::method::{ObjcMethod, ObjcMethodList, Procedure};
+use super::protocol::ObjcProtocolList;
+use super::ptr::Ptr;
+use super::send::Encode;
+use super::str_ptr::StrPtr;
+
+/*
+ * Functions which can be used as method implementations, with the encodings of their return
+ * type and arguments following the receiver and the selector.
+ */
+pub trait MethodImplementation {
+    fn return_encoding() -> &'static str;
+
+    fn argument_encodings() -> Vec<&'static str>;
+
+    fn imp(self) -> Imp;
+}
+
+macro_rules! method_implementation_impls {
+    ($($t:ident),*) => {
+        impl<R: Encode $(, $t: Encode)*> MethodImplementation
+            for extern "C" fn(*mut Object, Sel $(, $t)*) -> R
+        {
+            fn return_encoding() -> &'static str {
+                R::ENCODING
+            }
+
+            fn argument_encodings() -> Vec<&'static str> {
+                vec![$($t::ENCODING),*]
+            }
+
+            fn imp(self) -> Imp {
+                unsafe { mem::transmute::<Self, Imp>(self) }
+            }
+        }
+
+        impl<R: Encode $(, $t: Encode)*> MethodImplementation
+            for unsafe extern "C" fn(*mut Object, Sel $(, $t)*) -> R
+        {
+            fn return_encoding() -> &'static str {
+                R::ENCODING
+            }
+
+            fn argument_encodings() -> Vec<&'static str> {
+                vec![$($t::ENCODING),*]
+            }
+
+            fn imp(self) -> Imp {
+                unsafe { mem::transmute::<Self, Imp>(self) }
+            }
+        }
+    };
+}
+
+method_implementation_impls!();
+method_implementation_impls!(A);
+method_implementation_impls!(A, B);
+method_implementation_impls!(A, B, C);
+method_implementation_impls!(A, B, C, D);
+method_implementation_impls!(A, B, C, D, E);
+method_implementation_impls!(A, B, C, D, E, F);
+method_implementation_impls!(A, B, C, D, E, F, G);
+method_implementation_impls!(A, B, C, D, E, F, G, H);
+
+/*
+ * Builds method types in the form GCC emits, e.g. i20@0:8i16, where each argument is followed
+ * by its offset in the argument frame and the return type by the size of the frame.
+ */
+fn method_types<F: MethodImplementation>() -> String {
+    let mut arguments = vec![<*mut Object>::ENCODING, Sel::ENCODING];
+    arguments.extend(F::argument_encodings());
+    let mut offset = 0;
+    let mut frame = String::new();
+    for argument in arguments {
+        let size = encoding::sizeof_type(argument.as_bytes())
+            .unwrap_or_else(|| panic!("unknown type encoding {}", argument));
+        frame.push_str(&format!("{}{}", argument, offset));
+        offset += size.next_multiple_of(mem::size_of::<raw::c_int>());
+    }
+    format!("{}{}{}", F::return_encoding(), offset, frame)
+}
+
+struct MethodDecl {
+    name: CString,
+    types: CString,
+    imp: Imp,
+}
+
+impl MethodDecl {
+    fn into_method(self) -> ObjcMethod {
+        let imp = unsafe { Ptr::new(self.imp as *const Procedure) };
+        ObjcMethod::new(StrPtr::leak(self.name), StrPtr::leak(self.types), imp)
+    }
+}
+
+fn into_method_list(methods: Vec<MethodDecl>) -> Option<Ptr<ObjcMethodList>> {
+    if methods.is_empty() {
+        return None;
+    }
+    Some(ObjcMethodList::new(
+        methods.into_iter().map(MethodDecl::into_method).collect(),
+    ))
+}
+
+pub struct ClassDecl {
+    name: CString,
+    superclass: Option<Class>,
+    instance_size: usize,
+    ivars: Vec<(CString, CString, usize)>,
+    methods: Vec<MethodDecl>,
+    class_methods: Vec<MethodDecl>,
+    protocols: Vec<Protocol>,
+    storage: Option<*mut ObjcClass>,
+}
+
+/*
+ * The storage is only set by the runtime, to statics which live as long as the process.
+ */
+unsafe impl Send for ClassDecl {}
+
+impl ClassDecl {
+    /*
+     * Does not look up the context, so that built-in classes can be declared while the context
+     * is initialized.
+     */
+    pub(crate) fn with_superclass(name: &str, superclass: Option<Class>) -> Option<ClassDecl> {
+        let name = CString::new(name).ok()?;
+        Some(ClassDecl {
+            name,
+            superclass,
+            instance_size: superclass.map_or(mem::size_of::<*const Object>(), |superclass| {
+                superclass.instance_size()
+            }),
+            ivars: Vec::new(),
+            methods: Vec::new(),
+            class_methods: Vec::new(),
+            protocols: Vec::new(),
+            storage: None,
+        })
+    }
+
+    /*
+     * Places the class in the given storage instead of allocating it, for classes whose
+     * symbols compiled code uses as class pointers.
+     */
+    pub(crate) unsafe fn place_at(&mut self, storage: *mut ObjcClass) {
+        self.storage = Some(storage);
+    }
+
+    /*
+     * Returns None if the class already exists or the superclass is not loaded.
+     */
+    pub fn new(name: &str, superclass: &str) -> Option<ClassDecl> {
+        if Class::named(name).is_some() {
+            return None;
+        }
+        ClassDecl::with_superclass(name, Some(Class::named(superclass)?))
+    }
+
+    /*
+     * Declares a root class, whose instances only have the isa pointer unless ivars are added.
+     */
+    pub fn root(name: &str) -> Option<ClassDecl> {
+        if Class::named(name).is_some() {
+            return None;
+        }
+        ClassDecl::with_superclass(name, None)
+    }
+
+    /*
+     * Returns false if an ivar of the name is already added.
+     */
+    pub fn add_ivar<T: Encode>(&mut self, name: &str) -> bool {
+        let name = match CString::new(name) {
+            Ok(name) => name,
+            Err(_) => return false,
+        };
+        if self.ivars.iter().any(|(n, _, _)| n == &name) {
+            return false;
+        }
+        let offset = self.instance_size.next_multiple_of(mem::align_of::<T>());
+        self.instance_size = offset + mem::size_of::<T>();
+        let ivar_type = CString::new(T::ENCODING).unwrap();
+        self.ivars.push((name, ivar_type, offset));
+        true
+    }
+
+    fn add_method_to<F: MethodImplementation>(
+        methods: &mut Vec<MethodDecl>,
+        selector: Sel,
+        imp: F,
+    ) -> bool {
+        let name = CString::new(selector.name()).unwrap();
+        if methods.iter().any(|method| method.name == name) {
+            return false;
+        }
+        let types = CString::new(method_types::<F>()).unwrap();
+        methods.push(MethodDecl {
+            name,
+            types,
+            imp: imp.imp(),
+        });
+        true
+    }
+
+    /*
+     * Returns false if a method of the selector is already added.
+     */
+    pub fn add_method<F: MethodImplementation>(&mut self, selector: Sel, imp: F) -> bool {
+        ClassDecl::add_method_to(&mut self.methods, selector, imp)
+    }
+
+    pub fn add_class_method<F: MethodImplementation>(&mut self, selector: Sel, imp: F) -> bool {
+        ClassDecl::add_method_to(&mut self.class_methods, selector, imp)
+    }
+
+    pub fn add_protocol(&mut self, protocol: Protocol) -> bool {
+        if self.protocols.contains(&protocol) {
+            return false;
+        }
+        self.protocols.push(protocol);
+        true
+    }
+
+    /*
+     * Returns None if a class of the same name has been loaded since the declaration began.
+     */
+    pub fn register(self) -> Option<Class> {
+        let (class, loaded) = {
+            let mut ctx = CONTEXT.write().unwrap();
+            ctx.start_loading();
+            let class = self.register_in(&mut ctx);
+            (class, ctx.take_loaded(StrPtr::null()))
+        };
+        load::notify(loaded);
+        class
+    }
+
+    pub(crate) fn register_in(self, ctx: &mut Context) -> Option<Class> {
+        let exists = {
+            let name = unsafe { StrPtr::from_ptr(self.name.as_ptr()) };
+            ctx.get_class_entry(&name).is_some()
+        };
+        if exists {
+            return None;
+        }
+        let name = StrPtr::leak(self.name);
+        let root_name = match self.superclass {
+            Some(superclass) => {
+                let mut root = superclass;
+                while let Some(superclass) = root.superclass() {
+                    root = superclass;
+                }
+                root.name_ptr()
+            }
+            None => name.clone(),
+        };
+        let ivars = if self.ivars.is_empty() {
+            None
+        } else {
+            Some(ObjcIvarList::new(
+                self.ivars
+                    .into_iter()
+                    .map(|(name, ivar_type, offset)| {
+                        ObjcIvar::new(StrPtr::leak(name), StrPtr::leak(ivar_type), offset)
+                    })
+                    .collect(),
+            ))
+        };
+        let protocols = if self.protocols.is_empty() {
+            None
+        } else {
+            Some(ObjcProtocolList::new(
+                self.protocols
+                    .iter()
+                    .map(|protocol| unsafe { Ptr::new(protocol.as_ptr()) })
+                    .collect(),
+            ))
+        };
+        let class = ObjcClass::new_pair(
+            name,
+            self.superclass.map(|superclass| superclass.name_ptr()),
+            root_name,
+            self.instance_size,
+            ivars,
+            into_method_list(self.methods),
+            into_method_list(self.class_methods),
+            protocols,
+        );
+        let class = match self.storage {
+            Some(storage) => unsafe { ObjcClass::move_to(class, storage) },
+            None => class,
+        };
+        ctx.load_class_pair(class.clone());
+        Some(Class::from_ptr(class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClassDecl;
+    use crate::api::{Class, Object, Sel};
+    use crate::msg_send;
+    use crate::runtime::objc_getClass;
+    use crate::str_ptr::StrPtr;
+    use crate::testing;
+
+    fn value_ptr(this: *mut Object) -> *mut i32 {
+        let class = Class::named("RobjcDeclBase").unwrap();
+        let ivar = class.ivars().find(|ivar| ivar.name() == "_value").unwrap();
+        unsafe { (this as *mut u8).offset(ivar.offset()) as *mut i32 }
+    }
+
+    extern "C" fn value(this: *mut Object, _selector: Sel) -> i32 {
+        unsafe { *value_ptr(this) }
+    }
+
+    extern "C" fn set_value(this: *mut Object, _selector: Sel, value: i32) {
+        unsafe { *value_ptr(this) = value }
+    }
+
+    extern "C" fn answer(_this: *mut Object, _selector: Sel) -> f64 {
+        42.0
+    }
+
+    #[test]
+    fn declare() {
+        let mut decl = ClassDecl::root("RobjcDeclBase").unwrap();
+        assert!(decl.add_ivar::<i32>("_value"));
+        assert!(!decl.add_ivar::<i32>("_value"));
+        let value_sel = Sel::register("value").unwrap();
+        let set_value_sel = Sel::register("setValue:").unwrap();
+        assert!(decl.add_method(value_sel, value as extern "C" fn(_, _) -> _));
+        assert!(decl.add_method(set_value_sel, set_value as extern "C" fn(_, _, _)));
+        let answer_sel = Sel::register("answer").unwrap();
+        assert!(decl.add_class_method(answer_sel, answer as extern "C" fn(_, _) -> _));
+        let base = decl.register().unwrap();
+
+        assert_eq!(Class::named("RobjcDeclBase"), Some(base));
+        let found = objc_getClass(StrPtr::from_static(b"RobjcDeclBase\0"));
+        assert_eq!(found.0.as_ref().unwrap().as_ptr(), base.as_ptr());
+        assert!(ClassDecl::root("RobjcDeclBase").is_none());
+        assert_eq!(base.instance_size(), 12);
+        assert_eq!(
+            base.instance_method(set_value_sel).unwrap().types(),
+            Some("v20@0:8i16")
+        );
+
+        let sub = testing::declare("RobjcDeclSub", Some("RobjcDeclBase"), |_| {});
+        assert_eq!(sub.superclass(), Some(base));
+        assert_eq!(sub.metaclass().unwrap().superclass(), base.metaclass());
+
+        let object = sub.create_instance().as_ptr();
+        unsafe {
+            let () = msg_send![object, setValue: 7i32];
+            let value: i32 = msg_send![object, value];
+            assert_eq!(value, 7);
+            let class = base.as_ptr() as *mut Object;
+            let answer: f64 = msg_send![class, answer];
+            assert_eq!(answer, 42.0);
+        }
+    }
+}
@@ -0,0 +1,384 @@
+/*
+ * Dumps of the runtime state for debugging: the loaded modules, the class tree, the dispatch
+ * tables with the categories the methods come from, and what is still waiting to be resolved.
+ * robjc_dump writes a dump on request, and ROBJC_DUMP=1 or ROBJC_DUMP=json writes one to
+ * stderr after each module is loaded.
+ */
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+use std::os::raw;
+
+use super::class::ObjcClass;
+use super::context::{Context, CONTEXT};
+use super::method::ObjcMethod;
+use super::ptr::Ptr;
+use super::str_ptr::StrPtr;
+use super::Bool;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/*
+ * Read from the environment on each load, so that it can be set before any module is loaded.
+ */
+pub fn format_from_env() -> Option<Format> {
+    match env::var("ROBJC_DUMP").ok()?.as_str() {
+        "" | "0" => None,
+        "json" => Some(Format::Json),
+        _ => Some(Format::Text),
+    }
+}
+
+pub fn dump(ctx: &Context, format: Format) -> String {
+    match format {
+        Format::Text => dump_text(ctx),
+        Format::Json => dump_json(ctx),
+    }
+}
+
+fn name(s: &StrPtr) -> &str {
+    s.as_ref().unwrap_or("")
+}
+
+/*
+ * The methods of loaded classes are linked to their selectors.
+ */
+fn selector_name(method: &ObjcMethod) -> &str {
+    name(method.name().get_id())
+}
+
+/*
+ * Pending methods still have their names in place of the selectors.
+ */
+fn unresolved_selector_name(method: &Ptr<ObjcMethod>) -> &str {
+    let s = unsafe { &*(method.as_ptr() as *const StrPtr) };
+    name(s)
+}
+
+fn sorted_classes(ctx: &Context) -> Vec<&Ptr<ObjcClass>> {
+    let mut classes: Vec<_> = ctx.iter_class_entry().map(|entry| entry.class()).collect();
+    classes.sort_by(|a, b| name(a.get_name()).cmp(name(b.get_name())));
+    classes
+}
+
+fn sorted_subclasses(class: &ObjcClass) -> Vec<Ptr<ObjcClass>> {
+    let mut subclasses: Vec<_> = class
+        .iter_subclass()
+        .filter(|subclass| subclass.is_class())
+        .collect();
+    subclasses.sort_by(|a, b| name(a.get_name()).cmp(name(b.get_name())));
+    subclasses
+}
+
+/*
+ * Maps the methods added by the categories of the loaded modules to the category names.
+ */
+fn category_origins(ctx: &Context) -> HashMap<*const ObjcMethod, String> {
+    let mut origins = HashMap::new();
+    for module in ctx.modules() {
+        for category in module.symtab().iter_category() {
+            let lists = [category.instance_methods(), category.class_methods()];
+            for list in lists.iter().flatten() {
+                for method in list.iter() {
+                    origins.insert(
+                        method.as_ptr() as *const ObjcMethod,
+                        name(category.category_name()).to_string(),
+                    );
+                }
+            }
+        }
+    }
+    origins
+}
+
+fn sorted_methods(class: &ObjcClass) -> Vec<&Ptr<ObjcMethod>> {
+    let mut methods: Vec<_> = class.iter_registered_method().collect();
+    methods.sort_by(|a, b| selector_name(a).cmp(selector_name(b)));
+    methods
+}
+
+fn protocol_names(class: &ObjcClass) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut list = class.protocols();
+    while let Some(protocols) = list {
+        names.extend(
+            protocols
+                .iter()
+                .map(|protocol| name(protocol.name()).to_string()),
+        );
+        list = protocols.next();
+    }
+    names
+}
+
+fn write_tree(out: &mut String, class: &ObjcClass, depth: usize) {
+    let _ = writeln!(out, "  {:2$}{}", "", name(class.get_name()), depth * 2);
+    for subclass in sorted_subclasses(class) {
+        write_tree(out, &subclass, depth + 1);
+    }
+}
+
+pub fn dump_text(ctx: &Context) -> String {
+    let origins = category_origins(ctx);
+    let classes = sorted_classes(ctx);
+    let mut out = String::new();
+
+    let _ = writeln!(out, "robjc: modules:");
+    for module in ctx.modules() {
+        let _ = writeln!(out, "  {}", module.as_ref());
+    }
+
+    let _ = writeln!(out, "robjc: class tree:");
+    for class in classes
+        .iter()
+        .filter(|class| class.is_resolved() && class.super_pointer().is_none())
+    {
+        write_tree(&mut out, class, 0);
+    }
+
+    let _ = writeln!(out, "robjc: classes:");
+    for class in &classes {
+        let _ = writeln!(out, "{}", class.as_ref());
+        let _ = writeln!(out, " live instances: {}", class.live_instances());
+        for (kind, class) in [('-', *class), ('+', class.class_pointer())] {
+            for method in sorted_methods(class) {
+                let _ = write!(
+                    out,
+                    "  {}{} {}",
+                    kind,
+                    selector_name(method),
+                    name(method.types())
+                );
+                match origins.get(&(method.as_ptr() as *const ObjcMethod)) {
+                    Some(category) => {
+                        let _ = writeln!(out, " ({})", category);
+                    }
+                    None => out.push('\n'),
+                }
+            }
+        }
+    }
+
+    let _ = writeln!(out, "robjc: orphan classes:");
+    for class in ctx.orphan_classes() {
+        let _ = writeln!(out, "{}", class.as_ref());
+    }
+
+    let _ = writeln!(out, "robjc: unresolved methods:");
+    for (class, method) in ctx.unresolved_methods() {
+        let kind = if class.is_meta() { '+' } else { '-' };
+        let _ = writeln!(
+            out,
+            "  {}[{} {}] {}",
+            kind,
+            name(class.get_name()),
+            unresolved_selector_name(method),
+            name(method.types())
+        );
+    }
+
+    let _ = writeln!(out, "robjc: unresolved categories:");
+    for category in ctx.unresolved_categories() {
+        let _ = writeln!(out, "{}", category.as_ref());
+    }
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_str_ptr(s: &StrPtr) -> String {
+    s.as_ref().map_or("null".to_string(), json_string)
+}
+
+fn json_array(items: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+fn json_methods(class: &ObjcClass, origins: &HashMap<*const ObjcMethod, String>) -> String {
+    json_array(sorted_methods(class).into_iter().map(|method| {
+        format!(
+            "{{\"selector\":{},\"types\":{},\"category\":{}}}",
+            json_string(selector_name(method)),
+            json_str_ptr(method.types()),
+            origins
+                .get(&(method.as_ptr() as *const ObjcMethod))
+                .map_or("null".to_string(), |category| json_string(category))
+        )
+    }))
+}
+
+fn json_class(class: &ObjcClass, origins: &HashMap<*const ObjcMethod, String>) -> String {
+    let ivars = json_array(class.ivars().into_iter().flat_map(|ivars| {
+        ivars.iter().map(|ivar| {
+            format!(
+                "{{\"name\":{},\"type\":{},\"offset\":{}}}",
+                json_str_ptr(ivar.name()),
+                json_str_ptr(ivar.ivar_type()),
+                ivar.offset()
+            )
+        })
+    }));
+    format!(
+        "{{\"name\":{},\"superclass\":{},\"version\":{},\"instance_size\":{},\
+         \"live_instances\":{},\"subclasses\":{},\"protocols\":{},\"ivars\":{},\
+         \"instance_methods\":{},\"class_methods\":{}}}",
+        json_str_ptr(class.get_name()),
+        class
+            .superclass_name()
+            .map_or("null".to_string(), |name| json_str_ptr(&name)),
+        class.version(),
+        class.get_instance_size(),
+        class.live_instances(),
+        json_array(
+            sorted_subclasses(class)
+                .iter()
+                .map(|subclass| json_str_ptr(subclass.get_name()))
+        ),
+        json_array(protocol_names(class).iter().map(|name| json_string(name))),
+        ivars,
+        json_methods(class, origins),
+        json_methods(class.class_pointer(), origins)
+    )
+}
+
+pub fn dump_json(ctx: &Context) -> String {
+    let origins = category_origins(ctx);
+    let modules = json_array(ctx.modules().map(|module| {
+        let symtab = module.symtab();
+        format!(
+            "{{\"name\":{},\"classes\":{},\"categories\":{}}}",
+            json_str_ptr(module.name()),
+            json_array(
+                symtab
+                    .iter_class()
+                    .map(|class| json_str_ptr(class.get_name()))
+            ),
+            json_array(symtab.iter_category().map(|category| format!(
+                "{{\"class\":{},\"category\":{}}}",
+                json_str_ptr(category.class_name()),
+                json_str_ptr(category.category_name())
+            )))
+        )
+    }));
+    let classes = json_array(
+        sorted_classes(ctx)
+            .into_iter()
+            .map(|class| json_class(class, &origins)),
+    );
+    let orphans = json_array(ctx.orphan_classes().iter().map(|class| {
+        format!(
+            "{{\"name\":{},\"meta\":{},\"superclass\":{}}}",
+            json_str_ptr(class.get_name()),
+            class.is_meta(),
+            class
+                .superclass_name()
+                .map_or("null".to_string(), |name| json_str_ptr(&name))
+        )
+    }));
+    let unresolved_methods = json_array(ctx.unresolved_methods().iter().map(|(class, method)| {
+        format!(
+            "{{\"class\":{},\"meta\":{},\"selector\":{},\"types\":{}}}",
+            json_str_ptr(class.get_name()),
+            class.is_meta(),
+            json_string(unresolved_selector_name(method)),
+            json_str_ptr(method.types())
+        )
+    }));
+    let unresolved_categories = json_array(ctx.unresolved_categories().map(|category| {
+        format!(
+            "{{\"class\":{},\"category\":{}}}",
+            json_str_ptr(category.class_name()),
+            json_str_ptr(category.category_name())
+        )
+    }));
+    format!(
+        "{{\"modules\":{},\"classes\":{},\"orphan_classes\":{},\"unresolved_methods\":{},\
+         \"unresolved_categories\":{}}}\n",
+        modules, classes, orphans, unresolved_methods, unresolved_categories
+    )
+}
+
+pub fn write_to(fd: raw::c_int, dump: &str) {
+    let mut bytes = dump.as_bytes();
+    while !bytes.is_empty() {
+        let written =
+            unsafe { libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len()) };
+        if written <= 0 {
+            return;
+        }
+        bytes = &bytes[written as usize..];
+    }
+}
+
+/*
+ * Writes a dump of the runtime state, as JSON or in the form of the Display impls.  A negative
+ * descriptor stands for stderr.
+ */
+#[no_mangle]
+pub extern "C" fn robjc_dump(fd: raw::c_int, json: Bool) {
+    let format = if json.into() {
+        Format::Json
+    } else {
+        Format::Text
+    };
+    let dump = dump(&CONTEXT.read().unwrap(), format);
+    write_to(if fd < 0 { libc::STDERR_FILENO } else { fd }, &dump);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dump_json, dump_text};
+    use crate::api::{Object, Sel};
+    use crate::context::CONTEXT;
+    use crate::testing;
+
+    extern "C" fn value(_this: *mut Object, _selector: Sel) -> i32 {
+        1
+    }
+
+    #[test]
+    fn dump() {
+        testing::declare("RobjcDumpBase", None, |decl| {
+            decl.add_ivar::<i32>("_value");
+            testing::add_method(decl, "robjcDumpValue", value as extern "C" fn(_, _) -> _);
+        });
+        testing::declare("RobjcDump\"Sub", Some("RobjcDumpBase"), |_| {});
+
+        let ctx = CONTEXT.read().unwrap();
+        let text = dump_text(&ctx);
+        assert!(text.contains("  RobjcDumpBase\n    RobjcDump\"Sub\n"));
+        assert!(text.contains("  -robjcDumpValue i16@0:8\n"));
+
+        let json = dump_json(&ctx);
+        assert!(json.contains(
+            "{\"name\":\"RobjcDump\\\"Sub\",\"superclass\":\"RobjcDumpBase\",\"version\":0,"
+        ));
+        assert!(json.contains(
+            "\"instance_methods\":[{\"selector\":\"robjcDumpValue\",\"types\":\"i16@0:8\",\
+             \"category\":null}]"
+        ));
+    }
+}
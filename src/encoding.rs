@@ -0,0 +1,794 @@
+use std::cmp;
+use std::mem;
+use std::os::raw;
+
+use super::str_ptr::StrPtr;
+use super::Bool;
+
+const C_ID: u8 = b'@';
+const C_CLASS: u8 = b'#';
+const C_SEL: u8 = b':';
+const C_CHR: u8 = b'c';
+const C_UCHR: u8 = b'C';
+const C_SHT: u8 = b's';
+const C_USHT: u8 = b'S';
+const C_INT: u8 = b'i';
+const C_UINT: u8 = b'I';
+const C_LNG: u8 = b'l';
+const C_ULNG: u8 = b'L';
+const C_LNG_LNG: u8 = b'q';
+const C_ULNG_LNG: u8 = b'Q';
+const C_FLT: u8 = b'f';
+const C_DBL: u8 = b'd';
+const C_LNG_DBL: u8 = b'D';
+const C_BFLD: u8 = b'b';
+const C_BOOL: u8 = b'B';
+const C_VOID: u8 = b'v';
+const C_UNDEF: u8 = b'?';
+const C_PTR: u8 = b'^';
+const C_CHARPTR: u8 = b'*';
+const C_ATOM: u8 = b'%';
+const C_ARY_B: u8 = b'[';
+const C_ARY_E: u8 = b']';
+const C_UNION_B: u8 = b'(';
+const C_UNION_E: u8 = b')';
+const C_STRUCT_B: u8 = b'{';
+const C_STRUCT_E: u8 = b'}';
+const C_VECTOR: u8 = b'!';
+const C_COMPLEX: u8 = b'j';
+
+const C_CONST: u8 = b'r';
+const C_IN: u8 = b'n';
+const C_INOUT: u8 = b'N';
+const C_OUT: u8 = b'o';
+const C_BYCOPY: u8 = b'O';
+const C_BYREF: u8 = b'R';
+const C_ONEWAY: u8 = b'V';
+const C_GCINVISIBLE: u8 = b'|';
+
+pub const F_CONST: raw::c_uint = 0x01;
+pub const F_IN: raw::c_uint = 0x01;
+pub const F_OUT: raw::c_uint = 0x02;
+pub const F_INOUT: raw::c_uint = 0x03;
+pub const F_BYCOPY: raw::c_uint = 0x04;
+pub const F_BYREF: raw::c_uint = 0x08;
+pub const F_ONEWAY: raw::c_uint = 0x10;
+pub const F_GCINVISIBLE: raw::c_uint = 0x20;
+
+const BITS_PER_UNIT: usize = 8;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const LONG_DOUBLE_SIZE_ALIGN: (usize, usize) = (16, 16);
+#[cfg(target_arch = "x86")]
+const LONG_DOUBLE_SIZE_ALIGN: (usize, usize) = (12, 4);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "x86")))]
+const LONG_DOUBLE_SIZE_ALIGN: (usize, usize) = (8, 8);
+
+fn size_align_of<T>() -> (usize, usize) {
+    (mem::size_of::<T>(), mem::align_of::<T>())
+}
+
+fn primitive_size_align(c: u8) -> Option<(usize, usize)> {
+    match c {
+        C_ID | C_CLASS | C_SEL | C_PTR | C_CHARPTR | C_ATOM | C_UNDEF => {
+            Some(size_align_of::<*const raw::c_void>())
+        }
+        C_CHR | C_UCHR => Some(size_align_of::<raw::c_char>()),
+        C_SHT | C_USHT => Some(size_align_of::<raw::c_short>()),
+        C_INT | C_UINT => Some(size_align_of::<raw::c_int>()),
+        C_LNG | C_ULNG => Some(size_align_of::<raw::c_long>()),
+        C_LNG_LNG | C_ULNG_LNG => Some(size_align_of::<raw::c_longlong>()),
+        C_FLT => Some(size_align_of::<raw::c_float>()),
+        C_DBL => Some(size_align_of::<raw::c_double>()),
+        C_LNG_DBL => Some(LONG_DOUBLE_SIZE_ALIGN),
+        C_BOOL => Some(size_align_of::<bool>()),
+        C_VOID => Some((0, 1)),
+        _ => None,
+    }
+}
+
+fn round(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+fn skip_digits(t: &[u8]) -> &[u8] {
+    let len = t.iter().take_while(|c| c.is_ascii_digit()).count();
+    &t[len..]
+}
+
+fn parse_number(t: &[u8]) -> Option<(usize, &[u8])> {
+    let rest = skip_digits(t);
+    let digits = &t[..t.len() - rest.len()];
+    let n = std::str::from_utf8(digits).ok()?.parse().ok()?;
+    Some((n, rest))
+}
+
+fn expect(t: &[u8], c: u8) -> Option<&[u8]> {
+    match t.split_first() {
+        Some((&first, rest)) if first == c => Some(rest),
+        _ => None,
+    }
+}
+
+/*
+ * Skips the quoted name which precedes struct members in some encodings, e.g. {Point="x"i"y"i}.
+ */
+fn skip_variable_name(t: &[u8]) -> Option<&[u8]> {
+    match t.split_first() {
+        Some((b'"', rest)) => {
+            let end = rest.iter().position(|&c| c == b'"')?;
+            Some(&rest[end + 1..])
+        }
+        _ => Some(t),
+    }
+}
+
+/*
+ * Splits the GNU encoding of bitfields, b<position><type><size>, into its parts.
+ */
+fn parse_bitfield(t: &[u8]) -> Option<(usize, &[u8], usize)> {
+    let rest = expect(t, C_BFLD)?;
+    let (position, rest) = parse_number(rest)?;
+    let (bitfield_type, rest) = (rest.get(..1)?, &rest[1..]);
+    let (size, _) = parse_number(rest)?;
+    Some((position, bitfield_type, size))
+}
+
+pub fn skip_type_qualifiers(t: &[u8]) -> &[u8] {
+    let qualifiers = [
+        C_CONST,
+        C_IN,
+        C_INOUT,
+        C_OUT,
+        C_BYCOPY,
+        C_BYREF,
+        C_ONEWAY,
+        C_GCINVISIBLE,
+    ];
+    let len = t.iter().take_while(|c| qualifiers.contains(c)).count();
+    &t[len..]
+}
+
+pub fn get_type_qualifiers(t: &[u8]) -> raw::c_uint {
+    let mut qualifiers = 0;
+    for c in t {
+        qualifiers |= match *c {
+            C_CONST => F_CONST,
+            C_IN => F_IN,
+            C_INOUT => F_INOUT,
+            C_OUT => F_OUT,
+            C_BYCOPY => F_BYCOPY,
+            C_BYREF => F_BYREF,
+            C_ONEWAY => F_ONEWAY,
+            C_GCINVISIBLE => F_GCINVISIBLE,
+            _ => break,
+        };
+    }
+    qualifiers
+}
+
+/*
+ * Returns the rest of the encoding after one type, or None if the encoding is malformed.
+ */
+pub fn skip_typespec(t: &[u8]) -> Option<&[u8]> {
+    let t = skip_type_qualifiers(skip_variable_name(t)?);
+    let (&c, rest) = t.split_first()?;
+    match c {
+        C_ID => match rest.split_first() {
+            Some((b'"', rest)) => {
+                let end = rest.iter().position(|&c| c == b'"')?;
+                Some(&rest[end + 1..])
+            }
+            // blocks are encoded as @?
+            Some((&C_UNDEF, rest)) => Some(rest),
+            _ => Some(rest),
+        },
+        C_COMPLEX => skip_typespec(rest),
+        C_ARY_B => {
+            let rest = skip_typespec(skip_digits(rest))?;
+            expect(rest, C_ARY_E)
+        }
+        C_VECTOR => {
+            let rest = skip_digits(expect(rest, C_ARY_B)?);
+            let rest = skip_digits(expect(rest, b',')?);
+            let rest = skip_typespec(rest)?;
+            expect(rest, C_ARY_E)
+        }
+        C_BFLD => {
+            let rest = skip_digits(rest);
+            let rest = rest.get(1..)?;
+            Some(skip_digits(rest))
+        }
+        C_STRUCT_B | C_UNION_B => {
+            let end = if c == C_STRUCT_B {
+                C_STRUCT_E
+            } else {
+                C_UNION_E
+            };
+            let name_len = rest
+                .iter()
+                .position(|&c| c == end || c == b'=')
+                .unwrap_or(rest.len());
+            let mut rest = match rest.get(name_len) {
+                Some(b'=') => &rest[name_len + 1..],
+                _ => &rest[name_len..],
+            };
+            while *rest.first()? != end {
+                rest = skip_typespec(rest)?;
+            }
+            Some(&rest[1..])
+        }
+        C_PTR => skip_typespec(rest),
+        c if primitive_size_align(c).is_some() => Some(rest),
+        _ => None,
+    }
+}
+
+/*
+ * Skips the offset which follows each type in method type encodings.  Old compilers prepended
+ * a '+' to offsets of arguments passed in registers.
+ */
+pub fn skip_offset(t: &[u8]) -> &[u8] {
+    let t = match t.first() {
+        Some(b'+') => &t[1..],
+        _ => t,
+    };
+    let t = match t.first() {
+        Some(b'-') => &t[1..],
+        _ => t,
+    };
+    skip_digits(t)
+}
+
+pub fn skip_argspec(t: &[u8]) -> Option<&[u8]> {
+    skip_typespec(t).map(skip_offset)
+}
+
+/*
+ * Parses the offset which follows a type.  The flag tells whether the offset is relative to the
+ * register area of the argument frame, which old compilers marked with a '+'.
+ */
+pub fn parse_offset(t: &[u8]) -> Option<(bool, isize)> {
+    let (in_registers, t) = match t.first() {
+        Some(b'+') => (true, &t[1..]),
+        _ => (false, t),
+    };
+    let (negative, t) = match t.first() {
+        Some(b'-') => (true, &t[1..]),
+        _ => (false, t),
+    };
+    let (offset, _) = parse_number(t)?;
+    let offset = offset as isize;
+    Some((in_registers, if negative { -offset } else { offset }))
+}
+
+/*
+ * Counts the arguments of a method type encoding, including self and _cmd.
+ */
+pub fn number_of_arguments(t: &[u8]) -> Option<usize> {
+    let mut t = skip_argspec(t)?;
+    let mut count = 0;
+    while !t.is_empty() {
+        t = skip_argspec(t)?;
+        count += 1;
+    }
+    Some(count)
+}
+
+pub fn sizeof_type(t: &[u8]) -> Option<usize> {
+    skip_typespec(t)?;
+    let t = skip_type_qualifiers(skip_variable_name(t)?);
+    let (&c, rest) = t.split_first()?;
+    match c {
+        C_ARY_B => {
+            let (count, rest) = parse_number(rest)?;
+            count.checked_mul(sizeof_type(rest)?)
+        }
+        C_VECTOR => parse_number(expect(rest, C_ARY_B)?).map(|(size, _)| size),
+        C_BFLD => {
+            let (position, _, size) = parse_bitfield(t)?;
+            Some((position + size) / BITS_PER_UNIT - position / BITS_PER_UNIT)
+        }
+        C_STRUCT_B | C_UNION_B => {
+            let mut layout = StructLayout::new(t)?;
+            while layout.next_member() {}
+            Some(layout.finish().0)
+        }
+        C_COMPLEX => Some(2 * sizeof_type(rest)?),
+        c => primitive_size_align(c).map(|(size, _)| size),
+    }
+}
+
+pub fn alignof_type(t: &[u8]) -> Option<usize> {
+    skip_typespec(t)?;
+    let t = skip_type_qualifiers(skip_variable_name(t)?);
+    let (&c, rest) = t.split_first()?;
+    match c {
+        C_ARY_B => alignof_type(skip_digits(rest)),
+        C_VECTOR => {
+            let rest = skip_digits(expect(rest, C_ARY_B)?);
+            parse_number(expect(rest, b',')?).map(|(align, _)| align)
+        }
+        C_BFLD => {
+            let (_, bitfield_type, _) = parse_bitfield(t)?;
+            alignof_type(bitfield_type)
+        }
+        C_STRUCT_B | C_UNION_B => {
+            let mut layout = StructLayout::new(t)?;
+            while layout.next_member() {}
+            Some(layout.finish().1)
+        }
+        C_COMPLEX => alignof_type(rest),
+        c => primitive_size_align(c).map(|(_, align)| align),
+    }
+}
+
+pub fn aligned_size(t: &[u8]) -> Option<usize> {
+    let t = skip_variable_name(t)?;
+    Some(round(sizeof_type(t)?, alignof_type(t)?))
+}
+
+pub fn promoted_size(t: &[u8]) -> Option<usize> {
+    let t = skip_variable_name(t)?;
+    Some(round(sizeof_type(t)?, mem::size_of::<*const raw::c_void>()))
+}
+
+/*
+ * Computes the layout of a struct or union member by member, following the rules the GNU
+ * runtime uses.  Sizes and alignments are kept in bits so that bitfields can be laid out.
+ */
+pub struct StructLayout<'a> {
+    whole: &'a [u8],
+    type_pos: Option<usize>,
+    prev_type_pos: Option<usize>,
+    record_size: usize,
+    record_align: usize,
+}
+
+impl<'a> StructLayout<'a> {
+    pub fn new(t: &'a [u8]) -> Option<StructLayout<'a>> {
+        skip_typespec(t)?;
+        let rest = match t.first() {
+            Some(&C_STRUCT_B) | Some(&C_UNION_B) => &t[1..],
+            _ => return None,
+        };
+        let end = if t[0] == C_STRUCT_B {
+            C_STRUCT_E
+        } else {
+            C_UNION_E
+        };
+        let name_len = rest
+            .iter()
+            .position(|&c| c == end || c == C_STRUCT_B || c == C_UNION_B || c == b'=');
+        let type_pos = match name_len {
+            Some(len) if rest[len] == b'=' => 1 + len + 1,
+            Some(len) if rest[len] == end => 1 + len,
+            _ => 1,
+        };
+        Some(StructLayout {
+            whole: t,
+            type_pos: Some(type_pos),
+            prev_type_pos: None,
+            record_size: 0,
+            record_align: BITS_PER_UNIT,
+        })
+    }
+
+    fn is_union(&self) -> bool {
+        self.whole[0] == C_UNION_B
+    }
+
+    fn end(&self) -> u8 {
+        if self.is_union() {
+            C_UNION_E
+        } else {
+            C_STRUCT_E
+        }
+    }
+
+    fn at(&self, pos: usize) -> &'a [u8] {
+        &self.whole[pos..]
+    }
+
+    fn pos_of(&self, rest: &[u8]) -> usize {
+        self.whole.len() - rest.len()
+    }
+
+    pub fn next_member(&mut self) -> bool {
+        if let Some(prev_type_pos) = self.prev_type_pos {
+            let t = skip_type_qualifiers(self.at(prev_type_pos));
+            if self.is_union() {
+                self.record_size = cmp::max(
+                    self.record_size,
+                    sizeof_type(t).unwrap_or(0).saturating_mul(BITS_PER_UNIT),
+                );
+            } else if let Some((_, _, size)) = parse_bitfield(t) {
+                self.record_size = self.record_size.saturating_add(size);
+            } else {
+                let size = sizeof_type(t).unwrap_or(0).saturating_mul(BITS_PER_UNIT);
+                self.record_size = self.record_size.saturating_add(size);
+            }
+        }
+
+        let t = match self.type_pos {
+            Some(pos) => self.at(pos),
+            None => return false,
+        };
+        if t.first().map_or(true, |&c| c == self.end()) {
+            return false;
+        }
+        let t = match skip_variable_name(t) {
+            Some(t) => t,
+            None => return false,
+        };
+        let member = skip_type_qualifiers(t);
+
+        let desired_align = match parse_bitfield(member) {
+            Some((position, bitfield_type, _)) => {
+                let type_align = alignof_type(bitfield_type).unwrap_or(1) * BITS_PER_UNIT;
+                self.record_align = cmp::max(self.record_align, type_align);
+                self.record_size = position;
+                None
+            }
+            None => {
+                let align = alignof_type(member).unwrap_or(1) * BITS_PER_UNIT;
+                self.record_align = cmp::max(self.record_align, align);
+                Some(align)
+            }
+        };
+        if let Some(desired_align) = desired_align {
+            if self.record_size % desired_align != 0 {
+                self.record_size = round(self.record_size, desired_align);
+            }
+        }
+
+        self.prev_type_pos = Some(self.pos_of(t));
+        self.type_pos = skip_typespec(t).map(|rest| self.pos_of(rest));
+        true
+    }
+
+    /*
+     * Returns the size and alignment of the record in bytes.
+     */
+    pub fn finish(&mut self) -> (usize, usize) {
+        if let Some(pos) = self.type_pos {
+            if self.whole.get(pos) == Some(&self.end()) {
+                self.record_size = round(self.record_size, self.record_align);
+                self.type_pos = None;
+            }
+        }
+        (
+            self.record_size / BITS_PER_UNIT,
+            self.record_align / BITS_PER_UNIT,
+        )
+    }
+
+    /*
+     * Returns the offset, the alignment of the record so far and the type of the current member.
+     */
+    pub fn get_info(&self) -> (usize, usize, Option<&'a [u8]>) {
+        (
+            self.record_size / BITS_PER_UNIT,
+            self.record_align / BITS_PER_UNIT,
+            self.prev_type_pos.map(|pos| self.at(pos)),
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ObjcStructLayout {
+    original_type: StrPtr,
+    type_: StrPtr,
+    prev_type: StrPtr,
+    record_size: raw::c_uint,
+    record_align: raw::c_uint,
+}
+
+impl ObjcStructLayout {
+    /*
+     * The original type points just after the opening brace, as in the GNU runtime.
+     */
+    fn whole(&self) -> &'static [u8] {
+        let p = unsafe { StrPtr::from_ptr(self.original_type.as_ptr().offset(-1)) };
+        to_static_bytes(&p)
+    }
+
+    /*
+     * None for the layout of a type which is not a structure or a union.
+     */
+    fn to_layout(&self) -> Option<StructLayout<'static>> {
+        if self.original_type.is_null() {
+            return None;
+        }
+        let whole = self.whole();
+        let pos_of = |p: &StrPtr| {
+            if p.is_null() {
+                None
+            } else {
+                Some(p.as_ptr() as usize - whole.as_ptr() as usize)
+            }
+        };
+        Some(StructLayout {
+            whole,
+            type_pos: pos_of(&self.type_),
+            prev_type_pos: pos_of(&self.prev_type),
+            record_size: self.record_size as usize,
+            record_align: self.record_align as usize,
+        })
+    }
+
+    fn update(&mut self, layout: &StructLayout) {
+        let ptr_of = |pos: Option<usize>| match pos {
+            Some(pos) => unsafe {
+                StrPtr::from_ptr((layout.whole.as_ptr() as *const raw::c_char).add(pos))
+            },
+            None => StrPtr::null(),
+        };
+        self.original_type = ptr_of(Some(1));
+        self.type_ = ptr_of(layout.type_pos);
+        self.prev_type = ptr_of(layout.prev_type_pos);
+        self.record_size = layout.record_size as raw::c_uint;
+        self.record_align = layout.record_align as raw::c_uint;
+    }
+}
+
+fn to_static_bytes(s: &StrPtr) -> &'static [u8] {
+    let s: &'static str = unsafe { &*(s.as_ref().expect("type is null") as *const str) };
+    s.as_bytes()
+}
+
+/*
+ * Null for a null type, so that the exports below can report it instead of panicking.
+ */
+fn type_bytes(t: &StrPtr) -> Option<&'static [u8]> {
+    if t.is_null() {
+        None
+    } else {
+        Some(to_static_bytes(t))
+    }
+}
+
+fn rest_of(t: &StrPtr, rest: &[u8]) -> StrPtr {
+    let offset = to_static_bytes(t).len() - rest.len();
+    unsafe { StrPtr::from_ptr(t.as_ptr().add(offset)) }
+}
+
+/*
+ * The sizes are 0 for null or invalid types.
+ */
+#[no_mangle]
+pub extern "C" fn objc_sizeof_type(t: StrPtr) -> raw::c_int {
+    type_bytes(&t).and_then(sizeof_type).unwrap_or(0) as raw::c_int
+}
+
+#[no_mangle]
+pub extern "C" fn objc_alignof_type(t: StrPtr) -> raw::c_int {
+    type_bytes(&t).and_then(alignof_type).unwrap_or(0) as raw::c_int
+}
+
+#[no_mangle]
+pub extern "C" fn objc_aligned_size(t: StrPtr) -> raw::c_int {
+    type_bytes(&t).and_then(aligned_size).unwrap_or(0) as raw::c_int
+}
+
+#[no_mangle]
+pub extern "C" fn objc_promoted_size(t: StrPtr) -> raw::c_int {
+    type_bytes(&t).and_then(promoted_size).unwrap_or(0) as raw::c_int
+}
+
+#[no_mangle]
+pub extern "C" fn objc_skip_type_qualifiers(t: StrPtr) -> StrPtr {
+    match type_bytes(&t) {
+        Some(bytes) => rest_of(&t, skip_type_qualifiers(bytes)),
+        None => StrPtr::null(),
+    }
+}
+
+/*
+ * Returns null for null or invalid types.
+ */
+#[no_mangle]
+pub extern "C" fn objc_skip_typespec(t: StrPtr) -> StrPtr {
+    match type_bytes(&t).and_then(skip_typespec) {
+        Some(rest) => rest_of(&t, rest),
+        None => StrPtr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn objc_skip_offset(t: StrPtr) -> StrPtr {
+    match type_bytes(&t) {
+        Some(bytes) => rest_of(&t, skip_offset(bytes)),
+        None => StrPtr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn objc_skip_argspec(t: StrPtr) -> StrPtr {
+    match type_bytes(&t).and_then(skip_argspec) {
+        Some(rest) => rest_of(&t, rest),
+        None => StrPtr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn objc_get_type_qualifiers(t: StrPtr) -> raw::c_uint {
+    type_bytes(&t).map_or(0, get_type_qualifiers)
+}
+
+/*
+ * A type which is not a structure or a union gives a layout without members, whose size and
+ * alignment are 0.
+ */
+#[no_mangle]
+pub extern "C" fn objc_layout_structure(t: StrPtr, layout: &mut ObjcStructLayout) {
+    match type_bytes(&t).and_then(StructLayout::new) {
+        Some(new_layout) => layout.update(&new_layout),
+        None => {
+            layout.original_type = StrPtr::null();
+            layout.type_ = StrPtr::null();
+            layout.prev_type = StrPtr::null();
+            layout.record_size = 0;
+            layout.record_align = 0;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn objc_layout_structure_next_member(layout: &mut ObjcStructLayout) -> Bool {
+    let mut new_layout = match layout.to_layout() {
+        Some(new_layout) => new_layout,
+        None => return Bool::from(false),
+    };
+    let has_next = new_layout.next_member();
+    layout.update(&new_layout);
+    Bool::from(has_next)
+}
+
+#[no_mangle]
+pub extern "C" fn objc_layout_finish_structure(
+    layout: &mut ObjcStructLayout,
+    size: Option<&mut raw::c_uint>,
+    align: Option<&mut raw::c_uint>,
+) {
+    let (record_size, record_align) = match layout.to_layout() {
+        Some(mut new_layout) => {
+            let size_and_align = new_layout.finish();
+            layout.update(&new_layout);
+            size_and_align
+        }
+        None => (0, 0),
+    };
+    if let Some(size) = size {
+        *size = record_size as raw::c_uint;
+    }
+    if let Some(align) = align {
+        *align = record_align as raw::c_uint;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn objc_layout_structure_get_info(
+    layout: &ObjcStructLayout,
+    offset: Option<&mut raw::c_uint>,
+    align: Option<&mut raw::c_uint>,
+    t: Option<&mut StrPtr>,
+) {
+    let (record_offset, record_align, member_type) = layout
+        .to_layout()
+        .map_or((0, 0, None), |layout| layout.get_info());
+    if let Some(offset) = offset {
+        *offset = record_offset as raw::c_uint;
+    }
+    if let Some(align) = align {
+        *align = record_align as raw::c_uint;
+    }
+    if let Some(t) = t {
+        *t = match member_type {
+            Some(_) => layout.prev_type.clone(),
+            None => StrPtr::null(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PTR: usize = mem::size_of::<*const raw::c_void>();
+
+    #[test]
+    fn skip() {
+        assert_eq!(skip_typespec(b"i8@0:4"), Some(&b"8@0:4"[..]));
+        assert_eq!(skip_typespec(b"@\"NSString\"16"), Some(&b"16"[..]));
+        assert_eq!(skip_typespec(b"@?"), Some(&b""[..]));
+        assert_eq!(skip_typespec(b"^{Point=ii}i"), Some(&b"i"[..]));
+        assert_eq!(skip_typespec(b"{Point=\"x\"i\"y\"i}"), Some(&b""[..]));
+        assert_eq!(skip_typespec(b"[4{Node=^{Node}i}]v"), Some(&b"v"[..]));
+        assert_eq!(skip_typespec(b"(Value=id)"), Some(&b""[..]));
+        assert_eq!(skip_typespec(b"b0i3b3i5"), Some(&b"b3i5"[..]));
+        assert_eq!(skip_typespec(b"![16,16i]"), Some(&b""[..]));
+        assert_eq!(skip_typespec(b"rnO@"), Some(&b""[..]));
+        assert_eq!(skip_typespec(b"{Broken=i"), None);
+        assert_eq!(skip_typespec(b"y"), None);
+        assert_eq!(skip_argspec(b"Vv+16@0:8"), Some(&b"@0:8"[..]));
+        assert_eq!(skip_type_qualifiers(b"rnNoORV|*"), &b"*"[..]);
+        assert_eq!(get_type_qualifiers(b"Vv"), F_ONEWAY);
+        assert_eq!(get_type_qualifiers(b"rO@"), F_CONST | F_BYCOPY);
+        assert_eq!(get_type_qualifiers(b"No@"), F_INOUT);
+    }
+
+    #[test]
+    fn size_and_alignment() {
+        assert_eq!(sizeof_type(b"c"), Some(1));
+        assert_eq!(sizeof_type(b"i"), Some(mem::size_of::<raw::c_int>()));
+        assert_eq!(sizeof_type(b"l"), Some(mem::size_of::<raw::c_long>()));
+        assert_eq!(sizeof_type(b"@"), Some(PTR));
+        assert_eq!(sizeof_type(b"^{Point=dd}"), Some(PTR));
+        assert_eq!(sizeof_type(b"[10s]"), Some(20));
+        assert_eq!(sizeof_type(b"{Point=dd}"), Some(16));
+        assert_eq!(sizeof_type(b"{Mixed=cdc}"), Some(24));
+        assert_eq!(alignof_type(b"{Mixed=cdc}"), Some(8));
+        assert_eq!(sizeof_type(b"{Small=cs}"), Some(4));
+        assert_eq!(sizeof_type(b"{Outer={Inner=ci}c}"), Some(12));
+        assert_eq!(sizeof_type(b"(Value=cid)"), Some(8));
+        assert_eq!(alignof_type(b"(Value=cs)"), Some(2));
+        assert_eq!(sizeof_type(b"{Flags=b0I3b3I5}"), Some(4));
+        assert_eq!(sizeof_type(b"{Empty=}"), Some(0));
+        assert_eq!(sizeof_type(b"{Opaque}"), Some(0));
+        assert_eq!(sizeof_type(b"jd"), Some(16));
+        assert_eq!(sizeof_type(b"![16,16i]"), Some(16));
+        assert_eq!(alignof_type(b"![16,16i]"), Some(16));
+        assert_eq!(aligned_size(b"{Odd=ic}"), Some(8));
+        assert_eq!(promoted_size(b"c"), Some(PTR));
+        assert_eq!(sizeof_type(b"{Broken=i"), None);
+    }
+
+    #[test]
+    fn layout() {
+        let mut layout = StructLayout::new(b"{Mixed=\"a\"c\"b\"d\"c\"[3s]}").unwrap();
+        let mut members = Vec::new();
+        while layout.next_member() {
+            let (offset, _, member_type) = layout.get_info();
+            members.push((
+                offset,
+                skip_typespec(member_type.unwrap())
+                    .map(|rest| member_type.unwrap().len() - rest.len()),
+            ));
+        }
+        assert_eq!(members, vec![(0, Some(1)), (8, Some(1)), (16, Some(4))]);
+        assert_eq!(layout.finish(), (24, 8));
+        assert!(StructLayout::new(b"i").is_none());
+    }
+
+    #[test]
+    fn invalid_types() {
+        let broken = StrPtr::from_static(b"{Broken=i\0");
+        assert_eq!(objc_sizeof_type(broken.clone()), 0);
+        assert_eq!(objc_alignof_type(StrPtr::null()), 0);
+        assert_eq!(objc_aligned_size(StrPtr::from_static(b"y\0")), 0);
+        assert_eq!(objc_promoted_size(broken.clone()), 0);
+        assert!(objc_skip_typespec(broken.clone()).is_null());
+        assert!(objc_skip_argspec(StrPtr::null()).is_null());
+        assert_eq!(sizeof_type(b"[9999999999999999999q]"), None);
+        assert_eq!(sizeof_type(b"[99999999999999999999q]"), None);
+        assert_eq!(
+            objc_sizeof_type(StrPtr::from_static(b"[9999999999999999999q]\0")),
+            0
+        );
+
+        let mut layout = ObjcStructLayout {
+            original_type: StrPtr::null(),
+            type_: StrPtr::null(),
+            prev_type: StrPtr::null(),
+            record_size: 0,
+            record_align: 0,
+        };
+        objc_layout_structure(broken, &mut layout);
+        assert!(!bool::from(objc_layout_structure_next_member(&mut layout)));
+        let (mut size, mut align) = (1, 1);
+        objc_layout_finish_structure(&mut layout, Some(&mut size), Some(&mut align));
+        assert_eq!((size, align), (0, 0));
+    }
+}
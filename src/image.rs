@@ -0,0 +1,168 @@
+/*
+ * Images are the executable and the shared objects mapped into the process.  Modules are
+ * recorded with the image containing them, so that the classes, categories and selectors of a
+ * bundle can be unloaded before dlclose unmaps it.
+ *
+ * Unloading is not triggered by dlclose itself: the dynamic linker has no notification for it,
+ * and __cxa_atexit handlers run by dlclose are keyed by the __dso_handle of the image, which is
+ * hidden and cannot be found from __objc_exec_class.  So images are unloaded either by closing
+ * them with objc_dlclose, or by a destructor of their own (OBJC_UNLOAD_ON_DLCLOSE in runtime.h).
+ *
+ * Images are identified by link maps, which only glibc and compatible dynamic linkers provide.
+ * Elsewhere, Image::of uses the base address of the object and handles cannot be resolved, so
+ * objc_dlclose refuses to close images.
+ */
+
+use std::ffi::c_void;
+use std::mem;
+use std::os::raw;
+#[cfg(target_os = "linux")]
+use std::ptr;
+
+use super::context::CONTEXT;
+
+/*
+ * Not provided by the libc crate.
+ */
+#[cfg(target_os = "linux")]
+const RTLD_DL_LINKMAP: raw::c_int = 2;
+
+/*
+ * An image is identified by its link map, which is also what dlopen returns as the handle.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Image(*const c_void);
+
+impl Image {
+    /*
+     * Returns None for addresses which are not in any image, e.g. of heap memory.
+     */
+    #[cfg(target_os = "linux")]
+    pub fn of<T>(address: *const T) -> Option<Image> {
+        let mut info = mem::MaybeUninit::<libc::Dl_info>::uninit();
+        let mut map: *mut c_void = ptr::null_mut();
+        let found = unsafe {
+            libc::dladdr1(
+                address as *const c_void,
+                info.as_mut_ptr(),
+                &mut map,
+                RTLD_DL_LINKMAP,
+            )
+        };
+        if found == 0 || map.is_null() {
+            None
+        } else {
+            Some(Image(map))
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn of<T>(address: *const T) -> Option<Image> {
+        let mut info = mem::MaybeUninit::<libc::Dl_info>::uninit();
+        if unsafe { libc::dladdr(address as *const c_void, info.as_mut_ptr()) } == 0 {
+            return None;
+        }
+        let base = unsafe { info.assume_init() }.dli_fbase;
+        if base.is_null() {
+            None
+        } else {
+            Some(Image(base as *const c_void))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn of_handle(handle: *mut c_void) -> Option<Image> {
+        let mut map: *mut c_void = ptr::null_mut();
+        let result = unsafe {
+            libc::dlinfo(
+                handle,
+                libc::RTLD_DI_LINKMAP,
+                &mut map as *mut *mut c_void as *mut c_void,
+            )
+        };
+        if result != 0 || map.is_null() {
+            None
+        } else {
+            Some(Image(map))
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn of_handle(_handle: *mut c_void) -> Option<Image> {
+        None
+    }
+
+    pub fn contains<T>(&self, address: *const T) -> bool {
+        !address.is_null() && Image::of(address) == Some(*self)
+    }
+}
+
+/*
+ * Unloads the modules of the image containing the address.  This is meant to be called from a
+ * destructor of the image, when dlclose can no longer be refused, so known live instances are
+ * only reported.  Returns the number of modules unloaded.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_unloadImage(address: *const c_void) -> raw::c_int {
+    let image = match Image::of(address) {
+        Some(image) => image,
+        None => return 0,
+    };
+    let mut ctx = CONTEXT.write().unwrap();
+    for problem in ctx.check_unloading(image) {
+        eprintln!("robjc: unloading image: {}", problem);
+    }
+    ctx.unload_image(image) as raw::c_int
+}
+
+/*
+ * Unloads the modules of the image and closes it.  Fails with -1 without closing the image if
+ * its classes have known live instances or subclasses in other images.  Instances are known to
+ * the runtime if they were created with class_createInstance and not yet disposed; objects
+ * allocated otherwise, e.g. by a Foundation's own allocator, cannot be checked.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_dlclose(handle: *mut c_void) -> raw::c_int {
+    match Image::of_handle(handle) {
+        Some(image) => {
+            let mut ctx = CONTEXT.write().unwrap();
+            let problems = ctx.check_unloading(image);
+            if !problems.is_empty() {
+                for problem in problems {
+                    eprintln!("robjc: cannot unload image: {}", problem);
+                }
+                return -1;
+            }
+            ctx.unload_image(image);
+        }
+        None if cfg!(not(target_os = "linux")) => {
+            eprintln!("robjc: cannot unload image: handles cannot be resolved on this platform");
+            return -1;
+        }
+        None => {}
+    }
+    unsafe { libc::dlclose(handle) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Image;
+    use crate::context::CONTEXT;
+
+    static DATA: u8 = 0;
+
+    #[test]
+    fn image() {
+        let image = Image::of(&DATA as *const u8).unwrap();
+        assert!(image.contains(Image::of::<u8> as *const u8));
+        let heap = Box::new(0u8);
+        assert!(!image.contains(&*heap as *const u8));
+
+        /* The test executable has no modules. */
+        let mut ctx = CONTEXT.write().unwrap();
+        assert!(ctx.check_unloading(image).is_empty());
+        assert_eq!(ctx.unload_image(image), 0);
+    }
+}
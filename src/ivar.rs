@@ -1,6 +1,7 @@
 use std::fmt;
 use std::slice;
 
+use super::ptr::{self, Ptr};
 use super::str_ptr::StrPtr;
 use super::Int;
 
@@ -12,6 +13,28 @@ pub struct ObjcIvar {
     ivar_offset: Int,
 }
 
+impl ObjcIvar {
+    pub fn new(name: StrPtr, ivar_type: StrPtr, offset: usize) -> ObjcIvar {
+        ObjcIvar {
+            ivar_name: name,
+            ivar_type,
+            ivar_offset: offset as Int,
+        }
+    }
+
+    pub fn name(&self) -> &StrPtr {
+        &self.ivar_name
+    }
+
+    pub fn ivar_type(&self) -> &StrPtr {
+        &self.ivar_type
+    }
+
+    pub fn offset(&self) -> isize {
+        self.ivar_offset as isize
+    }
+}
+
 impl fmt::Display for ObjcIvar {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -30,6 +53,14 @@ pub struct ObjcIvarList {
 }
 
 impl ObjcIvarList {
+    pub fn new(ivars: Vec<ObjcIvar>) -> Ptr<ObjcIvarList> {
+        let header = ObjcIvarList {
+            ivar_count: ivars.len() as Int,
+            ivar_list: [],
+        };
+        ptr::leak_with_trailing(header, ivars)
+    }
+
     fn ivar_count(&self) -> usize {
         self.ivar_count as usize
     }
@@ -45,6 +76,10 @@ impl ObjcIvarList {
             Some(&list[i])
         }
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ObjcIvar> {
+        (0..self.ivar_count()).filter_map(move |i| self.nth_ivar(i))
+    }
 }
 
 impl fmt::Display for ObjcIvarList {
@@ -1,18 +1,39 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod api;
+mod arc;
+mod autorelease;
+mod blocks;
 mod category;
 mod class;
+mod constant_string;
 mod context;
+pub mod declare;
+mod dump;
+mod encoding;
+mod image;
 mod ivar;
+pub mod load;
 mod message;
 mod method;
 mod module;
 mod object;
+mod property;
+mod protocol;
 mod ptr;
+#[cfg(feature = "root-object")]
+mod root_object;
 pub mod runtime;
 mod selector;
+pub mod send;
+#[cfg(feature = "stats")]
+pub mod stats;
 mod str_ptr;
+#[cfg(test)]
+mod testing;
+mod trace;
+mod weak;
 
 use std::convert;
 use std::os::raw;
@@ -23,6 +44,7 @@ use ivar::ObjcIvar;
 use method::{ObjcMethod, Procedure};
 use module::ObjcModule;
 use object::ObjcObject;
+use property::ObjcProperty;
 use ptr::{NilablePtr, Ptr};
 use selector::ObjcSelector;
 
@@ -44,6 +66,12 @@ impl convert::From<bool> for Bool {
         }
     }
 }
+impl convert::From<Bool> for bool {
+    fn from(b: Bool) -> Self {
+        b.0 != 0
+    }
+}
+
 pub const YES: Bool = Bool(1u8);
 pub const NO: Bool = Bool(0u8);
 
@@ -75,17 +103,30 @@ pub struct Method(NilablePtr<ObjcMethod>);
 #[derive(Debug)]
 pub struct Module(Ptr<ObjcModule>);
 
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct Property(NilablePtr<ObjcProperty>);
+
 #[no_mangle]
 pub extern "C" fn __objc_exec_class(module: &'static mut ObjcModule) {
-    let mut ctx = CONTEXT.write().unwrap();
-    ctx.load_module(module);
+    let loaded = {
+        let mut ctx = CONTEXT.write().unwrap();
+        ctx.start_loading();
+        ctx.load_module(module);
+        ctx.take_loaded(module.name().clone())
+    };
+    load::notify(loaded);
+    if let Some(format) = dump::format_from_env() {
+        let dump = dump::dump(&CONTEXT.read().unwrap(), format);
+        dump::write_to(libc::STDERR_FILENO, &dump);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::ptr::NilablePtr;
     use super::str_ptr::StrPtr;
-    use super::{Class, Id, Imp, Ivar, Method, Module, Ptr, Sel};
+    use super::{Class, Id, Imp, Ivar, Method, Module, Property, Ptr, Sel};
     use std::mem;
 
     #[test]
@@ -100,6 +141,7 @@ mod tests {
         assert_eq!(mem::size_of::<Imp>(), mem::size_of::<usize>());
         assert_eq!(mem::size_of::<Method>(), mem::size_of::<usize>());
         assert_eq!(mem::size_of::<Module>(), mem::size_of::<usize>());
+        assert_eq!(mem::size_of::<Property>(), mem::size_of::<usize>());
         assert_eq!(mem::size_of::<StrPtr>(), mem::size_of::<usize>());
     }
 }
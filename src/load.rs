@@ -0,0 +1,232 @@
+/*
+ * Notifications of loaded classes, categories and selectors.  The GNU _objc_load_callback is
+ * called for each class and category, and observers added from Rust or C get everything a load
+ * added at once.  Classes are reported once their superclasses are resolved, so a class whose
+ * superclass is in a later module is reported with that module.
+ *
+ * Notifications are sent after the context is unlocked, and observers are called without holding
+ * the list of observers, so the callbacks can use the runtime and add observers.
+ */
+
+use std::os::raw;
+use std::ptr;
+use std::sync::{Arc, RwLock};
+
+use super::api::{Class, Sel};
+use super::category::ObjcCategory;
+use super::class::ObjcClass;
+use super::selector::ObjcSelector;
+use super::str_ptr::StrPtr;
+
+/*
+ * Called with a nil category for each class, and with the class extended for each category.
+ */
+#[allow(non_upper_case_globals)]
+#[no_mangle]
+pub static mut _objc_load_callback: Option<
+    unsafe extern "C" fn(class: *mut ObjcClass, category: *mut ObjcCategory),
+> = None;
+
+/*
+ * What a load added, collected by the context until the notifications are sent.
+ */
+pub(crate) struct Loaded {
+    pub(crate) module_name: StrPtr,
+    pub(crate) classes: Vec<Class>,
+    pub(crate) categories: Vec<(Class, *mut ObjcCategory)>,
+    pub(crate) selectors: Vec<Sel>,
+}
+
+unsafe impl Send for Loaded {}
+
+impl Default for Loaded {
+    fn default() -> Loaded {
+        Loaded {
+            module_name: StrPtr::null(),
+            classes: Vec::new(),
+            categories: Vec::new(),
+            selectors: Vec::new(),
+        }
+    }
+}
+
+impl Loaded {
+    fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.categories.is_empty() && self.selectors.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadEvent<'a> {
+    /*
+     * None for classes declared from Rust.
+     */
+    pub module_name: Option<&'a str>,
+    pub classes: &'a [Class],
+    /*
+     * The classes extended, with the names of the categories.
+     */
+    pub categories: &'a [(Class, &'a str)],
+    pub selectors: &'a [Sel],
+}
+
+#[repr(C)]
+pub struct ObjcLoadedCategory {
+    class: *mut ObjcClass,
+    category_name: StrPtr,
+}
+
+#[repr(C)]
+pub struct ObjcLoadInfo {
+    module_name: StrPtr,
+    class_count: raw::c_uint,
+    classes: *const *mut ObjcClass,
+    category_count: raw::c_uint,
+    categories: *const ObjcLoadedCategory,
+    selector_count: raw::c_uint,
+    selectors: *const *mut ObjcSelector,
+}
+
+type LoadObserverFunction = extern "C" fn(info: *const ObjcLoadInfo, context: *mut raw::c_void);
+
+enum Observer {
+    Rust(Box<dyn Fn(&LoadEvent) + Send + Sync>),
+    C(LoadObserverFunction, usize),
+}
+
+lazy_static! {
+    static ref OBSERVERS: RwLock<Vec<Arc<Observer>>> = RwLock::new(Vec::new());
+}
+
+/*
+ * The observer is called for loads after it is added, not for classes already loaded.
+ */
+pub fn add_load_observer(observer: impl Fn(&LoadEvent) + Send + Sync + 'static) {
+    OBSERVERS
+        .write()
+        .unwrap()
+        .push(Arc::new(Observer::Rust(Box::new(observer))));
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_addLoadObserver(
+    observer: Option<LoadObserverFunction>,
+    context: *mut raw::c_void,
+) {
+    if let Some(observer) = observer {
+        OBSERVERS
+            .write()
+            .unwrap()
+            .push(Arc::new(Observer::C(observer, context as usize)));
+    }
+}
+
+pub(crate) fn notify(loaded: Loaded) {
+    if loaded.is_empty() {
+        return;
+    }
+    if let Some(callback) = unsafe { _objc_load_callback } {
+        for class in &loaded.classes {
+            unsafe { callback(class.as_ptr(), ptr::null_mut()) };
+        }
+        for (class, category) in &loaded.categories {
+            unsafe { callback(class.as_ptr(), *category) };
+        }
+    }
+
+    let observers = OBSERVERS.read().unwrap().clone();
+    if observers.is_empty() {
+        return;
+    }
+    let category_names: Vec<StrPtr> = loaded
+        .categories
+        .iter()
+        .map(|(_, category)| unsafe { &**category }.category_name().clone())
+        .collect();
+    let categories: Vec<(Class, &str)> = loaded
+        .categories
+        .iter()
+        .zip(&category_names)
+        .map(|((class, _), name)| (*class, name.as_ref().unwrap_or("")))
+        .collect();
+    let event = LoadEvent {
+        module_name: loaded.module_name.as_ref(),
+        classes: &loaded.classes,
+        categories: &categories,
+        selectors: &loaded.selectors,
+    };
+
+    let classes: Vec<*mut ObjcClass> = loaded.classes.iter().map(|c| c.as_ptr()).collect();
+    let c_categories: Vec<ObjcLoadedCategory> = loaded
+        .categories
+        .iter()
+        .zip(&category_names)
+        .map(|((class, _), name)| ObjcLoadedCategory {
+            class: class.as_ptr(),
+            category_name: name.clone(),
+        })
+        .collect();
+    let selectors: Vec<*mut ObjcSelector> = loaded.selectors.iter().map(|s| s.as_ptr()).collect();
+    let info = ObjcLoadInfo {
+        module_name: loaded.module_name.clone(),
+        class_count: classes.len() as raw::c_uint,
+        classes: classes.as_ptr(),
+        category_count: c_categories.len() as raw::c_uint,
+        categories: c_categories.as_ptr(),
+        selector_count: selectors.len() as raw::c_uint,
+        selectors: selectors.as_ptr(),
+    };
+
+    for observer in observers.iter() {
+        match &**observer {
+            Observer::Rust(observer) => observer(&event),
+            Observer::C(observer, context) => observer(&info, *context as *mut raw::c_void),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::add_load_observer;
+    use crate::api::{Class, Object, Sel};
+    use crate::testing;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn observer() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let observed = events.clone();
+        let nested = Arc::new(AtomicBool::new(false));
+        add_load_observer(move |event| {
+            /* The runtime can be used from observers, including adding observers. */
+            for class in event.classes {
+                assert_eq!(Class::named(class.name()), Some(*class));
+            }
+            if !nested.swap(true, Ordering::SeqCst) {
+                add_load_observer(|_| {});
+            }
+            let names =
+                |names: Vec<&str>| -> Vec<String> { names.into_iter().map(String::from).collect() };
+            observed.lock().unwrap().push((
+                names(event.classes.iter().map(|class| class.name()).collect()),
+                names(event.selectors.iter().map(|sel| sel.name()).collect()),
+            ));
+        });
+
+        /* Selectors registered between loads are not reported with the next load. */
+        Sel::register("robjcLoadBetween").unwrap();
+        extern "C" fn noop(_this: *mut Object, _selector: Sel) {}
+        testing::declare("RobjcLoadObserved", None, |decl| {
+            testing::add_method(decl, "robjcLoadObserved", noop as extern "C" fn(_, _));
+        });
+
+        let events = events.lock().unwrap();
+        let (_, selectors) = events
+            .iter()
+            .find(|(classes, _)| classes == &["RobjcLoadObserved"])
+            .unwrap();
+        assert!(selectors.is_empty());
+    }
+}
@@ -1,9 +1,36 @@
+use std::mem;
+
 use super::class::ObjcClass;
 use super::method::Procedure;
 use super::object::ObjcObject;
 use super::ptr::{NilablePtr, Ptr};
+use super::selector::{ObjcSelector, StaticSelector};
+#[cfg(feature = "stats")]
+use super::stats;
+use super::trace::{self, Receiver};
 use super::{Id, Imp, Sel};
 
+type ObjectProcedure =
+    extern "C" fn(NilablePtr<ObjcObject>, Ptr<ObjcSelector>) -> NilablePtr<ObjcObject>;
+
+/*
+ * Sends a message taking no arguments and returning an object from Rust.
+ * Returns None if the receiver does not respond to the selector.
+ */
+pub fn send_message(
+    receiver: &Ptr<ObjcObject>,
+    selector: &StaticSelector,
+) -> Option<NilablePtr<ObjcObject>> {
+    let method = receiver
+        .get_class_pointer()
+        .lookup_method(&selector.get())?;
+    let procedure: ObjectProcedure = unsafe { mem::transmute(method.imp().as_ptr()) };
+    Some(procedure(
+        NilablePtr::new(receiver.clone()),
+        method.name().clone(),
+    ))
+}
+
 #[no_mangle]
 pub extern "C" fn objc_msg_lookup(receiver: Id, selector: Sel) -> Imp {
     let procedure = match (receiver.0.as_ref(), selector.0.as_ref()) {
@@ -12,11 +39,27 @@ pub extern "C" fn objc_msg_lookup(receiver: Id, selector: Sel) -> Imp {
         }
         (Some(object), Some(selector)) => {
             let class = object.get_class_pointer();
-            class
-                .resolve_method(selector.clone())
-                .map_or(Procedure::new_null_procedure(), |method| {
-                    method.imp().clone()
-                })
+            #[cfg(feature = "stats")]
+            if !class.is_cached(selector) {
+                stats::cache_miss();
+            }
+            let method = class.resolve_method(selector.clone());
+            #[cfg(feature = "stats")]
+            stats::lookup(class, selector, method.is_some());
+            if trace::is_enabled() {
+                trace::lookup(Receiver::Class(class), selector, method.as_ref());
+            }
+            method.map_or(Procedure::new_null_procedure(), |method| {
+                method.imp().clone()
+            })
+        }
+        (None, Some(selector)) => {
+            #[cfg(feature = "stats")]
+            stats::nil_send();
+            if trace::is_enabled() {
+                trace::lookup(Receiver::Nil, selector, None);
+            }
+            Procedure::new_nil_procedure(selector.get_types())
         }
         _ => Procedure::new_null_procedure(),
     };
@@ -38,12 +81,217 @@ pub extern "C" fn objc_msg_lookup_super(super_data: Ptr<ObjcSuper>, selector: Se
             return Imp(NilablePtr::new(Procedure::new_null_procedure()));
         }
     };
+    if super_data.self_obj.as_ref().is_none() {
+        #[cfg(feature = "stats")]
+        stats::nil_send();
+        if trace::is_enabled() {
+            trace::lookup(Receiver::Nil, &selector, None);
+        }
+        return Imp(NilablePtr::new(Procedure::new_nil_procedure(
+            selector.get_types(),
+        )));
+    }
+    #[cfg(feature = "stats")]
+    if !super_data.super_class.is_cached(&selector) {
+        stats::cache_miss();
+    }
+    let method = super_data.super_class.resolve_method(selector.clone());
+    #[cfg(feature = "stats")]
+    stats::lookup(&super_data.super_class, &selector, method.is_some());
+    if trace::is_enabled() {
+        trace::lookup(
+            Receiver::Super(&super_data.super_class),
+            &selector,
+            method.as_ref(),
+        );
+    }
     Imp(NilablePtr::new(
-        super_data
-            .super_class
-            .resolve_method(selector)
-            .map_or(Procedure::new_null_procedure(), |method| {
-                method.imp().clone()
-            }),
+        method.map_or(Procedure::new_null_procedure(), |method| {
+            method.imp().clone()
+        }),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::hint;
+    use std::mem;
+
+    use super::objc_msg_lookup;
+    use crate::ptr::{NilablePtr, Ptr};
+    use crate::selector::ObjcSelector;
+    use crate::str_ptr::StrPtr;
+    use crate::{Id, Sel};
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Size {
+        width: f32,
+        height: f32,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Pair {
+        key: i64,
+        value: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Triple {
+        a: i64,
+        b: i64,
+        c: i64,
+    }
+
+    /*
+     * The IMP is called through the C calling convention of the target, so that the test
+     * checks the x86_64 or aarch64 convention depending on where it runs.  The registers are
+     * dirtied by a call returning the same type just before.
+     */
+    #[inline(never)]
+    extern "C" fn dirty_registers<R>(value: R) -> R {
+        value
+    }
+
+    fn send_to_nil<R>(name: &'static [u8], types: &'static [u8], dirty: R) -> R {
+        let selector = ObjcSelector::new(StrPtr::from_static(name), StrPtr::from_static(types));
+        let selector = unsafe { Ptr::new(&selector) };
+        let imp = objc_msg_lookup(
+            Id(NilablePtr::nil()),
+            Sel(NilablePtr::new(selector.clone())),
+        );
+        let imp: extern "C" fn(NilablePtr<()>, Ptr<ObjcSelector>) -> R =
+            unsafe { mem::transmute(imp.0.as_ref().unwrap().as_ptr()) };
+        hint::black_box(dirty_registers(dirty));
+        imp(NilablePtr::nil(), selector)
+    }
+
+    #[test]
+    fn nil_receiver() {
+        assert_eq!(send_to_nil(b"intValue\0", b"i16@0:8\0", -1i32), 0);
+        assert_eq!(send_to_nil(b"longValue\0", b"q16@0:8\0", -1i64), 0);
+        assert_eq!(send_to_nil(b"floatValue\0", b"f16@0:8\0", 1.5f32), 0.0);
+        assert_eq!(send_to_nil(b"doubleValue\0", b"d16@0:8\0", 1.5f64), 0.0);
+        assert_eq!(send_to_nil(b"untyped\0", b"\0", 1.5f64), 0.0);
+        assert_eq!(
+            send_to_nil(
+                b"size\0",
+                b"{Size=ff}16@0:8\0",
+                Size {
+                    width: 1.0,
+                    height: 2.0
+                }
+            ),
+            Size {
+                width: 0.0,
+                height: 0.0
+            }
+        );
+        assert_eq!(
+            send_to_nil(b"pair\0", b"{Pair=qd}16@0:8\0", Pair { key: 1, value: 2.0 }),
+            Pair { key: 0, value: 0.0 }
+        );
+        assert_eq!(
+            send_to_nil(
+                b"rect\0",
+                b"{Rect=dddd}16@0:8\0",
+                Rect {
+                    x: 1.0,
+                    y: 2.0,
+                    width: 3.0,
+                    height: 4.0
+                }
+            ),
+            Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0
+            }
+        );
+        assert_eq!(
+            send_to_nil(
+                b"triple\0",
+                b"{Triple=qqq}16@0:8\0",
+                Triple { a: 1, b: 2, c: 3 }
+            ),
+            Triple { a: 0, b: 0, c: 0 }
+        );
+    }
+
+    /*
+     * Homogeneous floating-point aggregates are returned in v0-v3 on aarch64, so the caller does
+     * not pass a pointer in x8, which may hold anything.  The procedure must not write through it.
+     */
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn nil_receiver_float_aggregate() {
+        use std::arch::asm;
+
+        let selector = ObjcSelector::new(
+            StrPtr::from_static(b"rect\0"),
+            StrPtr::from_static(b"{Rect=dddd}16@0:8\0"),
+        );
+        let selector = unsafe { Ptr::new(&selector) };
+        let imp = objc_msg_lookup(
+            Id(NilablePtr::nil()),
+            Sel(NilablePtr::new(selector.clone())),
+        );
+        let imp = imp.0.as_ref().unwrap().as_ptr();
+        let mut canary = [0xffu8; 32];
+        let (x, y, width, height): (f64, f64, f64, f64);
+        unsafe {
+            asm!(
+                "fmov d0, #1.0",
+                "fmov d1, #1.0",
+                "fmov d2, #1.0",
+                "fmov d3, #1.0",
+                "blr {imp}",
+                imp = in(reg) imp,
+                in("x0") 0usize,
+                in("x1") selector.as_ptr(),
+                in("x8") canary.as_mut_ptr(),
+                out("d0") x,
+                out("d1") y,
+                out("d2") width,
+                out("d3") height,
+                clobber_abi("C"),
+            );
+        }
+        assert_eq!((x, y, width, height), (0.0, 0.0, 0.0, 0.0));
+        assert_eq!(canary, [0xffu8; 32]);
+    }
+
+    /*
+     * Selectors registered at run time have no types, so the nil procedure cannot know that a
+     * struct is returned through memory.  It leaves the struct as it is, and returns its address
+     * in rax as the x86_64 ABI requires of such calls.
+     */
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn nil_receiver_untyped_struct() {
+        let selector = ObjcSelector::new(StrPtr::from_static(b"triple\0"), StrPtr::null());
+        let selector = unsafe { Ptr::new(&selector) };
+        let imp = objc_msg_lookup(
+            Id(NilablePtr::nil()),
+            Sel(NilablePtr::new(selector.clone())),
+        );
+        let imp: extern "C" fn(*mut Triple, NilablePtr<()>, Ptr<ObjcSelector>) -> *mut Triple =
+            unsafe { mem::transmute(imp.0.as_ref().unwrap().as_ptr()) };
+        let mut triple = Triple { a: 1, b: 2, c: 3 };
+        let ret = imp(&mut triple, NilablePtr::nil(), selector);
+        assert_eq!(ret, &mut triple as *mut Triple);
+        assert_eq!(triple, Triple { a: 1, b: 2, c: 3 });
+    }
+}
@@ -2,8 +2,9 @@ use std::fmt;
 use std::mem;
 use std::slice;
 
+use super::encoding;
 use super::object::ObjcObject;
-use super::ptr::{NilablePtr, Ptr};
+use super::ptr::{self, NilablePtr, Ptr};
 use super::selector::ObjcSelector;
 use super::str_ptr::StrPtr;
 use super::Int;
@@ -12,7 +13,7 @@ use super::Int;
 pub struct Procedure;
 
 impl Procedure {
-    fn null_procedure(
+    extern "C" fn null_procedure(
         _id: NilablePtr<ObjcObject>,
         _sel: Ptr<ObjcSelector>,
     ) -> NilablePtr<ObjcObject> {
@@ -23,7 +24,7 @@ impl Procedure {
         unsafe { Ptr::new(Procedure::null_procedure as *const Procedure) }
     }
 
-    fn identity_procedure(
+    extern "C" fn identity_procedure(
         zelf: NilablePtr<ObjcObject>,
         _sel: Ptr<ObjcSelector>,
     ) -> NilablePtr<ObjcObject> {
@@ -33,6 +34,229 @@ impl Procedure {
     pub fn new_identity_procedure() -> Ptr<Procedure> {
         unsafe { Ptr::new(Procedure::identity_procedure as *const Procedure) }
     }
+
+    /*
+     * Selects the procedure returning zero for a message to nil from the return type of the
+     * selector.  Without types, e.g. for selectors registered at run time, only register
+     * returned values are zeroed; a struct returned through memory is left as it is, but the
+     * procedure still returns its address as the ABI requires.
+     */
+    pub fn new_nil_procedure(types: &StrPtr) -> Ptr<Procedure> {
+        let return_type = types
+            .as_ref()
+            .map(|types| encoding::skip_type_qualifiers(types.as_bytes()));
+        let procedure = match return_type {
+            Some(t) if nil::is_returned_in_memory(t) => nil::struct_procedure(),
+            Some([b'D', ..]) => nil::long_double_procedure(),
+            _ => nil::procedure(),
+        };
+        unsafe { Ptr::new(procedure) }
+    }
+}
+
+/*
+ * Procedures for messages to nil.  They zero every register a value can be returned in, so
+ * that the caller reads zero whatever the return type is.  Long doubles on x86_64 are returned
+ * on the x87 stack and large structs through memory, so they need their own procedures.
+ */
+mod nil {
+    use super::encoding;
+
+    pub use self::target::{long_double_procedure, procedure, struct_procedure};
+
+    /*
+     * Both on x86_64 and aarch64, aggregates larger than 16 bytes are returned through a
+     * pointer passed by the caller, except on aarch64 for homogeneous floating-point aggregates,
+     * which are returned in v0-v3 whatever their size.
+     */
+    pub fn is_returned_in_memory(t: &[u8]) -> bool {
+        matches!(t.first(), Some(b'{') | Some(b'(') | Some(b'['))
+            && encoding::sizeof_type(t).is_some_and(|size| size > 16)
+            && !(cfg!(target_arch = "aarch64") && is_homogeneous_float_aggregate(t))
+    }
+
+    /*
+     * Structs and arrays of up to four floats or four doubles, counting the members of nested
+     * structs and arrays, as the aarch64 procedure call standard defines them.
+     */
+    pub fn is_homogeneous_float_aggregate(t: &[u8]) -> bool {
+        float_members(t, &mut None).is_some_and(|count| count <= 4)
+    }
+
+    /*
+     * The number of floating-point members of an aggregate, or None if it has members of other
+     * types or of both float and double.
+     */
+    fn float_members(t: &[u8], kind: &mut Option<u8>) -> Option<usize> {
+        let t = encoding::skip_type_qualifiers(t);
+        match *t.first()? {
+            c @ b'f' | c @ b'd' => (*kind.get_or_insert(c) == c).then_some(1),
+            b'{' => {
+                let mut layout = encoding::StructLayout::new(t)?;
+                let mut count = 0;
+                while layout.next_member() {
+                    let (_, _, member) = layout.get_info();
+                    count += float_members(member?, kind)?;
+                }
+                (count > 0).then_some(count)
+            }
+            b'[' => {
+                let element = &t[1 + t[1..].iter().take_while(|c| c.is_ascii_digit()).count()..];
+                let members = float_members(element, kind)?;
+                let length = encoding::sizeof_type(t)? / encoding::sizeof_type(element)?;
+                length.checked_mul(members).filter(|&count| count > 0)
+            }
+            _ => None,
+        }
+    }
+
+    /*
+     * The assembly uses ELF directives.
+     */
+    #[cfg(all(
+        any(target_arch = "x86_64", target_arch = "aarch64"),
+        not(any(target_vendor = "apple", target_os = "windows"))
+    ))]
+    mod target {
+        use std::ptr;
+
+        use super::super::{encoding, ObjcSelector, Procedure};
+
+        extern "C" {
+            fn robjc_nil_procedure();
+            fn robjc_nil_long_double_procedure();
+            fn robjc_nil_struct_procedure();
+        }
+
+        pub fn procedure() -> *const Procedure {
+            robjc_nil_procedure as *const Procedure
+        }
+
+        pub fn long_double_procedure() -> *const Procedure {
+            robjc_nil_long_double_procedure as *const Procedure
+        }
+
+        pub fn struct_procedure() -> *const Procedure {
+            robjc_nil_struct_procedure as *const Procedure
+        }
+
+        fn zero_return_value(ret: *mut u8, selector: &ObjcSelector) {
+            let size = selector
+                .get_types()
+                .as_ref()
+                .and_then(|types| encoding::sizeof_type(types.as_bytes()))
+                .unwrap_or(0);
+            unsafe { ptr::write_bytes(ret, 0, size) };
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        extern "C" fn struct_return(
+            ret: *mut u8,
+            _receiver: usize,
+            selector: &ObjcSelector,
+        ) -> *mut u8 {
+            zero_return_value(ret, selector);
+            ret
+        }
+
+        /*
+         * rdi holds either the nil receiver or the address of a struct returned through
+         * memory, which is to be returned in rax.  So moving it to rax zeroes the value when
+         * the types of the selector are not known, without breaking a struct return.
+         */
+        #[cfg(target_arch = "x86_64")]
+        std::arch::global_asm!(
+            ".pushsection .text",
+            ".globl robjc_nil_procedure",
+            ".hidden robjc_nil_procedure",
+            ".type robjc_nil_procedure, @function",
+            "robjc_nil_procedure:",
+            "mov rax, rdi",
+            "xor edx, edx",
+            "xorps xmm0, xmm0",
+            "xorps xmm1, xmm1",
+            "ret",
+            ".globl robjc_nil_long_double_procedure",
+            ".hidden robjc_nil_long_double_procedure",
+            ".type robjc_nil_long_double_procedure, @function",
+            "robjc_nil_long_double_procedure:",
+            "xor eax, eax",
+            "fldz",
+            "ret",
+            ".globl robjc_nil_struct_procedure",
+            ".hidden robjc_nil_struct_procedure",
+            ".type robjc_nil_struct_procedure, @function",
+            "robjc_nil_struct_procedure:",
+            "jmp {struct_return}",
+            ".popsection",
+            struct_return = sym struct_return,
+        );
+
+        /*
+         * On aarch64 the pointer to the returned struct is passed in x8, which is moved to the
+         * third argument before calling into Rust.
+         */
+        #[cfg(target_arch = "aarch64")]
+        extern "C" fn struct_return(_receiver: usize, selector: &ObjcSelector, ret: *mut u8) {
+            zero_return_value(ret, selector);
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        std::arch::global_asm!(
+            ".pushsection .text",
+            ".globl robjc_nil_procedure",
+            ".hidden robjc_nil_procedure",
+            ".type robjc_nil_procedure, %function",
+            "robjc_nil_procedure:",
+            "mov x0, xzr",
+            "mov x1, xzr",
+            "movi v0.2d, #0",
+            "movi v1.2d, #0",
+            "movi v2.2d, #0",
+            "movi v3.2d, #0",
+            "ret",
+            ".globl robjc_nil_long_double_procedure",
+            ".hidden robjc_nil_long_double_procedure",
+            ".type robjc_nil_long_double_procedure, %function",
+            "robjc_nil_long_double_procedure:",
+            "b robjc_nil_procedure",
+            ".globl robjc_nil_struct_procedure",
+            ".hidden robjc_nil_struct_procedure",
+            ".type robjc_nil_struct_procedure, %function",
+            "robjc_nil_struct_procedure:",
+            "mov x2, x8",
+            "b {struct_return}",
+            ".popsection",
+            struct_return = sym struct_return,
+        );
+    }
+
+    /*
+     * ToDo: zero the floating point and struct return values on other targets
+     */
+    #[cfg(not(all(
+        any(target_arch = "x86_64", target_arch = "aarch64"),
+        not(any(target_vendor = "apple", target_os = "windows"))
+    )))]
+    mod target {
+        use super::super::Procedure;
+
+        extern "C" fn zero_procedure() -> usize {
+            0
+        }
+
+        pub fn procedure() -> *const Procedure {
+            zero_procedure as *const Procedure
+        }
+
+        pub fn long_double_procedure() -> *const Procedure {
+            zero_procedure as *const Procedure
+        }
+
+        pub fn struct_procedure() -> *const Procedure {
+            zero_procedure as *const Procedure
+        }
+    }
 }
 
 #[repr(C)]
@@ -44,6 +268,17 @@ pub struct ObjcMethod {
 }
 
 impl ObjcMethod {
+    /*
+     * Creates a method in the compiled form, whose name is linked to a selector when loaded.
+     */
+    pub fn new(name: StrPtr, types: StrPtr, imp: Ptr<Procedure>) -> ObjcMethod {
+        ObjcMethod {
+            method_name: unsafe { mem::transmute::<StrPtr, Ptr<ObjcSelector>>(name) },
+            method_types: types,
+            method_imp: imp,
+        }
+    }
+
     pub fn name(&self) -> &Ptr<ObjcSelector> {
         &self.method_name
     }
@@ -56,6 +291,24 @@ impl ObjcMethod {
         &self.method_imp
     }
 
+    /*
+     * None if the method types are invalid.
+     */
+    pub fn number_of_arguments(&self) -> Option<usize> {
+        let types = self.method_types.as_ref().unwrap_or("");
+        encoding::number_of_arguments(types.as_bytes())
+    }
+
+    /*
+     * The size of the argument frame is recorded as the offset following the return type.
+     */
+    pub fn sizeof_arguments(&self) -> usize {
+        let types = self.method_types.as_ref().unwrap_or("");
+        encoding::skip_typespec(types.as_bytes())
+            .and_then(encoding::parse_offset)
+            .map_or(0, |(_, size)| size as usize)
+    }
+
     pub unsafe fn link_to_selector(&mut self, mut name: Ptr<ObjcSelector>) -> Ptr<ObjcSelector> {
         mem::swap(&mut self.method_name, &mut name);
         name
@@ -119,6 +372,15 @@ pub struct ObjcMethodList {
 }
 
 impl ObjcMethodList {
+    pub fn new(methods: Vec<ObjcMethod>) -> Ptr<ObjcMethodList> {
+        let header = ObjcMethodList {
+            method_next: None,
+            method_count: methods.len() as Int,
+            method_list: [],
+        };
+        ptr::leak_with_trailing(header, methods)
+    }
+
     fn get_next(&self) -> Option<Ptr<ObjcMethodList>> {
         self.method_next.clone()
     }
@@ -165,3 +427,60 @@ impl fmt::Display for ObjcMethodList {
         write!(f, "]")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{nil, ObjcMethod, Procedure};
+    use crate::ptr::Ptr;
+    use crate::selector::ObjcSelector;
+    use crate::str_ptr::StrPtr;
+
+    fn method(name: &'static [u8], types: &'static [u8]) -> ObjcMethod {
+        let selector = Box::new(ObjcSelector::new(StrPtr::from_static(name), StrPtr::null()));
+        ObjcMethod {
+            method_name: unsafe { Ptr::new(Box::into_raw(selector)) },
+            method_types: StrPtr::from_static(types),
+            method_imp: Procedure::new_null_procedure(),
+        }
+    }
+
+    #[test]
+    fn arguments() {
+        // + (int) sum: (int)firstNumber, ...;
+        let sum = method(b"sum:\0", b"i20@0:8i16\0");
+        assert_eq!(sum.number_of_arguments(), Some(3));
+        assert_eq!(sum.sizeof_arguments(), 20);
+
+        // - (bycopy id) bycopyMethod;
+        let bycopy = method(b"bycopyMethod\0", b"O@16@0:8\0");
+        assert_eq!(bycopy.number_of_arguments(), Some(2));
+        assert_eq!(bycopy.sizeof_arguments(), 16);
+
+        // - (oneway void) setOrigin: (in struct Point)origin count: (inout int *)count;
+        let oneway = method(b"setOrigin:count:\0", b"Vv+40@0:8n{Point=dd}16N^i+32\0");
+        assert_eq!(oneway.number_of_arguments(), Some(4));
+        assert_eq!(oneway.sizeof_arguments(), 40);
+
+        let invalid = method(b"invalid\0", b"{Point=dd\0");
+        assert_eq!(invalid.number_of_arguments(), None);
+        assert_eq!(invalid.sizeof_arguments(), 0);
+    }
+
+    #[test]
+    fn float_aggregates() {
+        assert!(nil::is_homogeneous_float_aggregate(b"{Rect=dddd}"));
+        assert!(nil::is_homogeneous_float_aggregate(
+            b"{Rect={Point=dd}{Size=dd}}"
+        ));
+        assert!(nil::is_homogeneous_float_aggregate(b"{Quad=[4f]}"));
+        assert!(!nil::is_homogeneous_float_aggregate(b"{Five=ddddd}"));
+        assert!(!nil::is_homogeneous_float_aggregate(b"{Mixed=fd}"));
+        assert!(!nil::is_homogeneous_float_aggregate(b"{Pair=qd}"));
+        assert!(!nil::is_homogeneous_float_aggregate(b"{Empty=}"));
+
+        /* Only aarch64 returns the larger ones in registers. */
+        let in_memory = nil::is_returned_in_memory(b"{Rect=dddd}");
+        assert_eq!(in_memory, cfg!(not(target_arch = "aarch64")));
+        assert!(nil::is_returned_in_memory(b"{Triple=qqq}"));
+    }
+}
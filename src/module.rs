@@ -1,9 +1,9 @@
-use std::collections::HashMap;
 use std::fmt;
 use std::slice;
 
 use super::category::ObjcCategory;
 use super::class::ObjcClass;
+use super::object::ObjcObject;
 use super::ptr::Ptr;
 use super::selector::ObjcSelector;
 use super::str_ptr::StrPtr;
@@ -20,12 +20,6 @@ pub struct ObjcSymtab {
     defs: [Ptr<()>; 0],
 }
 
-pub type SelectorMap = HashMap<StrPtr, Ptr<ObjcSelector>>;
-/*
- * // ToDo: currently, ignore types
-pub type SelectorMap = HashMap<(StrPtr, StrPtr), Ptr<ObjcSelector>>;
- */
-
 impl ObjcSymtab {
     pub fn cls_def_cnt(&self) -> usize {
         self.cls_def_cnt as usize
@@ -35,6 +29,7 @@ impl ObjcSymtab {
         self.cat_def_cnt as usize
     }
 
+    #[allow(clippy::mut_from_ref)]
     fn nth_def<T>(&self, i: usize) -> Option<&mut Ptr<T>> {
         let num_entries = self.cls_def_cnt() + self.cat_def_cnt();
         if i >= num_entries {
@@ -82,20 +77,53 @@ impl ObjcSymtab {
         ObjcSelectorIterator(self.refs.clone())
     }
 
-    pub fn create_selector_map(&self) -> SelectorMap {
-        let mut map = HashMap::new();
-        for selector in self.iter_selector() {
-            let id = selector.as_ref().get_id().clone();
-            assert_ne!(id, StrPtr::null());
-            let _types = selector.as_ref().get_types().clone();
-            /*
-             * // ToDo: currently, ignore types
-             * assert_ne!(_types, StrPtr::null());
-             * map.insert((id, _types), selector);
-             */
-            map.insert(id, selector);
+    /*
+     * The definitions are followed by a pointer to the nil-terminated list of static instances,
+     * e.g. constant strings, which is null if the module has none.
+     */
+    pub fn iter_static_instances(&self) -> impl Iterator<Item = Ptr<ObjcStaticInstances>> {
+        type List = Ptr<Option<Ptr<ObjcStaticInstances>>>;
+        let index = self.cls_def_cnt() + self.cat_def_cnt();
+        let list = unsafe {
+            let defs = &self.defs as *const Ptr<()> as *const Option<List>;
+            (*defs.add(index)).clone()
+        };
+        (0..).map_while(move |i| unsafe { (*list.as_ref()?.as_ptr().add(i)).clone() })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ObjcStaticInstances {
+    class_name: StrPtr,
+    instances: [Ptr<ObjcObject>; 0],
+}
+
+impl ObjcStaticInstances {
+    pub fn class_name(&self) -> &StrPtr {
+        &self.class_name
+    }
+
+    /*
+     * The instances are terminated by nil.
+     */
+    pub fn iter_instance(&self) -> impl Iterator<Item = Ptr<ObjcObject>> + '_ {
+        let instances = self.instances.as_ptr() as *const Option<Ptr<ObjcObject>>;
+        (0..).map_while(move |i| unsafe { (*instances.add(i)).clone() })
+    }
+}
+
+impl fmt::Display for ObjcStaticInstances {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "StaticInstances @ {:p} [ class_name: {}, instances:",
+            self, self.class_name
+        )?;
+        for instance in self.iter_instance() {
+            write!(f, " {:p}", instance.as_ptr())?;
         }
-        map
+        write!(f, " ]")
     }
 }
 
@@ -114,7 +142,7 @@ impl Iterator for ObjcClassIterator {
             return None;
         }
         self.index += 1;
-        symtab.nth_class_ptr(index).map(|p| p.clone())
+        symtab.nth_class_ptr(index).cloned()
     }
 }
 
@@ -133,7 +161,7 @@ impl Iterator for ObjcCategoryIterator {
             return None;
         }
         self.index += 1;
-        symtab.nth_category_ptr(index).map(|p| p.clone())
+        symtab.nth_category_ptr(index).cloned()
     }
 }
 
@@ -189,6 +217,10 @@ pub struct ObjcModule {
 }
 
 impl ObjcModule {
+    pub fn name(&self) -> &StrPtr {
+        &self.name
+    }
+
     pub fn symtab(&self) -> &Ptr<ObjcSymtab> {
         &self.symtab
     }
@@ -196,6 +228,13 @@ impl ObjcModule {
     pub fn symtab_mut(&mut self) -> &mut Ptr<ObjcSymtab> {
         &mut self.symtab
     }
+
+    /*
+     * Categories carry property lists since the ABI version 9 of the GNUstep runtime.
+     */
+    pub fn has_category_properties(&self) -> bool {
+        self.version >= 9
+    }
 }
 
 impl fmt::Display for ObjcModule {
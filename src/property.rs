@@ -0,0 +1,596 @@
+use std::fmt;
+use std::hint;
+use std::mem;
+use std::os::raw;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::encoding;
+use super::message;
+use super::object::ObjcObject;
+use super::ptr::{NilablePtr, Ptr};
+use super::selector::StaticSelector;
+use super::str_ptr::StrPtr;
+use super::{Bool, Id, Int, Sel};
+
+const NUM_SPINLOCKS: usize = 16;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const UNLOCKED: AtomicBool = AtomicBool::new(false);
+
+static SPINLOCKS: [AtomicBool; NUM_SPINLOCKS] = [UNLOCKED; NUM_SPINLOCKS];
+
+fn spinlock_index(address: *const raw::c_void) -> usize {
+    let address = address as usize;
+    ((address >> 8) ^ (address >> 4)) & (NUM_SPINLOCKS - 1)
+}
+
+struct SpinlockGuard(&'static AtomicBool);
+
+impl SpinlockGuard {
+    fn lock_index(index: usize) -> SpinlockGuard {
+        let lock = &SPINLOCKS[index];
+        while lock
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        SpinlockGuard(lock)
+    }
+
+    fn lock(address: *const raw::c_void) -> SpinlockGuard {
+        SpinlockGuard::lock_index(spinlock_index(address))
+    }
+
+    /*
+     * Locks are always taken in index order so that two copies in opposite directions cannot
+     * deadlock.
+     */
+    fn lock_pair(
+        a: *const raw::c_void,
+        b: *const raw::c_void,
+    ) -> (SpinlockGuard, Option<SpinlockGuard>) {
+        let (first, second) = {
+            let (a, b) = (spinlock_index(a), spinlock_index(b));
+            (a.min(b), a.max(b))
+        };
+        let first_guard = SpinlockGuard::lock_index(first);
+        let second_guard = if first != second {
+            Some(SpinlockGuard::lock_index(second))
+        } else {
+            None
+        };
+        (first_guard, second_guard)
+    }
+}
+
+impl Drop for SpinlockGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/*
+ * Objects which do not implement the reference counting methods are treated as if the methods
+ * were no-ops.
+ */
+fn send_or_identity(
+    object: NilablePtr<ObjcObject>,
+    selector: &StaticSelector,
+) -> NilablePtr<ObjcObject> {
+    match object.as_ref() {
+        Some(receiver) => message::send_message(receiver, selector).unwrap_or(object.clone()),
+        None => object,
+    }
+}
+
+fn retain(object: NilablePtr<ObjcObject>) -> NilablePtr<ObjcObject> {
+    static RETAIN: StaticSelector = StaticSelector::new(b"retain\0");
+    send_or_identity(object, &RETAIN)
+}
+
+fn copy(object: NilablePtr<ObjcObject>) -> NilablePtr<ObjcObject> {
+    static COPY: StaticSelector = StaticSelector::new(b"copy\0");
+    send_or_identity(object, &COPY)
+}
+
+fn autorelease(object: NilablePtr<ObjcObject>) -> NilablePtr<ObjcObject> {
+    static AUTORELEASE: StaticSelector = StaticSelector::new(b"autorelease\0");
+    send_or_identity(object, &AUTORELEASE)
+}
+
+fn release(object: NilablePtr<ObjcObject>) {
+    static RELEASE: StaticSelector = StaticSelector::new(b"release\0");
+    send_or_identity(object, &RELEASE);
+}
+
+fn ivar_slot(object: &ObjcObject, offset: isize) -> *mut NilablePtr<ObjcObject> {
+    unsafe {
+        (object as *const ObjcObject as *mut u8).offset(offset) as *mut NilablePtr<ObjcObject>
+    }
+}
+
+fn store_ivar(
+    object: &ObjcObject,
+    offset: isize,
+    value: NilablePtr<ObjcObject>,
+    is_atomic: bool,
+) -> NilablePtr<ObjcObject> {
+    let slot = ivar_slot(object, offset);
+    let _guard = if is_atomic {
+        Some(SpinlockGuard::lock(slot as *const raw::c_void))
+    } else {
+        None
+    };
+    unsafe { ptr::replace(slot, value) }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_getProperty(zelf: Id, _cmd: Sel, offset: isize, is_atomic: Bool) -> Id {
+    let object = match zelf.0.as_ref() {
+        Some(object) => object,
+        None => return Id(NilablePtr::nil()),
+    };
+    let slot = ivar_slot(object, offset);
+    if !bool::from(is_atomic) {
+        return Id(unsafe { (*slot).clone() });
+    }
+    let value = {
+        let _guard = SpinlockGuard::lock(slot as *const raw::c_void);
+        retain(unsafe { (*slot).clone() })
+    };
+    Id(autorelease(value))
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_setProperty(
+    zelf: Id,
+    _cmd: Sel,
+    offset: isize,
+    new_value: Id,
+    is_atomic: Bool,
+    should_copy: Bool,
+) {
+    let object = match zelf.0.as_ref() {
+        Some(object) => object,
+        None => return,
+    };
+    let new_value = if bool::from(should_copy) {
+        copy(new_value.0)
+    } else {
+        retain(new_value.0)
+    };
+    let old_value = store_ivar(object, offset, new_value, bool::from(is_atomic));
+    release(old_value);
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_setProperty_nonatomic_copy(
+    zelf: Id,
+    _cmd: Sel,
+    new_value: Id,
+    offset: isize,
+) {
+    let object = match zelf.0.as_ref() {
+        Some(object) => object,
+        None => return,
+    };
+    let old_value = store_ivar(object, offset, copy(new_value.0), false);
+    release(old_value);
+}
+
+/*
+ * Struct properties are copied by value; has_strong is only meaningful for garbage collection.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_getPropertyStruct(
+    destination: *mut raw::c_void,
+    source: *const raw::c_void,
+    size: isize,
+    is_atomic: Bool,
+    _has_strong: Bool,
+) {
+    let _guard = if bool::from(is_atomic) {
+        Some(SpinlockGuard::lock(source))
+    } else {
+        None
+    };
+    unsafe { ptr::copy_nonoverlapping(source as *const u8, destination as *mut u8, size as usize) };
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_setPropertyStruct(
+    destination: *mut raw::c_void,
+    source: *const raw::c_void,
+    size: isize,
+    is_atomic: Bool,
+    _has_strong: Bool,
+) {
+    let _guard = if bool::from(is_atomic) {
+        Some(SpinlockGuard::lock(destination))
+    } else {
+        None
+    };
+    unsafe { ptr::copy_nonoverlapping(source as *const u8, destination as *mut u8, size as usize) };
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_copyStruct(
+    destination: *mut raw::c_void,
+    source: *const raw::c_void,
+    size: isize,
+    is_atomic: Bool,
+    _has_strong: Bool,
+) {
+    let _guards = if bool::from(is_atomic) {
+        Some(SpinlockGuard::lock_pair(source, destination))
+    } else {
+        None
+    };
+    unsafe { ptr::copy_nonoverlapping(source as *const u8, destination as *mut u8, size as usize) };
+}
+
+/*
+ * Property attribute flags of the GNUstep ABIs.
+ */
+const OBJC_PR_READONLY: u8 = 1 << 0;
+const OBJC_PR_GETTER: u8 = 1 << 1;
+const OBJC_PR_RETAIN: u8 = 1 << 4;
+const OBJC_PR_COPY: u8 = 1 << 5;
+const OBJC_PR_NONATOMIC: u8 = 1 << 6;
+const OBJC_PR_SETTER: u8 = 1 << 7;
+
+const OBJC_PR_DYNAMIC: u8 = 1 << 1;
+const OBJC_PR_WEAK: u8 = 1 << 3;
+const OBJC_PR_STRONG: u8 = 1 << 4;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ObjcProperty {
+    name: StrPtr,
+    attributes: u8,
+    attributes2: u8,
+    _unused1: u8,
+    _unused2: u8,
+    getter_name: StrPtr,
+    getter_types: StrPtr,
+    setter_name: StrPtr,
+    setter_types: StrPtr,
+}
+
+/*
+ * Returns the leading type of a method type encoding, i.e. the return type of a getter.
+ */
+fn return_type(types: &str) -> &str {
+    match encoding::skip_typespec(types.as_bytes()) {
+        Some(rest) => &types[..types.len() - rest.len()],
+        None => types,
+    }
+}
+
+impl ObjcProperty {
+    pub fn name(&self) -> &StrPtr {
+        &self.name
+    }
+
+    /*
+     * Builds the attribute string in the format documented for property_getAttributes, e.g.
+     * "T@,&,N,Gvalue".
+     */
+    pub fn attributes(&self) -> String {
+        let mut attributes = vec![format!(
+            "T{}",
+            self.getter_types.as_ref().map_or("", return_type)
+        )];
+        if self.attributes & OBJC_PR_READONLY != 0 {
+            attributes.push("R".to_string());
+        }
+        if self.attributes & OBJC_PR_COPY != 0 {
+            attributes.push("C".to_string());
+        } else if self.attributes & OBJC_PR_RETAIN != 0 || self.attributes2 & OBJC_PR_STRONG != 0 {
+            attributes.push("&".to_string());
+        } else if self.attributes2 & OBJC_PR_WEAK != 0 {
+            attributes.push("W".to_string());
+        }
+        if self.attributes & OBJC_PR_NONATOMIC != 0 {
+            attributes.push("N".to_string());
+        }
+        if self.attributes & OBJC_PR_GETTER != 0 {
+            if let Some(getter_name) = self.getter_name.as_ref() {
+                attributes.push(format!("G{}", getter_name));
+            }
+        }
+        if self.attributes & OBJC_PR_SETTER != 0 {
+            if let Some(setter_name) = self.setter_name.as_ref() {
+                attributes.push(format!("S{}", setter_name));
+            }
+        }
+        if self.attributes2 & OBJC_PR_DYNAMIC != 0 {
+            attributes.push("D".to_string());
+        }
+        attributes.join(",")
+    }
+}
+
+impl fmt::Display for ObjcProperty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Property @ {:p} [ name: {}, attributes: {}, getter: {} ({}), setter: {} ({}) ]",
+            self,
+            self.name,
+            self.attributes(),
+            self.getter_name,
+            self.getter_types,
+            self.setter_name,
+            self.setter_types
+        )
+    }
+}
+
+pub struct ObjcPropertyIterator {
+    current_list: Option<Ptr<ObjcPropertyList>>,
+    index: usize,
+}
+
+impl Iterator for ObjcPropertyIterator {
+    type Item = Ptr<ObjcProperty>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let list = self.current_list.clone()?;
+        match list.nth_property(self.index) {
+            Some(property) => {
+                self.index += 1;
+                Some(property)
+            }
+            None => {
+                self.current_list = list.property_next.clone();
+                self.index = 0;
+                self.next()
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ObjcPropertyList {
+    property_count: Int,
+    property_next: Option<Ptr<ObjcPropertyList>>,
+    property_list: [ObjcProperty; 0],
+}
+
+impl ObjcPropertyList {
+    fn property_count(&self) -> usize {
+        self.property_count as usize
+    }
+
+    fn nth_property(&self, i: usize) -> Option<Ptr<ObjcProperty>> {
+        let count = self.property_count();
+        if i >= count {
+            return None;
+        }
+        unsafe {
+            let list = (self as *const ObjcPropertyList).offset(1) as *const ObjcProperty;
+            let list = slice::from_raw_parts(list, count);
+            Some(Ptr::new(&list[i]))
+        }
+    }
+
+    pub fn iter(&self) -> ObjcPropertyIterator {
+        ObjcPropertyIterator {
+            current_list: Some(unsafe { Ptr::new(self) }),
+            index: 0,
+        }
+    }
+}
+
+impl fmt::Display for ObjcPropertyList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "PropertyList @ {:p} [ count: {}, list:",
+            self, self.property_count
+        )?;
+        for i in 0..self.property_count() {
+            if let Some(property) = self.nth_property(i) {
+                writeln!(f, "  ({}) {},", i, property.as_ref())?;
+            } else {
+                unreachable!()
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct PropertyAttribute {
+    name: StrPtr,
+    value: StrPtr,
+}
+
+/*
+ * Splits an attribute string into (name, value) pairs.  Attribute names are single letters.
+ */
+pub fn parse_attributes(attributes: &str) -> Vec<(&str, &str)> {
+    attributes
+        .split(',')
+        .filter(|attribute| !attribute.is_empty())
+        .map(|attribute| attribute.split_at(1))
+        .collect()
+}
+
+/*
+ * Copies the attributes into a single block allocated with malloc, so that the caller can
+ * release it with one call to free.
+ */
+pub fn copy_attribute_list(attributes: &[(&str, &str)]) -> *mut PropertyAttribute {
+    let array_size = mem::size_of::<PropertyAttribute>() * (attributes.len() + 1);
+    let strings_size: usize = attributes
+        .iter()
+        .map(|(name, value)| name.len() + value.len() + 2)
+        .sum();
+    unsafe {
+        let block = libc::calloc(1, array_size + strings_size) as *mut u8;
+        if block.is_null() {
+            return ptr::null_mut();
+        }
+        let array = block as *mut PropertyAttribute;
+        let mut string = block.add(array_size);
+        let mut copy_string = |s: &str| {
+            ptr::copy_nonoverlapping(s.as_ptr(), string, s.len());
+            let p = StrPtr::from_ptr(string as *const raw::c_char);
+            string = string.add(s.len() + 1);
+            p
+        };
+        for (i, (name, value)) in attributes.iter().enumerate() {
+            let name = copy_string(name);
+            let value = copy_string(value);
+            ptr::write(array.add(i), PropertyAttribute { name, value });
+        }
+        array
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    use super::StrPtr;
+    use super::{
+        parse_attributes, ObjcProperty, ObjcPropertyList, OBJC_PR_COPY, OBJC_PR_NONATOMIC,
+        OBJC_PR_READONLY,
+    };
+    use crate::declare::ClassDecl;
+    use crate::module::ObjcModule;
+    use crate::ptr::{NilablePtr, Ptr};
+    use crate::runtime::{class_copyPropertyList, property_getName};
+    use crate::Class;
+
+    /*
+     * A module of the GNUstep ABI 9 whose only definition is a category, laid out as compilers
+     * emit it.
+     */
+    #[repr(C)]
+    struct PropertyList {
+        list: ObjcPropertyList,
+        property: ObjcProperty,
+    }
+
+    #[repr(C)]
+    struct Category {
+        category_name: StrPtr,
+        class_name: StrPtr,
+        instance_methods: *const u8,
+        class_methods: *const u8,
+        protocols: *const u8,
+        properties: *const PropertyList,
+    }
+
+    #[repr(C)]
+    struct Symtab {
+        sel_ref_cnt: libc::c_ulong,
+        refs: *const u8,
+        cls_def_cnt: libc::c_ushort,
+        cat_def_cnt: libc::c_ushort,
+        defs: [*const Category; 2],
+    }
+
+    #[repr(C)]
+    struct Module {
+        version: libc::c_ulong,
+        size: libc::c_ulong,
+        name: StrPtr,
+        symtab: *const Symtab,
+    }
+
+    #[test]
+    fn attributes() {
+        let property = ObjcProperty {
+            name: StrPtr::from_static(b"origin\0"),
+            attributes: OBJC_PR_READONLY | OBJC_PR_COPY | OBJC_PR_NONATOMIC,
+            attributes2: 0,
+            _unused1: 0,
+            _unused2: 0,
+            getter_name: StrPtr::from_static(b"origin\0"),
+            getter_types: StrPtr::from_static(b"{Point=[2d]}16@0:8\0"),
+            setter_name: StrPtr::null(),
+            setter_types: StrPtr::null(),
+        };
+        assert_eq!(property.attributes(), "T{Point=[2d]},R,C,N");
+        assert_eq!(
+            parse_attributes(&property.attributes()),
+            vec![("T", "{Point=[2d]}"), ("R", ""), ("C", ""), ("N", "")]
+        );
+    }
+
+    #[test]
+    fn category_before_class() {
+        let properties = Box::leak(Box::new(PropertyList {
+            list: ObjcPropertyList {
+                property_count: 1,
+                property_next: None,
+                property_list: [],
+            },
+            property: ObjcProperty {
+                name: StrPtr::from_static(b"robjcLate\0"),
+                attributes: OBJC_PR_READONLY,
+                attributes2: 0,
+                _unused1: 0,
+                _unused2: 0,
+                getter_name: StrPtr::from_static(b"robjcLate\0"),
+                getter_types: StrPtr::from_static(b"i16@0:8\0"),
+                setter_name: StrPtr::null(),
+                setter_types: StrPtr::null(),
+            },
+        }));
+        let category = Box::leak(Box::new(Category {
+            category_name: StrPtr::from_static(b"Late\0"),
+            class_name: StrPtr::from_static(b"RobjcCategoryFirst\0"),
+            instance_methods: ptr::null(),
+            class_methods: ptr::null(),
+            protocols: ptr::null(),
+            properties,
+        }));
+        let symtab = Box::leak(Box::new(Symtab {
+            sel_ref_cnt: 0,
+            refs: ptr::null(),
+            cls_def_cnt: 0,
+            cat_def_cnt: 1,
+            defs: [category, ptr::null()],
+        }));
+        let module = Box::leak(Box::new(Module {
+            version: 9,
+            size: std::mem::size_of::<Module>() as libc::c_ulong,
+            name: StrPtr::from_static(b"category_before_class.m\0"),
+            symtab,
+        }));
+        crate::__objc_exec_class(unsafe { &mut *(module as *mut Module as *mut ObjcModule) });
+
+        let class = ClassDecl::root("RobjcCategoryFirst")
+            .unwrap()
+            .register()
+            .unwrap();
+        let mut count = 0;
+        let list = class_copyPropertyList(
+            Class(NilablePtr::new(unsafe { Ptr::new(class.as_ptr()) })),
+            Some(&mut count),
+        );
+        assert_eq!(count, 1);
+        unsafe {
+            let name = property_getName(ptr::read(list));
+            assert_eq!(CStr::from_ptr(name.as_ptr()).to_str(), Ok("robjcLate"));
+            libc::free(list as *mut libc::c_void);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::slice;
+
+use super::ptr::{self, Ptr};
+use super::str_ptr::StrPtr;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ObjcProtocol {
+    class_pointer: Option<Ptr<()>>,
+    protocol_name: StrPtr,
+    protocol_list: Option<Ptr<ObjcProtocolList>>,
+    instance_methods: Option<Ptr<()>>,
+    class_methods: Option<Ptr<()>>,
+}
+
+impl ObjcProtocol {
+    pub fn name(&self) -> &StrPtr {
+        &self.protocol_name
+    }
+
+    /*
+     * Protocols conform to themselves and to the protocols they incorporate.  Protocols are
+     * compared by name, as each module emits its own copy.
+     */
+    pub fn conforms_to(&self, name: &StrPtr) -> bool {
+        &self.protocol_name == name
+            || self
+                .protocol_list
+                .as_ref()
+                .is_some_and(|list| list.iter().any(|protocol| protocol.conforms_to(name)))
+    }
+
+    pub fn protocols(&self) -> Option<&Ptr<ObjcProtocolList>> {
+        self.protocol_list.as_ref()
+    }
+}
+
+impl fmt::Display for ObjcProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Protocol @ {:p} [ name: {} ]", self, self.protocol_name)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ObjcProtocolList {
+    next: Option<Ptr<ObjcProtocolList>>,
+    count: usize,
+    list: [Ptr<ObjcProtocol>; 0],
+}
+
+impl ObjcProtocolList {
+    pub fn new(protocols: Vec<Ptr<ObjcProtocol>>) -> Ptr<ObjcProtocolList> {
+        let header = ObjcProtocolList {
+            next: None,
+            count: protocols.len(),
+            list: [],
+        };
+        ptr::leak_with_trailing(header, protocols)
+    }
+
+    pub fn next(&self) -> Option<&Ptr<ObjcProtocolList>> {
+        self.next.as_ref()
+    }
+
+    pub fn set_next(&mut self, next: Option<Ptr<ObjcProtocolList>>) {
+        self.next = next;
+    }
+
+    fn protocols(&self) -> &[Ptr<ObjcProtocol>] {
+        unsafe {
+            let list = (self as *const ObjcProtocolList).offset(1) as *const Ptr<ObjcProtocol>;
+            slice::from_raw_parts(list, self.count)
+        }
+    }
+
+    /*
+     * Iterates the protocols of this list and the lists chained to it.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = Ptr<ObjcProtocol>> + '_ {
+        let mut current = Some(self);
+        std::iter::from_fn(move || {
+            let list = current?;
+            current = list.next.as_ref().map(|next| next.as_ref());
+            Some(list.protocols().iter().cloned())
+        })
+        .flatten()
+    }
+}
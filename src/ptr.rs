@@ -1,3 +1,4 @@
+use std::alloc;
 use std::cmp;
 use std::convert;
 use std::hash;
@@ -62,9 +63,15 @@ impl<T> hash::Hash for Ptr<T> {
 }
 
 #[repr(transparent)]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct NilablePtr<T>(Option<Ptr<T>>);
 
+impl<T> Clone for NilablePtr<T> {
+    fn clone(&self) -> Self {
+        NilablePtr(self.0.clone())
+    }
+}
+
 impl<T> NilablePtr<T> {
     #[allow(dead_code)]
     pub fn new(ptr: Ptr<T>) -> NilablePtr<T> {
@@ -89,3 +96,26 @@ impl<T> convert::From<Option<Ptr<T>>> for NilablePtr<T> {
         NilablePtr::wrap(ptr)
     }
 }
+
+/*
+ * Allocates a header followed by an array of items, the layout of lists emitted by the
+ * compiler, e.g. method lists.  The allocation is never freed as compiled lists are not.
+ */
+pub fn leak_with_trailing<H, T>(header: H, items: Vec<T>) -> Ptr<H> {
+    assert_eq!(mem::size_of::<H>() % mem::align_of::<T>(), 0);
+    let size = mem::size_of::<H>() + mem::size_of::<T>() * items.len();
+    let align = cmp::max(mem::align_of::<H>(), mem::align_of::<T>());
+    let layout = alloc::Layout::from_size_align(size, align).unwrap();
+    unsafe {
+        let p = alloc::alloc(layout) as *mut H;
+        if p.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        ptr::write(p, header);
+        let list = p.offset(1) as *mut T;
+        for (i, item) in items.into_iter().enumerate() {
+            ptr::write(list.add(i), item);
+        }
+        Ptr::new(p)
+    }
+}
@@ -0,0 +1,237 @@
+/*
+ * Object, a root class compatible with that of the GNU runtime, for programs which do not link
+ * a Foundation.  Instances are counted by the runtime, as objects of classes which do not
+ * implement retain and release are, and -description returns an NXConstantString made at run
+ * time.
+ */
+
+use std::os::raw;
+
+use super::api::{Class, Object, Protocol, Sel};
+use super::arc;
+use super::class::ObjcClass;
+use super::constant_string;
+use super::context::Context;
+use super::declare::ClassDecl;
+use super::object::ObjcObject;
+use super::ptr::{NilablePtr, Ptr};
+use super::{msg_send, Bool, Id};
+
+pub const CLASS_NAME: &str = "Object";
+
+fn object_ptr(zelf: *mut Object) -> Ptr<ObjcObject> {
+    unsafe { Ptr::new(zelf as *mut ObjcObject) }
+}
+
+fn class_of(zelf: *mut Object) -> Class {
+    unsafe { (*zelf).class() }
+}
+
+fn as_class(zelf: *mut Object) -> Class {
+    Class::from_ptr(unsafe { Ptr::new(zelf as *mut ObjcClass) })
+}
+
+extern "C" fn alloc(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    as_class(zelf).create_instance().as_ptr()
+}
+
+extern "C" fn new(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    unsafe {
+        let object: *mut Object = msg_send![zelf, alloc];
+        msg_send![object, init]
+    }
+}
+
+extern "C" fn class_class(zelf: *mut Object, _cmd: Sel) -> Class {
+    as_class(zelf)
+}
+
+extern "C" fn class_superclass(zelf: *mut Object, _cmd: Sel) -> Option<Class> {
+    as_class(zelf).superclass()
+}
+
+extern "C" fn class_name(zelf: *mut Object, _cmd: Sel) -> *const raw::c_char {
+    as_class(zelf).name_ptr().as_ptr()
+}
+
+extern "C" fn init(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    zelf
+}
+
+/*
+ * Returns nil, as the method of the GNU runtime does.
+ */
+extern "C" fn free(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    super::runtime::object_dispose(Id(NilablePtr::new(object_ptr(zelf))));
+    std::ptr::null_mut()
+}
+
+extern "C" fn dealloc(zelf: *mut Object, _cmd: Sel) {
+    super::runtime::object_dispose(Id(NilablePtr::new(object_ptr(zelf))));
+}
+
+extern "C" fn class(zelf: *mut Object, _cmd: Sel) -> Class {
+    class_of(zelf)
+}
+
+extern "C" fn superclass(zelf: *mut Object, _cmd: Sel) -> Option<Class> {
+    class_of(zelf).superclass()
+}
+
+extern "C" fn name(zelf: *mut Object, _cmd: Sel) -> *const raw::c_char {
+    class_of(zelf).name_ptr().as_ptr()
+}
+
+extern "C" fn retain(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    arc::retain_counted(&object_ptr(zelf));
+    zelf
+}
+
+extern "C" fn release(zelf: *mut Object, _cmd: Sel) {
+    arc::release_counted(&object_ptr(zelf));
+}
+
+extern "C" fn autorelease(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    arc::autorelease_pooled(&object_ptr(zelf));
+    zelf
+}
+
+extern "C" fn retain_count(zelf: *mut Object, _cmd: Sel) -> usize {
+    arc::retain_count(&object_ptr(zelf))
+}
+
+extern "C" fn responds_to_selector(zelf: *mut Object, _cmd: Sel, selector: Option<Sel>) -> Bool {
+    Bool::from(selector.is_some_and(|selector| class_of(zelf).instance_method(selector).is_some()))
+}
+
+extern "C" fn is_kind_of_class(zelf: *mut Object, _cmd: Sel, class: Option<Class>) -> Bool {
+    Bool::from(class.is_some_and(|class| unsafe { (*zelf).is_kind_of(class) }))
+}
+
+extern "C" fn is_member_of_class(zelf: *mut Object, _cmd: Sel, class: Option<Class>) -> Bool {
+    Bool::from(Some(class_of(zelf)) == class)
+}
+
+extern "C" fn conforms_to_protocol(
+    zelf: *mut Object,
+    _cmd: Sel,
+    protocol: Option<Protocol>,
+) -> Bool {
+    Bool::from(protocol.is_some_and(|protocol| class_of(zelf).conforms_to(protocol)))
+}
+
+/*
+ * Returns an autoreleased string such as <Object: 0x5581f2a0>, or nil if the NXConstantString
+ * of a Foundation is not laid out as the built-in one.
+ */
+extern "C" fn description(zelf: *mut Object, _cmd: Sel) -> *mut Object {
+    let text = format!("<{}: {:p}>", class_of(zelf).name(), zelf);
+    match constant_string::create(&text) {
+        Some(string) => {
+            arc::autorelease(&object_ptr(string.as_ptr()));
+            string.as_ptr()
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+pub fn register(ctx: &mut Context) {
+    let mut decl = ClassDecl::with_superclass(CLASS_NAME, None).unwrap();
+    let mut selector = |name| Sel::from_ptr(ctx.register_selector_name(name));
+
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = alloc;
+    decl.add_class_method(selector("alloc"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = new;
+    decl.add_class_method(selector("new"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> Class = class_class;
+    decl.add_class_method(selector("class"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> Option<Class> = class_superclass;
+    decl.add_class_method(selector("superclass"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *const raw::c_char = class_name;
+    decl.add_class_method(selector("name"), f);
+
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = init;
+    decl.add_method(selector("init"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = free;
+    decl.add_method(selector("free"), f);
+    let f: extern "C" fn(*mut Object, Sel) = dealloc;
+    decl.add_method(selector("dealloc"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> Class = class;
+    decl.add_method(selector("class"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> Option<Class> = superclass;
+    decl.add_method(selector("superclass"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *const raw::c_char = name;
+    decl.add_method(selector("name"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = retain;
+    decl.add_method(selector("retain"), f);
+    let f: extern "C" fn(*mut Object, Sel) = release;
+    decl.add_method(selector("release"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = autorelease;
+    decl.add_method(selector("autorelease"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> usize = retain_count;
+    decl.add_method(selector("retainCount"), f);
+    let f: extern "C" fn(*mut Object, Sel, Option<Sel>) -> Bool = responds_to_selector;
+    decl.add_method(selector("respondsToSelector:"), f);
+    let f: extern "C" fn(*mut Object, Sel, Option<Class>) -> Bool = is_kind_of_class;
+    decl.add_method(selector("isKindOfClass:"), f);
+    let f: extern "C" fn(*mut Object, Sel, Option<Class>) -> Bool = is_member_of_class;
+    decl.add_method(selector("isMemberOfClass:"), f);
+    let f: extern "C" fn(*mut Object, Sel, Option<Protocol>) -> Bool = conforms_to_protocol;
+    decl.add_method(selector("conformsToProtocol:"), f);
+    let f: extern "C" fn(*mut Object, Sel) -> *mut Object = description;
+    decl.add_method(selector("description"), f);
+
+    decl.register_in(ctx);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::os::raw;
+
+    use super::CLASS_NAME;
+    use crate::api::{Class, Object, Protocol, Sel};
+    use crate::arc::object_getRetainCount_np;
+    use crate::testing::{self, id};
+    use crate::{msg_send, Bool};
+
+    #[test]
+    fn root_object() {
+        let root = Class::named(CLASS_NAME).unwrap();
+        let class = testing::declare("RobjcRootObjectSub", Some(CLASS_NAME), |_| {});
+        let receiver = class.as_ptr() as *mut Object;
+        unsafe {
+            let superclass: Option<Class> = msg_send![receiver, superclass];
+            assert_eq!(superclass, Some(root));
+            let name: *const raw::c_char = msg_send![receiver, name];
+            assert_eq!(CStr::from_ptr(name).to_str(), Ok("RobjcRootObjectSub"));
+
+            let object: *mut Object = msg_send![receiver, new];
+            let object_class: Class = msg_send![object, class];
+            assert_eq!(object_class, class);
+            let kind: Bool = msg_send![object, isKindOfClass: Some(root)];
+            assert!(bool::from(kind));
+            let member: Bool = msg_send![object, isMemberOfClass: Some(root)];
+            assert!(!bool::from(member));
+            let responds: Bool = msg_send![object, respondsToSelector: Sel::register("init").ok()];
+            assert!(bool::from(responds));
+            let responds: Bool = msg_send![object, respondsToSelector: Sel::register("count").ok()];
+            assert!(!bool::from(responds));
+            let conforms: Bool = msg_send![object, conformsToProtocol: None::<Protocol>];
+            assert!(!bool::from(conforms));
+
+            let description: *mut Object = msg_send![object, description];
+            let c_string: *const raw::c_char = msg_send![description, cString];
+            let expected = format!("<RobjcRootObjectSub: {:p}>", object);
+            assert_eq!(CStr::from_ptr(c_string).to_str(), Ok(&expected[..]));
+
+            let retained: *mut Object = msg_send![object, retain];
+            let count: usize = msg_send![retained, retainCount];
+            assert_eq!(count, 2);
+            let () = msg_send![object, release];
+            assert_eq!(object_getRetainCount_np(id(object)), 1);
+            let freed: *mut Object = msg_send![object, free];
+            assert!(freed.is_null());
+        }
+    }
+}
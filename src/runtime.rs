@@ -1,19 +1,17 @@
+use std::ffi::CString;
 use std::mem;
+use std::os::raw;
 use std::ptr;
 
+use super::arc;
+use super::class::ObjcClass;
 use super::context::CONTEXT;
+use super::encoding;
 use super::object::ObjcObject;
+use super::property::{self, ObjcProperty, PropertyAttribute};
 use super::ptr::{NilablePtr, Ptr};
 use super::str_ptr::StrPtr;
-use super::{Bool, Class, Id, Imp, Method, Sel};
-
-unsafe fn alloc(len: usize) -> (*mut u8, usize) {
-    let word_size = mem::size_of::<usize>();
-    let num_words = (len + word_size - 1) / word_size;
-    let mut vec = Vec::<usize>::with_capacity(num_words);
-    vec.set_len(num_words);
-    (Box::into_raw(vec.into_boxed_slice()) as *mut u8, num_words)
-}
+use super::{Bool, Class, Id, Imp, Long, Method, Property, Sel};
 
 /*
 * Maybe, not called because the function is inlined.
@@ -29,6 +27,133 @@ pub extern "C" fn object_getClass(object: Id) -> Class {
     ))
 }
 
+/*
+ * Whether the object is an instance of the class or of one of its subclasses.  Class objects
+ * are instances of their metaclasses.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_isKindOfClass(object: Id, class: Class) -> Bool {
+    Bool::from(match (object.0.as_ref(), class.0.as_ref()) {
+        (Some(object), Some(class)) => object.get_class_pointer().is_subclass_of(class),
+        _ => false,
+    })
+}
+
+/*
+ * Whether the object is an instance of the class itself, not of a subclass.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_isMemberOfClass(object: Id, class: Class) -> Bool {
+    Bool::from(match (object.0.as_ref(), class.0.as_ref()) {
+        (Some(object), Some(class)) => object.get_class_pointer() == class,
+        _ => false,
+    })
+}
+
+/*
+ * A class is an object whose class is a metaclass.  The class of a metaclass is the root
+ * metaclass, so the info of the object itself tells classes and metaclasses apart.
+ */
+fn object_as_class(object: &Ptr<ObjcObject>) -> Option<&ObjcClass> {
+    if object.get_class_pointer().is_meta() {
+        Some(unsafe { &*(object.as_ptr() as *const ObjcClass) })
+    } else {
+        None
+    }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_isClass(object: Id) -> Bool {
+    Bool::from(
+        object
+            .0
+            .as_ref()
+            .and_then(object_as_class)
+            .is_some_and(|class| class.is_class()),
+    )
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_isMetaClass(object: Id) -> Bool {
+    Bool::from(
+        object
+            .0
+            .as_ref()
+            .and_then(object_as_class)
+            .is_some_and(|class| class.is_meta()),
+    )
+}
+
+/*
+ * Replaces the class of the object, returning the previous one.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_setClass(object: Id, class: Class) -> Class {
+    match (object.0.as_ref(), class.0.as_ref()) {
+        (Some(object), Some(class)) => {
+            let mut object = object.clone();
+            let previous = object.get_class_pointer().clone();
+            object.initialize(class.clone());
+            previous.remove_instance();
+            class.add_instance();
+            Class(NilablePtr::new(previous))
+        }
+        _ => Class(NilablePtr::nil()),
+    }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_getClassName(object: Id) -> StrPtr {
+    match object.0.as_ref() {
+        Some(object) => object.get_class_pointer().get_name().clone(),
+        None => StrPtr::from_static(b"Nil\0"),
+    }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_getSuperclass(object: Id) -> Class {
+    Class(NilablePtr::from(
+        object
+            .0
+            .as_ref()
+            .map(|object| object.get_class_pointer())
+            .filter(|class| class.is_resolved())
+            .and_then(|class| class.super_pointer().clone()),
+    ))
+}
+
+/*
+ * Copies the instance variables and the extra bytes, which the caller must have allocated with
+ * the object.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_copy(object: Id, extra_bytes: usize) -> Id {
+    let source = match object.0.as_ref() {
+        Some(object) => object,
+        None => return Id(NilablePtr::nil()),
+    };
+    let class = source.get_class_pointer().clone();
+    let copy = class_createInstance(Class(NilablePtr::new(class.clone())), extra_bytes);
+    if let Some(copy) = copy.0.as_ref() {
+        unsafe {
+            ptr::copy_nonoverlapping(
+                source.as_ptr() as *const u8,
+                copy.as_ptr() as *mut u8,
+                class.get_instance_size() + extra_bytes,
+            );
+        }
+    }
+    copy
+}
+
 #[allow(non_snake_case)]
 #[no_mangle]
 pub extern "C" fn sel_getName(selector: Sel) -> StrPtr {
@@ -41,27 +166,42 @@ pub extern "C" fn sel_getName(selector: Sel) -> StrPtr {
 
 #[allow(non_snake_case)]
 #[no_mangle]
-pub extern "C" fn sel_getTypeEncoding(_selector: Sel) -> StrPtr {
-    unimplemented!()
+pub extern "C" fn sel_getTypeEncoding(selector: Sel) -> StrPtr {
+    selector
+        .0
+        .as_ref()
+        .map_or(StrPtr::null(), |selector| selector.get_types().clone())
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn sel_getUid(name: StrPtr) -> Sel {
+    sel_registerName(name)
 }
 
 #[allow(non_snake_case)]
 #[no_mangle]
-pub extern "C" fn sel_getUid(_name: StrPtr) -> Sel {
-    unimplemented!()
+pub extern "C" fn sel_registerName(name: StrPtr) -> Sel {
+    let name = match name.as_ref() {
+        Some(name) => name,
+        None => return Sel(NilablePtr::nil()),
+    };
+    let mut ctx = CONTEXT.write().unwrap();
+    Sel(NilablePtr::new(ctx.register_selector_name(name)))
 }
 
 #[allow(non_snake_case)]
 #[no_mangle]
 pub extern "C" fn class_createInstance(class: Class, extra_bytes: usize) -> Id {
-    Id(NilablePtr::from(class.0.as_ref().map(|class| {
-        let p: &mut ObjcObject = unsafe {
-            let (p, num_words) = alloc(class.get_instance_size() + extra_bytes);
-            ptr::write_bytes(p, 0, mem::size_of::<usize>() * num_words);
-            mem::transmute(p)
-        };
-        p.initialize(class.clone());
-        unsafe { Ptr::new(p) }
+    Id(NilablePtr::from(class.0.as_ref().and_then(|class| {
+        let p = unsafe { libc::calloc(1, class.get_instance_size() + extra_bytes) };
+        if p.is_null() {
+            return None;
+        }
+        let mut object = unsafe { Ptr::new(p as *mut ObjcObject) };
+        object.initialize(class.clone());
+        class.add_instance();
+        Some(object)
     })))
 }
 
@@ -102,14 +242,67 @@ pub extern "C" fn class_getSuperclass(class: Class) -> Class {
         class
             .0
             .as_ref()
+            .filter(|class| class.is_resolved())
             .and_then(|class| class.super_pointer().clone()),
     ))
 }
 
 #[allow(non_snake_case)]
 #[no_mangle]
-pub extern "C" fn object_dispose(_object: Id) -> Id {
-    // ToDo: free the object
+pub extern "C" fn class_isSubclassOfClass(class: Class, superclass: Class) -> Bool {
+    Bool::from(match (class.0.as_ref(), superclass.0.as_ref()) {
+        (Some(class), Some(superclass)) => class.is_subclass_of(superclass),
+        _ => false,
+    })
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_respondsToSelector(class: Class, selector: Sel) -> Bool {
+    Bool::from(match (class.0.as_ref(), selector.0.as_ref()) {
+        (Some(class), Some(selector)) => class.resolve_method(selector.clone()).is_some(),
+        _ => false,
+    })
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_getInstanceSize(class: Class) -> usize {
+    class
+        .0
+        .as_ref()
+        .map_or(0, |class| class.get_instance_size())
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_getVersion(class: Class) -> raw::c_int {
+    class.0.as_ref().map_or(0, |class| {
+        let _ctx = CONTEXT.read().unwrap();
+        class.version() as raw::c_int
+    })
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_setVersion(class: Class, version: raw::c_int) {
+    if let Some(class) = class.0.as_ref() {
+        let _ctx = CONTEXT.write().unwrap();
+        class.clone().set_version(version as Long);
+    }
+}
+
+/*
+ * Frees an object allocated by class_createInstance.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn object_dispose(object: Id) -> Id {
+    if let Some(object) = object.0.as_ref() {
+        object.get_class_pointer().remove_instance();
+        arc::dispose(object);
+        unsafe { libc::free(object.as_ptr() as *mut libc::c_void) };
+    }
     Id(NilablePtr::nil())
 }
 
@@ -126,7 +319,7 @@ pub extern "C" fn class_getName(class: Class) -> StrPtr {
 #[allow(non_snake_case)]
 #[no_mangle]
 pub extern "C" fn class_isMetaClass(class: Class) -> Bool {
-    Bool::from(class.0.as_ref().map_or(false, |class| class.is_meta()))
+    Bool::from(class.0.as_ref().is_some_and(|class| class.is_meta()))
 }
 
 #[allow(non_snake_case)]
@@ -158,3 +351,490 @@ pub extern "C" fn method_getImplementation(method: Method) -> Imp {
 pub extern "C" fn __objc_class_name_Protocol() {
     panic!("calling unknown function")
 }
+
+/*
+ * Copies the items into a nil-terminated array allocated with malloc, which the caller releases
+ * with free.
+ */
+fn copy_to_malloc_array<T>(
+    items: Vec<Ptr<T>>,
+    out_count: Option<&mut raw::c_uint>,
+) -> *mut NilablePtr<T> {
+    if let Some(out_count) = out_count {
+        *out_count = items.len() as raw::c_uint;
+    }
+    if items.is_empty() {
+        return ptr::null_mut();
+    }
+    unsafe {
+        let array =
+            libc::malloc(mem::size_of::<NilablePtr<T>>() * (items.len() + 1)) as *mut NilablePtr<T>;
+        if array.is_null() {
+            return array;
+        }
+        let len = items.len();
+        for (i, item) in items.into_iter().enumerate() {
+            ptr::write(array.add(i), NilablePtr::new(item));
+        }
+        ptr::write(array.add(len), NilablePtr::nil());
+        array
+    }
+}
+
+/*
+ * Properties are searched in the class and category property lists of the class itself and then
+ * of its superclasses.
+ */
+fn find_property(class: &Ptr<ObjcClass>, name: &StrPtr) -> Option<Ptr<ObjcProperty>> {
+    let ctx = CONTEXT.read().unwrap();
+    let mut class = Some(class.clone());
+    while let Some(c) = class {
+        if let Some(entry) = ctx.get_class_entry(c.get_name()) {
+            if let Some(property) = entry
+                .iter_property()
+                .find(|property| property.name() == name)
+            {
+                return Some(property);
+            }
+        }
+        class = c.super_pointer().clone();
+    }
+    None
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_copyPropertyList(
+    class: Class,
+    out_count: Option<&mut raw::c_uint>,
+) -> *mut Property {
+    let properties = match class.0.as_ref() {
+        Some(class) if !class.is_meta() => {
+            let ctx = CONTEXT.read().unwrap();
+            ctx.get_class_entry(class.get_name())
+                .map_or(Vec::new(), |entry| entry.iter_property().collect())
+        }
+        _ => Vec::new(),
+    };
+    copy_to_malloc_array(properties, out_count) as *mut Property
+}
+
+/*
+ * Lists the direct subclasses of the class, or the subclasses of all depths if include_indirect
+ * is YES.  The metaclass of a class lists the metaclasses of its subclasses.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_copySubclassList(
+    class: Class,
+    include_indirect: Bool,
+    out_count: Option<&mut raw::c_uint>,
+) -> *mut Class {
+    let subclasses = match class.0.as_ref() {
+        Some(class) => {
+            let _ctx = CONTEXT.read().unwrap();
+            if include_indirect.into() {
+                class.iter_all_subclass().collect()
+            } else {
+                class.iter_subclass().collect()
+            }
+        }
+        None => Vec::new(),
+    };
+    copy_to_malloc_array(subclasses, out_count) as *mut Class
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn class_getProperty(class: Class, name: StrPtr) -> Property {
+    Property(NilablePtr::from(match class.0.as_ref() {
+        Some(class) if !name.is_null() => find_property(class, &name),
+        _ => None,
+    }))
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn property_getName(property: Property) -> StrPtr {
+    property
+        .0
+        .as_ref()
+        .map_or(StrPtr::null(), |property| property.name().clone())
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn property_getAttributes(property: Property) -> StrPtr {
+    let property = match property.0.as_ref() {
+        Some(property) => property,
+        None => return StrPtr::null(),
+    };
+    let ctx = CONTEXT.read().unwrap();
+    ctx.get_property_attributes(property)
+        .map_or(StrPtr::null(), |attributes| unsafe {
+            StrPtr::from_ptr(attributes.as_ptr())
+        })
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn property_copyAttributeValue(
+    property: Property,
+    name: StrPtr,
+) -> *mut raw::c_char {
+    let (property, name) = match (property.0.as_ref(), name.as_ref()) {
+        (Some(property), Some(name)) => (property, name),
+        _ => return ptr::null_mut(),
+    };
+    let attributes = property.attributes();
+    match property::parse_attributes(&attributes)
+        .into_iter()
+        .find(|(attribute, _)| *attribute == name)
+    {
+        Some((_, value)) => {
+            let value = CString::new(value).unwrap();
+            unsafe { libc::strdup(value.as_ptr()) }
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn property_copyAttributeList(
+    property: Property,
+    out_count: Option<&mut raw::c_uint>,
+) -> *mut PropertyAttribute {
+    let attributes = property
+        .0
+        .as_ref()
+        .map_or(String::new(), |property| property.attributes());
+    let attributes = property::parse_attributes(&attributes);
+    if let Some(out_count) = out_count {
+        *out_count = attributes.len() as raw::c_uint;
+    }
+    if attributes.is_empty() {
+        return ptr::null_mut();
+    }
+    property::copy_attribute_list(&attributes)
+}
+
+#[repr(C)]
+pub union ArgList {
+    arg_ptr: *mut raw::c_char,
+    arg_regs: [raw::c_char; mem::size_of::<*mut raw::c_char>()],
+}
+
+/*
+ * Null if the type is not followed by a valid offset.
+ */
+fn argument_address(arg_frame: &mut ArgList, t: &StrPtr) -> *mut raw::c_char {
+    let types = t.as_ref().unwrap_or("").as_bytes();
+    let (in_registers, offset) =
+        match encoding::skip_typespec(types).and_then(encoding::parse_offset) {
+            Some(offset) => offset,
+            None => return ptr::null_mut(),
+        };
+    unsafe {
+        if in_registers {
+            arg_frame.arg_regs.as_mut_ptr().offset(offset)
+        } else {
+            arg_frame.arg_ptr.offset(offset)
+        }
+    }
+}
+
+/*
+ * Methods with invalid types have no arguments.
+ */
+#[no_mangle]
+pub extern "C" fn method_get_number_of_arguments(method: Method) -> raw::c_int {
+    method
+        .0
+        .as_ref()
+        .and_then(|method| method.number_of_arguments())
+        .map_or(0, |count| count as raw::c_int)
+}
+
+#[no_mangle]
+pub extern "C" fn method_get_sizeof_arguments(method: Method) -> raw::c_int {
+    method
+        .0
+        .as_ref()
+        .map_or(0, |method| method.sizeof_arguments() as raw::c_int)
+}
+
+#[no_mangle]
+pub extern "C" fn method_get_next_argument(
+    arg_frame: Option<&mut ArgList>,
+    t: Option<&mut StrPtr>,
+) -> *mut raw::c_char {
+    let (arg_frame, t) = match (arg_frame, t) {
+        (Some(arg_frame), Some(t)) => (arg_frame, t),
+        _ => return ptr::null_mut(),
+    };
+    let types = t.as_ref().unwrap_or("");
+    let next = match encoding::skip_argspec(types.as_bytes()) {
+        Some(next) if !next.is_empty() => next,
+        _ => return ptr::null_mut(),
+    };
+    *t = unsafe { StrPtr::from_ptr(t.as_ptr().add(types.len() - next.len())) };
+    argument_address(arg_frame, t)
+}
+
+#[no_mangle]
+pub extern "C" fn method_get_first_argument(
+    method: Method,
+    arg_frame: Option<&mut ArgList>,
+    t: Option<&mut StrPtr>,
+) -> *mut raw::c_char {
+    let (method, t) = match (method.0.as_ref(), t) {
+        (Some(method), Some(t)) => (method, t),
+        _ => return ptr::null_mut(),
+    };
+    *t = method.types().clone();
+    method_get_next_argument(arg_frame, Some(t))
+}
+
+#[no_mangle]
+pub extern "C" fn method_get_nth_argument(
+    method: Method,
+    arg_frame: Option<&mut ArgList>,
+    arg: raw::c_int,
+    t: Option<&mut StrPtr>,
+) -> *mut raw::c_char {
+    let (method, arg_frame, t) = match (method.0.as_ref(), arg_frame, t) {
+        (Some(method), Some(arg_frame), Some(t)) => (method, arg_frame, t),
+        _ => return ptr::null_mut(),
+    };
+    let count = method.number_of_arguments().unwrap_or(0);
+    if arg < 0 || arg as usize >= count {
+        return ptr::null_mut();
+    }
+    let types = method.types().as_ref().unwrap_or("");
+    let mut rest = types.as_bytes();
+    for _ in 0..=arg {
+        rest = match encoding::skip_argspec(rest) {
+            Some(rest) => rest,
+            None => return ptr::null_mut(),
+        };
+    }
+    *t = unsafe { StrPtr::from_ptr(method.types().as_ptr().add(types.len() - rest.len())) };
+    argument_address(arg_frame, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::selector::ObjcSelector;
+    use crate::testing::{self, id};
+    use std::ffi::CStr;
+
+    fn class(class: api::Class) -> Class {
+        Class(NilablePtr::new(unsafe { Ptr::new(class.as_ptr()) }))
+    }
+
+    fn class_ptr(class: Class) -> *mut ObjcClass {
+        class.0.as_ref().unwrap().as_ptr()
+    }
+
+    extern "C" fn noop(_this: *mut api::Object, _selector: api::Sel) {}
+
+    #[test]
+    fn introspection() {
+        let base = testing::declare("RobjcIntrospectionBase", None, |decl| {
+            decl.add_ivar::<i64>("_value");
+            testing::add_method(decl, "robjcIntrospection", noop as extern "C" fn(_, _));
+        });
+        let sub = testing::declare(
+            "RobjcIntrospectionSub",
+            Some("RobjcIntrospectionBase"),
+            |_| {},
+        );
+        let sub_object = sub.as_ptr() as *mut ObjcObject;
+        let meta_object = sub.metaclass().unwrap().as_ptr() as *mut ObjcObject;
+
+        let object = class_createInstance(class(sub), 0)
+            .0
+            .as_ref()
+            .unwrap()
+            .as_ptr();
+        assert!(!bool::from(object_isClass(id(object))));
+        assert!(bool::from(object_isClass(id(sub_object))));
+        assert!(!bool::from(object_isMetaClass(id(sub_object))));
+        assert!(bool::from(object_isMetaClass(id(meta_object))));
+        assert_eq!(
+            class_ptr(object_getClass(id(meta_object))),
+            base.metaclass().unwrap().as_ptr()
+        );
+
+        let name = object_getClassName(id(object));
+        assert_eq!(
+            unsafe { CStr::from_ptr(name.as_ptr()) }.to_str(),
+            Ok("RobjcIntrospectionSub")
+        );
+        assert_eq!(class_ptr(object_getSuperclass(id(object))), base.as_ptr());
+        assert_eq!(class_getInstanceSize(class(sub)), 16);
+        let sel = |name| {
+            let selector = api::Sel::register(name).unwrap();
+            Sel(NilablePtr::new(unsafe { Ptr::new(selector.as_ptr()) }))
+        };
+        assert!(bool::from(class_respondsToSelector(
+            class(sub),
+            sel("robjcIntrospection")
+        )));
+        assert!(!bool::from(class_respondsToSelector(
+            class(sub),
+            sel("robjcIntrospectionMissing")
+        )));
+
+        unsafe { *(object as *mut i64).add(1) = 42 };
+        let copy = object_copy(id(object), 0).0.as_ref().unwrap().as_ptr();
+        assert_eq!(unsafe { *(copy as *mut i64).add(1) }, 42);
+
+        assert_eq!(
+            class_ptr(object_setClass(id(copy), class(base))),
+            sub.as_ptr()
+        );
+        assert_eq!(class_ptr(object_getClass(id(copy))), base.as_ptr());
+
+        class_setVersion(class(base), 3);
+        assert_eq!(class_getVersion(class(base)), 3);
+        assert_eq!(class_getVersion(class(sub)), 0);
+    }
+
+    /*
+     * Until a class is resolved its superclass is only a name, so it has no superclass to return.
+     */
+    #[test]
+    fn unresolved_superclass() {
+        let unresolved = ObjcClass::new_pair(
+            StrPtr::from_static(b"RobjcUnresolvedSuperclass\0"),
+            Some(StrPtr::from_static(b"RobjcUnresolvedSuperclassBase\0")),
+            StrPtr::from_static(b"RobjcUnresolvedSuperclassBase\0"),
+            0,
+            None,
+            None,
+            None,
+            None,
+        );
+        let object = unresolved.as_ptr() as *mut ObjcObject;
+        assert!(class_getSuperclass(Class(NilablePtr::new(unresolved)))
+            .0
+            .as_ref()
+            .is_none());
+        assert!(object_getSuperclass(id(object)).0.as_ref().is_none());
+    }
+
+    extern "C" fn add(_this: *mut api::Object, _selector: api::Sel, value: i32) -> i32 {
+        value
+    }
+
+    #[test]
+    fn selector_types() {
+        let selector = ObjcSelector::new(
+            StrPtr::from_static(b"robjcTyped\0"),
+            StrPtr::from_static(b"i16@0:8\0"),
+        );
+        let selector = Sel(NilablePtr::new(unsafe { Ptr::new(&selector) }));
+        assert_eq!(sel_getTypeEncoding(selector).as_ref(), Some("i16@0:8"));
+        assert!(sel_getTypeEncoding(Sel(NilablePtr::nil()))
+            .as_ref()
+            .is_none());
+    }
+
+    #[test]
+    fn arguments() {
+        let arguments = testing::declare("RobjcArguments", None, |decl| {
+            testing::add_method(decl, "robjcAdd:", add as extern "C" fn(_, _, _) -> _);
+        });
+        let selector = api::Sel::register("robjcAdd:").unwrap();
+        let method = || {
+            class_getInstanceMethod(
+                class(arguments),
+                Sel(NilablePtr::new(unsafe { Ptr::new(selector.as_ptr()) })),
+            )
+        };
+
+        assert_eq!(method_get_number_of_arguments(method()), 3);
+        assert_eq!(method_get_sizeof_arguments(method()), 20);
+        let mut frame = [0 as raw::c_char; 32];
+        let mut arg_frame = ArgList {
+            arg_ptr: frame.as_mut_ptr(),
+        };
+        let mut t = StrPtr::null();
+        let address = method_get_nth_argument(method(), Some(&mut arg_frame), 2, Some(&mut t));
+        assert_eq!(address, unsafe { frame.as_mut_ptr().add(16) });
+        assert_eq!(t.as_ref(), Some("i16"));
+        let address = method_get_nth_argument(method(), Some(&mut arg_frame), 3, Some(&mut t));
+        assert!(address.is_null());
+
+        let nil = || Method(NilablePtr::nil());
+        assert_eq!(method_get_number_of_arguments(nil()), 0);
+        assert_eq!(method_get_sizeof_arguments(nil()), 0);
+        let address = method_get_first_argument(nil(), Some(&mut arg_frame), Some(&mut t));
+        assert!(address.is_null());
+        assert!(method_get_next_argument(None, Some(&mut t)).is_null());
+    }
+
+    #[test]
+    fn kind_of() {
+        let base = testing::declare("RobjcKindOfBase", None, |_| {});
+        let sub = testing::declare("RobjcKindOfSub", Some("RobjcKindOfBase"), |_| {});
+        let other = testing::declare("RobjcKindOfOther", None, |_| {});
+
+        assert!(bool::from(class_isSubclassOfClass(class(sub), class(base))));
+        assert!(bool::from(class_isSubclassOfClass(class(sub), class(sub))));
+        assert!(!bool::from(class_isSubclassOfClass(
+            class(base),
+            class(sub)
+        )));
+        assert!(!bool::from(class_isSubclassOfClass(
+            class(sub),
+            class(other)
+        )));
+        assert!(!bool::from(class_isSubclassOfClass(
+            class(sub),
+            Class(NilablePtr::nil())
+        )));
+
+        let object = sub.create_instance().as_ptr();
+        assert!(bool::from(object_isKindOfClass(
+            id(object as *mut ObjcObject),
+            class(base)
+        )));
+        assert!(!bool::from(object_isKindOfClass(
+            id(object as *mut ObjcObject),
+            class(other)
+        )));
+        assert!(!bool::from(object_isKindOfClass(
+            Id(NilablePtr::nil()),
+            class(base)
+        )));
+        assert!(bool::from(object_isMemberOfClass(
+            id(object as *mut ObjcObject),
+            class(sub)
+        )));
+        assert!(!bool::from(object_isMemberOfClass(
+            id(object as *mut ObjcObject),
+            class(base)
+        )));
+        assert!(!bool::from(object_isMemberOfClass(
+            Id(NilablePtr::nil()),
+            class(sub)
+        )));
+        assert!(unsafe { &*object }.is_kind_of(base));
+
+        /* Class objects are instances of their metaclasses. */
+        let sub_object = sub.as_ptr() as *mut ObjcObject;
+        assert!(bool::from(object_isKindOfClass(
+            id(sub_object),
+            class(base.metaclass().unwrap())
+        )));
+        assert!(!bool::from(object_isKindOfClass(
+            id(sub_object),
+            class(base)
+        )));
+    }
+}
@@ -1,5 +1,9 @@
 use std::fmt;
+use std::sync::atomic::{AtomicPtr, Ordering};
 
+use super::api::Sel;
+use super::context::CONTEXT;
+use super::ptr::Ptr;
 use super::str_ptr::StrPtr;
 
 #[repr(C)]
@@ -10,6 +14,10 @@ pub struct ObjcSelector {
 }
 
 impl ObjcSelector {
+    pub fn new(sel_id: StrPtr, sel_types: StrPtr) -> ObjcSelector {
+        ObjcSelector { sel_id, sel_types }
+    }
+
     pub fn get_id(&self) -> &StrPtr {
         &self.sel_id
     }
@@ -17,6 +25,62 @@ impl ObjcSelector {
     pub fn get_types(&self) -> &StrPtr {
         &self.sel_types
     }
+
+    /*
+     * Registered selectors and the selectors of loaded modules share the name registered first,
+     * so the address of the name identifies the selectors of the same name in dispatch tables.
+     */
+    pub fn key(&self) -> usize {
+        self.sel_id.as_ptr() as usize
+    }
+
+    pub fn set_id(&mut self, sel_id: StrPtr) {
+        self.sel_id = sel_id;
+    }
+
+    pub fn set_types(&mut self, sel_types: StrPtr) {
+        self.sel_types = sel_types;
+    }
+}
+
+/*
+ * A selector sent by the runtime itself or by a msg_send! call site, registered on first use.
+ * The first use must not happen while the context is locked for writing.
+ */
+pub struct StaticSelector {
+    name: &'static [u8],
+    selector: AtomicPtr<ObjcSelector>,
+}
+
+impl StaticSelector {
+    pub const fn new(name: &'static [u8]) -> StaticSelector {
+        StaticSelector {
+            name,
+            selector: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    pub fn get(&self) -> Ptr<ObjcSelector> {
+        let mut selector = self.selector.load(Ordering::Acquire);
+        if selector.is_null() {
+            let name = StrPtr::from_static(self.name);
+            let registered = CONTEXT.read().unwrap().get_selector(&name).cloned();
+            selector = registered
+                .unwrap_or_else(|| {
+                    CONTEXT
+                        .write()
+                        .unwrap()
+                        .register_selector(&name, &StrPtr::null())
+                })
+                .as_ptr();
+            self.selector.store(selector, Ordering::Release);
+        }
+        unsafe { Ptr::new(selector) }
+    }
+
+    pub fn sel(&self) -> Sel {
+        Sel::from_ptr(self.get())
+    }
 }
 
 impl fmt::Display for ObjcSelector {
@@ -0,0 +1,301 @@
+/*
+ * Sending messages from Rust.  The IMP found for the receiver is called as an extern "C"
+ * function typed after the arguments and the return type, which are described by Encode.
+ * In debug builds the types are checked against the encoding of the method found.
+ */
+
+use std::ffi::c_void;
+use std::mem;
+use std::os::raw;
+use std::ptr::NonNull;
+
+use super::api::{Class, Imp, Object, Protocol, Sel};
+use super::encoding;
+use super::message;
+use super::ptr::{NilablePtr, Ptr};
+#[doc(hidden)]
+pub use super::selector::StaticSelector;
+use super::Bool;
+
+/*
+ * Types which can be passed to or returned from a method, with their type encoding.  It is
+ * unsafe to implement, since a wrong encoding makes calls through it undefined.
+ */
+#[allow(clippy::missing_safety_doc)]
+pub unsafe trait Encode {
+    const ENCODING: &'static str;
+}
+
+macro_rules! encode_impls {
+    ($($t:ty => $e:expr,)*) => {
+        $(unsafe impl Encode for $t {
+            const ENCODING: &'static str = $e;
+        })*
+    };
+}
+
+encode_impls!(
+    () => "v",
+    bool => "B",
+    Bool => "C",
+    i8 => "c",
+    u8 => "C",
+    i16 => "s",
+    u16 => "S",
+    i32 => "i",
+    u32 => "I",
+    i64 => "q",
+    u64 => "Q",
+    f32 => "f",
+    f64 => "d",
+    *const raw::c_char => "*",
+    *mut raw::c_char => "*",
+    *const c_void => "^v",
+    *mut c_void => "^v",
+    *const Object => "@",
+    *mut Object => "@",
+    Option<NonNull<Object>> => "@",
+    Option<Class> => "#",
+    Option<Sel> => ":",
+    Option<Protocol> => "@",
+    Class => "#",
+    Sel => ":",
+);
+
+#[cfg(target_pointer_width = "64")]
+encode_impls!(
+    isize => "q",
+    usize => "Q",
+);
+
+#[cfg(target_pointer_width = "32")]
+encode_impls!(
+    isize => "i",
+    usize => "I",
+);
+
+/*
+ * Tuples of the arguments following the receiver and the selector.
+ */
+#[allow(clippy::missing_safety_doc)]
+pub unsafe trait MessageArguments: Sized {
+    fn encodings() -> Vec<&'static str>;
+
+    unsafe fn invoke<R: Encode>(imp: Imp, receiver: *mut Object, selector: Sel, args: Self) -> R;
+}
+
+macro_rules! message_arguments_impls {
+    ($($a:ident : $t:ident),*) => {
+        unsafe impl<$($t: Encode),*> MessageArguments for ($($t,)*) {
+            fn encodings() -> Vec<&'static str> {
+                vec![$($t::ENCODING),*]
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            unsafe fn invoke<R: Encode>(
+                imp: Imp,
+                receiver: *mut Object,
+                selector: Sel,
+                ($($a,)*): Self,
+            ) -> R {
+                let imp: unsafe extern "C" fn(*mut Object, Sel $(, $t)*) -> R =
+                    mem::transmute(imp);
+                imp(receiver, selector $(, $a)*)
+            }
+        }
+    };
+}
+
+message_arguments_impls!();
+message_arguments_impls!(a: A);
+message_arguments_impls!(a: A, b: B);
+message_arguments_impls!(a: A, b: B, c: C);
+message_arguments_impls!(a: A, b: B, c: C, d: D);
+message_arguments_impls!(a: A, b: B, c: C, d: D, e: E);
+message_arguments_impls!(a: A, b: B, c: C, d: D, e: E, f: F);
+message_arguments_impls!(a: A, b: B, c: C, d: D, e: E, f: F, g: G);
+message_arguments_impls!(a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H);
+
+/*
+ * Types agree if they are the same, or if they are passed alike, e.g. long and long long on
+ * LP64 targets.  Structs agree if their members agree and are laid out at the same offsets.
+ */
+fn is_compatible(expected: &[u8], actual: &[u8]) -> bool {
+    let expected = encoding::skip_type_qualifiers(expected);
+    let actual = encoding::skip_type_qualifiers(actual);
+    if expected == actual {
+        return true;
+    }
+    let kind = |t: &[u8]| match t.first() {
+        Some(b'c' | b'C' | b's' | b'S' | b'i' | b'I' | b'l' | b'L' | b'q' | b'Q' | b'B') => 0,
+        Some(b'@' | b'#' | b':' | b'^' | b'*' | b'%' | b'?') => 1,
+        Some(b'{') => 2,
+        _ => 3,
+    };
+    if kind(expected) != kind(actual)
+        || kind(actual) == 3
+        || encoding::sizeof_type(expected).is_none()
+        || encoding::sizeof_type(expected) != encoding::sizeof_type(actual)
+    {
+        return false;
+    }
+    if kind(actual) != 2 {
+        return true;
+    }
+    match (struct_members(expected), struct_members(actual)) {
+        (Some(expected), Some(actual)) => {
+            expected.len() == actual.len()
+                && expected.iter().zip(actual.iter()).all(
+                    |((expected_offset, expected), (actual_offset, actual))| {
+                        expected_offset == actual_offset && is_compatible(expected, actual)
+                    },
+                )
+        }
+        _ => false,
+    }
+}
+
+/*
+ * The offsets and the types of the members of a struct.
+ */
+fn struct_members(t: &[u8]) -> Option<Vec<(usize, &[u8])>> {
+    let mut layout = encoding::StructLayout::new(t)?;
+    let mut members = Vec::new();
+    while layout.next_member() {
+        let (offset, _, member) = layout.get_info();
+        let member = member?;
+        let rest = encoding::skip_typespec(member)?;
+        members.push((offset, &member[..member.len() - rest.len()]));
+    }
+    Some(members)
+}
+
+/*
+ * Splits method types such as i20@0:8i16 into the types of the return value and the arguments.
+ */
+fn split_method_types(types: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut result = Vec::new();
+    let mut t = types;
+    while !t.is_empty() {
+        let rest = encoding::skip_typespec(t)?;
+        result.push(&t[..t.len() - rest.len()]);
+        t = encoding::skip_offset(rest);
+    }
+    Some(result)
+}
+
+fn verify<A: MessageArguments, R: Encode>(receiver: &Object, selector: Sel) {
+    let method = match receiver.class().instance_method(selector) {
+        Some(method) => method,
+        None => return,
+    };
+    let types = match method.types() {
+        Some(types) => types,
+        None => return,
+    };
+    let types = split_method_types(types.as_bytes())
+        .unwrap_or_else(|| panic!("invalid method types {:?} of {:?}", types, selector));
+    let expected = A::encodings();
+    let matches = types.len() == expected.len() + 3
+        && is_compatible(types[0], R::ENCODING.as_bytes())
+        && types[3..]
+            .iter()
+            .zip(expected.iter())
+            .all(|(t, e)| is_compatible(t, e.as_bytes()));
+    if !matches {
+        panic!(
+            "types of {:?} are {:?}, but sent with return type {:?} and arguments {:?}",
+            selector,
+            method.types(),
+            R::ENCODING,
+            expected
+        );
+    }
+}
+
+/*
+ * Looks up the IMP for the receiver and calls it with the arguments.  A nil receiver returns
+ * zero.  The caller is responsible for the argument and return types to agree with the method.
+ */
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn send_message<A: MessageArguments, R: Encode>(
+    receiver: *mut Object,
+    selector: Sel,
+    args: A,
+) -> R {
+    let receiver_ptr = NilablePtr::from(NonNull::new(receiver).map(|p| Ptr::new(p.as_ptr() as _)));
+    if let Some(object) = receiver.as_ref() {
+        if cfg!(debug_assertions) {
+            verify::<A, R>(object, selector);
+        }
+    }
+    let imp = message::objc_msg_lookup(
+        super::Id(receiver_ptr),
+        super::Sel(NilablePtr::new(Ptr::new(selector.as_ptr()))),
+    );
+    let imp = imp.0.as_ref().expect("no procedure is found").as_ptr();
+    A::invoke(mem::transmute::<*mut _, Imp>(imp), receiver, selector, args)
+}
+
+/*
+ * Sends a message, e.g. msg_send![obj, count] or msg_send![obj, setX:1 y:2].  The selector is
+ * registered on the first use at each call site and cached there.  Must be used in an unsafe
+ * block, and not while the runtime is locked for writing.
+ */
+#[macro_export]
+macro_rules! msg_send {
+    ($receiver:expr, $name:ident) => {{
+        static SELECTOR: $crate::send::StaticSelector =
+            $crate::send::StaticSelector::new(concat!(stringify!($name), '\0').as_bytes());
+        $crate::send::send_message($receiver, SELECTOR.sel(), ())
+    }};
+    ($receiver:expr, $($name:ident : $arg:expr)+) => {{
+        static SELECTOR: $crate::send::StaticSelector = $crate::send::StaticSelector::new(
+            concat!($(stringify!($name), ':',)+ '\0').as_bytes(),
+        );
+        $crate::send::send_message($receiver, SELECTOR.sel(), ($($arg,)+))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_compatible, split_method_types};
+    use crate::context::CONTEXT;
+
+    #[test]
+    fn method_types() {
+        let types = split_method_types(b"i20@0:8i16").unwrap();
+        assert_eq!(types, vec![&b"i"[..], b"@", b":", b"i"]);
+        let types = split_method_types(b"{Pair=qd}16@0:8").unwrap();
+        assert_eq!(types, vec![&b"{Pair=qd}"[..], b"@", b":"]);
+
+        assert!(is_compatible(b"rO@", b"@"));
+        assert!(is_compatible(b"q", b"l"));
+        assert!(is_compatible(b"#", b"@"));
+        assert!(!is_compatible(b"i", b"q"));
+        assert!(!is_compatible(b"f", b"i"));
+        assert!(!is_compatible(b"d", b"q"));
+        assert!(is_compatible(b"{Pair=\"key\"q\"value\"d}", b"{Pair=qd}"));
+        assert!(is_compatible(b"{Range=QQ}", b"{Range=qq}"));
+        assert!(!is_compatible(b"{Pair=qd}", b"{Pair=qq}"));
+        assert!(!is_compatible(b"{Size=ff}", b"{Pair=ii}"));
+        assert!(!is_compatible(b"{Mixed=ci}", b"{Mixed=ic}"));
+    }
+
+    #[test]
+    fn nil_receiver() {
+        let value: i32 = unsafe { msg_send![std::ptr::null_mut(), count] };
+        assert_eq!(value, 0);
+        let value: f64 = unsafe { msg_send![std::ptr::null_mut(), valueAt:1i32 scale:2.0f64] };
+        assert_eq!(value, 0.0);
+    }
+
+    #[test]
+    fn cached_selector() {
+        let send = || -> i32 { unsafe { msg_send![std::ptr::null_mut(), robjcCachedSelector] } };
+        assert_eq!(send(), 0);
+        /* The selector is not registered again, which would wait for the lock. */
+        let _ctx = CONTEXT.read().unwrap();
+        assert_eq!(send(), 0);
+    }
+}
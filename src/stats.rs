@@ -0,0 +1,314 @@
+/*
+ * Statistics of message lookups, compiled in with the stats feature.  Lookups are counted per
+ * receiver class and selector, together with the misses of the method caches and the lookups
+ * of missing methods, which are forwarded.  A report of the hottest classes and selectors is
+ * written to stderr at exit, or to the descriptor named by ROBJC_STATS_FD.
+ */
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+
+use super::class::ObjcClass;
+use super::selector::ObjcSelector;
+
+/*
+ * The number of classes and selectors listed by the report at exit.
+ */
+const REPORT_SIZE: usize = 20;
+
+#[derive(Clone)]
+struct Counter {
+    class_name: String,
+    is_meta: bool,
+    selector_name: String,
+    hits: u64,
+    forwards: u64,
+}
+
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static FORWARDS: AtomicU64 = AtomicU64::new(0);
+static NIL_SENDS: AtomicU64 = AtomicU64::new(0);
+static REPORT: Once = Once::new();
+
+/*
+ * Keyed by the address of the class and the key of the selector, i.e. the address of its
+ * registered name, so that the selectors of different modules with the same name are counted
+ * together.  The names are copied when a pair is first seen, so that counters outlive unloaded
+ * images.
+ */
+type Counters = HashMap<(usize, usize), Counter>;
+
+lazy_static! {
+    /*
+     * Each thread counts in its own table, which is only locked by other threads for snapshots.
+     * The tables of exited threads are merged into EXITED.  THREADS is locked before EXITED.
+     */
+    static ref THREADS: Mutex<Vec<Arc<Mutex<Counters>>>> = Mutex::new(Vec::new());
+    static ref EXITED: Mutex<Counters> = Mutex::new(HashMap::new());
+}
+
+struct ThreadCounters(Arc<Mutex<Counters>>);
+
+impl Drop for ThreadCounters {
+    fn drop(&mut self) {
+        let mut threads = THREADS.lock().unwrap();
+        merge(&mut EXITED.lock().unwrap(), &self.0.lock().unwrap());
+        threads.retain(|counters| !Arc::ptr_eq(counters, &self.0));
+    }
+}
+
+thread_local! {
+    static COUNTERS: ThreadCounters = {
+        let counters = Arc::new(Mutex::new(HashMap::new()));
+        THREADS.lock().unwrap().push(counters.clone());
+        ThreadCounters(counters)
+    };
+}
+
+fn merge(into: &mut Counters, from: &Counters) {
+    for (key, counter) in from {
+        let merged = into.entry(*key).or_insert_with(|| Counter {
+            hits: 0,
+            forwards: 0,
+            ..counter.clone()
+        });
+        merged.hits += counter.hits;
+        merged.forwards += counter.forwards;
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SendCount {
+    pub class_name: String,
+    pub is_meta: bool,
+    pub selector_name: String,
+    /*
+     * Lookups which found a method.
+     */
+    pub hits: u64,
+    /*
+     * Lookups which found none, so that the message is forwarded.
+     */
+    pub forwards: u64,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Stats {
+    /*
+     * Sorted by the number of lookups, the hottest first.
+     */
+    pub sends: Vec<SendCount>,
+    pub cache_misses: u64,
+    pub forwards: u64,
+    pub nil_sends: u64,
+}
+
+/*
+ * Called once when the runtime is initialized.
+ */
+pub fn register_report() {
+    REPORT.call_once(|| unsafe {
+        libc::atexit(report_at_exit);
+    });
+}
+
+extern "C" fn report_at_exit() {
+    let fd = env::var("ROBJC_STATS_FD")
+        .ok()
+        .and_then(|fd| fd.parse().ok())
+        .unwrap_or(libc::STDERR_FILENO);
+    let report = report(&snapshot(), REPORT_SIZE);
+    unsafe {
+        libc::write(fd, report.as_ptr() as *const libc::c_void, report.len());
+    }
+}
+
+pub fn lookup(class: &ObjcClass, selector: &ObjcSelector, found: bool) {
+    if !found {
+        FORWARDS.fetch_add(1, Ordering::Relaxed);
+    }
+    let count = |counters: &mut Counters| {
+        let key = (class as *const ObjcClass as usize, selector.key());
+        let counter = counters.entry(key).or_insert_with(|| Counter {
+            class_name: class.get_name().as_ref().unwrap_or("").to_string(),
+            is_meta: class.is_meta(),
+            selector_name: selector.get_id().as_ref().unwrap_or("").to_string(),
+            hits: 0,
+            forwards: 0,
+        });
+        if found {
+            counter.hits += 1;
+        } else {
+            counter.forwards += 1;
+        }
+    };
+    /* Lookups from destructors of other thread locals may come after the table is dropped. */
+    if COUNTERS
+        .try_with(|counters| count(&mut counters.0.lock().unwrap()))
+        .is_err()
+    {
+        count(&mut EXITED.lock().unwrap());
+    }
+}
+
+pub fn cache_miss() {
+    CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn nil_send() {
+    NIL_SENDS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn snapshot() -> Stats {
+    let mut counters = HashMap::new();
+    {
+        let threads = THREADS.lock().unwrap();
+        merge(&mut counters, &EXITED.lock().unwrap());
+        for thread in threads.iter() {
+            merge(&mut counters, &thread.lock().unwrap());
+        }
+    }
+    let mut sends: Vec<SendCount> = counters
+        .values()
+        .map(|counter| SendCount {
+            class_name: counter.class_name.clone(),
+            is_meta: counter.is_meta,
+            selector_name: counter.selector_name.clone(),
+            hits: counter.hits,
+            forwards: counter.forwards,
+        })
+        .collect();
+    sends.sort_by(|a, b| {
+        (b.hits + b.forwards)
+            .cmp(&(a.hits + a.forwards))
+            .then_with(|| a.class_name.cmp(&b.class_name))
+            .then_with(|| a.selector_name.cmp(&b.selector_name))
+    });
+    Stats {
+        sends,
+        cache_misses: CACHE_MISSES.load(Ordering::Relaxed),
+        forwards: FORWARDS.load(Ordering::Relaxed),
+        nil_sends: NIL_SENDS.load(Ordering::Relaxed),
+    }
+}
+
+pub fn reset() {
+    let threads = THREADS.lock().unwrap();
+    EXITED.lock().unwrap().clear();
+    for thread in threads.iter() {
+        thread.lock().unwrap().clear();
+    }
+    CACHE_MISSES.store(0, Ordering::Relaxed);
+    FORWARDS.store(0, Ordering::Relaxed);
+    NIL_SENDS.store(0, Ordering::Relaxed);
+}
+
+/*
+ * Lists the totals, and the hottest classes and the hottest selectors up to the given number.
+ */
+pub fn report(stats: &Stats, size: usize) -> String {
+    let lookups: u64 = stats
+        .sends
+        .iter()
+        .map(|send| send.hits + send.forwards)
+        .sum();
+    let mut out = format!(
+        "robjc: {} lookups, {} cache misses, {} forwards, {} nil receivers\n",
+        lookups, stats.cache_misses, stats.forwards, stats.nil_sends
+    );
+
+    let mut classes: HashMap<(&str, bool), u64> = HashMap::new();
+    let mut selectors: HashMap<&str, u64> = HashMap::new();
+    for send in &stats.sends {
+        *classes.entry((&send.class_name, send.is_meta)).or_insert(0) += send.hits + send.forwards;
+        *selectors.entry(&send.selector_name).or_insert(0) += send.hits + send.forwards;
+    }
+    let mut classes: Vec<_> = classes.into_iter().collect();
+    classes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut selectors: Vec<_> = selectors.into_iter().collect();
+    selectors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    out.push_str("robjc: hottest classes:\n");
+    for ((name, is_meta), count) in classes.into_iter().take(size) {
+        let kind = if is_meta { " (meta)" } else { "" };
+        out.push_str(&format!("  {:>10} {}{}\n", count, name, kind));
+    }
+    out.push_str("robjc: hottest selectors:\n");
+    for (name, count) in selectors.into_iter().take(size) {
+        out.push_str(&format!("  {:>10} {}\n", count, name));
+    }
+    out.push_str("robjc: hottest sends:\n");
+    for send in stats.sends.iter().take(size) {
+        let kind = if send.is_meta { '+' } else { '-' };
+        out.push_str(&format!(
+            "  {:>10} {}[{} {}]",
+            send.hits, kind, send.class_name, send.selector_name
+        ));
+        if send.forwards > 0 {
+            out.push_str(&format!(", {} forwarded", send.forwards));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{report, snapshot};
+    use crate::api::{Object, Sel};
+    use crate::message::objc_msg_lookup;
+    use crate::msg_send;
+    use crate::ptr::{NilablePtr, Ptr};
+    use crate::testing::{self, id};
+    use std::thread;
+
+    extern "C" fn value(_this: *mut Object, _selector: Sel) -> i32 {
+        1
+    }
+
+    /*
+     * Other tests send messages concurrently, so the counters of the test class are checked
+     * rather than the totals, and the counters are not reset.
+     */
+    #[test]
+    fn stats() {
+        let class = testing::declare("RobjcStatsBase", None, |decl| {
+            testing::add_method(decl, "robjcStatsValue", value as extern "C" fn(_, _) -> _);
+        });
+        let missing = Sel::register("robjcStatsMissing").unwrap();
+
+        let object = class.create_instance().as_ptr();
+        /* The counters of exited threads are kept. */
+        let address = object as usize;
+        thread::spawn(move || unsafe {
+            let _: i32 = msg_send![address as *mut Object, robjcStatsValue];
+        })
+        .join()
+        .unwrap();
+        unsafe {
+            for _ in 0..2 {
+                let _: i32 = msg_send![object, robjcStatsValue];
+            }
+            objc_msg_lookup(
+                id(object),
+                crate::Sel(NilablePtr::new(Ptr::new(missing.as_ptr()))),
+            );
+        }
+
+        let stats = snapshot();
+        let count = |selector: &str| {
+            stats
+                .sends
+                .iter()
+                .find(|send| send.class_name == "RobjcStatsBase" && send.selector_name == selector)
+                .map(|send| (send.hits, send.forwards))
+        };
+        assert_eq!(count("robjcStatsValue"), Some((3, 0)));
+        assert_eq!(count("robjcStatsMissing"), Some((0, 1)));
+        assert!(stats.cache_misses >= 1);
+        assert!(stats.forwards >= 1);
+        assert!(report(&stats, usize::MAX).contains(" 3 -[RobjcStatsBase robjcStatsValue]\n"));
+    }
+}
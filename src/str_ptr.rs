@@ -14,6 +14,23 @@ impl StrPtr {
         StrPtr(None)
     }
 
+    pub fn from_static(s: &'static [u8]) -> StrPtr {
+        let s = ffi::CStr::from_bytes_with_nul(s).expect("string is not nul-terminated");
+        StrPtr(ptr::NonNull::new(s.as_ptr() as *mut raw::c_char))
+    }
+
+    pub unsafe fn from_ptr(p: *const raw::c_char) -> StrPtr {
+        StrPtr(ptr::NonNull::new(p as *mut raw::c_char))
+    }
+
+    pub fn as_ptr(&self) -> *const raw::c_char {
+        self.0.map_or(ptr::null(), |p| p.as_ptr())
+    }
+
+    pub fn leak(s: ffi::CString) -> StrPtr {
+        StrPtr(ptr::NonNull::new(s.into_raw()))
+    }
+
     pub fn as_ref(&self) -> Option<&str> {
         match self.0 {
             None => None,
@@ -0,0 +1,40 @@
+/*
+ * Fixtures shared by the unit tests.  All tests run against one runtime, so every class they
+ * declare needs a name of its own, by convention prefixed with Robjc and the module under test.
+ */
+
+use super::api::{Class, Sel};
+use super::declare::{ClassDecl, MethodImplementation};
+use super::ptr::{NilablePtr, Ptr};
+use super::Id;
+
+/*
+ * Wraps a pointer to an object, a class or a block as an id.
+ */
+pub fn id<T>(object: *const T) -> Id {
+    Id(NilablePtr::new(unsafe { Ptr::new(object as *mut _) }))
+}
+
+/*
+ * Declares and registers a root class, or a subclass of superclass, letting methods add its
+ * methods and ivars first.
+ */
+pub fn declare<F>(name: &str, superclass: Option<&str>, methods: F) -> Class
+where
+    F: FnOnce(&mut ClassDecl),
+{
+    let mut decl = match superclass {
+        Some(superclass) => ClassDecl::new(name, superclass),
+        None => ClassDecl::root(name),
+    }
+    .unwrap();
+    methods(&mut decl);
+    decl.register().unwrap()
+}
+
+/*
+ * Adds an instance method for the selector with the given name.
+ */
+pub fn add_method<F: MethodImplementation>(decl: &mut ClassDecl, name: &str, imp: F) -> bool {
+    decl.add_method(Sel::register(name).unwrap(), imp)
+}
@@ -0,0 +1,204 @@
+/*
+ * Tracing of message lookups, enabled by ROBJC_TRACE_MESSAGES=1 or objc_setMessageTracing.
+ * ROBJC_TRACE_CLASSES and ROBJC_TRACE_SELECTORS select receiver classes and selectors by name
+ * prefix, and ROBJC_TRACE_FD names the file descriptor written to, stderr by default.  While
+ * tracing is disabled, a lookup only loads a flag.
+ */
+
+use std::env;
+use std::os::raw;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use super::class::ObjcClass;
+use super::method::ObjcMethod;
+use super::ptr::Ptr;
+use super::selector::ObjcSelector;
+use super::str_ptr::StrPtr;
+use super::Bool;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+struct Config {
+    class_prefix: Option<String>,
+    selector_prefix: Option<String>,
+    fd: raw::c_int,
+}
+
+lazy_static! {
+    static ref CONFIG: RwLock<Config> = RwLock::new(Config {
+        class_prefix: None,
+        selector_prefix: None,
+        fd: libc::STDERR_FILENO,
+    });
+}
+
+/*
+ * Called once when the runtime is initialized.
+ */
+pub fn configure_from_env() {
+    if env::var("ROBJC_TRACE_MESSAGES").map_or(true, |value| value.is_empty() || value == "0") {
+        return;
+    }
+    let fd = env::var("ROBJC_TRACE_FD")
+        .ok()
+        .and_then(|fd| fd.parse().ok())
+        .unwrap_or(libc::STDERR_FILENO);
+    configure(
+        true,
+        env::var("ROBJC_TRACE_CLASSES").ok(),
+        env::var("ROBJC_TRACE_SELECTORS").ok(),
+        fd,
+    );
+}
+
+fn configure(
+    enabled: bool,
+    class_prefix: Option<String>,
+    selector_prefix: Option<String>,
+    fd: raw::c_int,
+) {
+    let mut config = CONFIG.write().unwrap();
+    config.class_prefix = class_prefix.filter(|prefix| !prefix.is_empty());
+    config.selector_prefix = selector_prefix.filter(|prefix| !prefix.is_empty());
+    config.fd = fd;
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/*
+ * The receiver is the class of the receiver, or the superclass searched by a super send.
+ */
+pub enum Receiver<'a> {
+    Nil,
+    Class(&'a ObjcClass),
+    Super(&'a ObjcClass),
+}
+
+pub fn lookup(receiver: Receiver, selector: &ObjcSelector, method: Option<&Ptr<ObjcMethod>>) {
+    let config = CONFIG.read().unwrap();
+    let selector_name = selector.get_id().as_ref().unwrap_or("");
+    if !config
+        .selector_prefix
+        .as_ref()
+        .map_or(true, |prefix| selector_name.starts_with(prefix.as_str()))
+    {
+        return;
+    }
+    let line = match receiver {
+        Receiver::Nil => {
+            if config.class_prefix.is_some() {
+                return;
+            }
+            format!("robjc: [nil {}] nil receiver\n", selector_name)
+        }
+        Receiver::Class(class) | Receiver::Super(class) => {
+            let class_name = class.get_name().as_ref().unwrap_or("");
+            if !config
+                .class_prefix
+                .as_ref()
+                .map_or(true, |prefix| class_name.starts_with(prefix.as_str()))
+            {
+                return;
+            }
+            let kind = if class.is_meta() { '+' } else { '-' };
+            let receiver = match receiver {
+                Receiver::Super(_) => format!("super {}", class_name),
+                _ => class_name.to_string(),
+            };
+            let outcome = match method.and_then(|method| class.method_owner(method)) {
+                Some(owner) => format!("found in {}", owner.get_name().as_ref().unwrap_or("")),
+                None if method.is_some() => "found".to_string(),
+                None => "not found, forwarding".to_string(),
+            };
+            format!(
+                "robjc: {}[{} {}] {}\n",
+                kind, receiver, selector_name, outcome
+            )
+        }
+    };
+    unsafe {
+        libc::write(config.fd, line.as_ptr() as *const libc::c_void, line.len());
+    }
+}
+
+/*
+ * Null prefixes select all classes or selectors, and a negative descriptor stands for stderr.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_setMessageTracing(
+    enabled: Bool,
+    class_prefix: StrPtr,
+    selector_prefix: StrPtr,
+    fd: raw::c_int,
+) {
+    configure(
+        enabled.into(),
+        class_prefix.as_ref().map(String::from),
+        selector_prefix.as_ref().map(String::from),
+        if fd < 0 { libc::STDERR_FILENO } else { fd },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::objc_setMessageTracing;
+    use crate::api::{Object, Sel};
+    use crate::message::objc_msg_lookup;
+    use crate::msg_send;
+    use crate::ptr::{NilablePtr, Ptr};
+    use crate::str_ptr::StrPtr;
+    use crate::testing::{self, id};
+    use crate::{NO, YES};
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+
+    extern "C" fn value(_this: *mut Object, _selector: Sel) -> i32 {
+        1
+    }
+
+    #[test]
+    fn trace() {
+        testing::declare("RobjcTraceBase", None, |decl| {
+            testing::add_method(decl, "robjcTraceValue", value as extern "C" fn(_, _) -> _);
+        });
+        let sub = testing::declare("RobjcTraceSub", Some("RobjcTraceBase"), |_| {});
+        let missing = Sel::register("robjcTraceMissing").unwrap();
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        objc_setMessageTracing(
+            YES,
+            StrPtr::from_static(b"RobjcTrace\0"),
+            StrPtr::null(),
+            fds[1],
+        );
+        let object = sub.create_instance().as_ptr();
+        unsafe {
+            let value: i32 = msg_send![object, robjcTraceValue];
+            assert_eq!(value, 1);
+            objc_msg_lookup(
+                id(object),
+                crate::Sel(NilablePtr::new(Ptr::new(missing.as_ptr()))),
+            );
+        }
+        objc_setMessageTracing(NO, StrPtr::null(), StrPtr::null(), -1);
+        unsafe { libc::close(fds[1]) };
+
+        let mut output = String::new();
+        let mut pipe = unsafe { File::from_raw_fd(fds[0]) };
+        pipe.read_to_string(&mut output).unwrap();
+        assert!(
+            output.contains("robjc: -[RobjcTraceSub robjcTraceValue] found in RobjcTraceBase\n")
+        );
+        assert!(
+            output.contains("robjc: -[RobjcTraceSub robjcTraceMissing] not found, forwarding\n")
+        );
+    }
+}
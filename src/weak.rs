@@ -0,0 +1,279 @@
+/*
+ * Weak references, which ARC stores through objc_storeWeak and friends.  The table maps each
+ * object to the locations referring to it weakly, and the locations are set to nil when the
+ * object is released for the last time or disposed of.
+ *
+ * The last release clears the references while holding the table, and objc_loadWeakRetained
+ * retains the object it reads while holding the table, so a load racing with the last release
+ * either retains the object before it is released or reads nil.  This holds for objects counted
+ * by the runtime.  Objects whose classes implement retain are sent -retain after the table is
+ * unlocked, as it may use weak references itself, so their Foundation has to keep them alive
+ * until it clears their references with objc_delete_weak_refs.
+ */
+
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+
+use super::arc;
+use super::ptr::NilablePtr;
+use super::{Bool, Id};
+
+pub struct WeakTable {
+    /*
+     * Locations by the addresses of the objects they refer to.
+     */
+    locations: HashMap<usize, Vec<usize>>,
+}
+
+impl WeakTable {
+    fn register(&mut self, object: usize, location: usize) {
+        self.locations.entry(object).or_default().push(location);
+    }
+
+    fn unregister(&mut self, object: usize, location: usize) {
+        if let Some(locations) = self.locations.get_mut(&object) {
+            locations.retain(|&l| l != location);
+            if locations.is_empty() {
+                self.locations.remove(&object);
+            }
+        }
+    }
+
+    /*
+     * Sets the locations referring to the object to nil.  Returns whether there were any.
+     */
+    pub fn clear(&mut self, object: usize) -> bool {
+        match self.locations.remove(&object) {
+            Some(locations) => {
+                for location in locations {
+                    unsafe { ptr::write(location as *mut Id, Id(NilablePtr::nil())) };
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /*
+     * Drops the references to and from an unloaded image.
+     */
+    pub fn forget(&mut self, unloaded: impl Fn(usize) -> bool) {
+        self.locations.retain(|&object, locations| {
+            locations.retain(|&location| !unloaded(location));
+            !unloaded(object) && !locations.is_empty()
+        });
+    }
+}
+
+lazy_static! {
+    static ref WEAK_TABLE: Mutex<WeakTable> = Mutex::new(WeakTable {
+        locations: HashMap::new(),
+    });
+}
+
+/*
+ * Taken before the reference counts, when both are needed.
+ */
+pub fn lock() -> MutexGuard<'static, WeakTable> {
+    WEAK_TABLE.lock().unwrap()
+}
+
+fn address(object: &Id) -> Option<usize> {
+    object.0.as_ref().map(|object| object.as_ptr() as usize)
+}
+
+fn store(table: &mut WeakTable, location: &mut Id, object: Id) {
+    let location_address = location as *mut Id as usize;
+    if let Some(old) = address(location) {
+        table.unregister(old, location_address);
+    }
+    if let Some(new) = address(&object) {
+        table.register(new, location_address);
+    }
+    *location = object;
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_storeWeak(location: Option<&mut Id>, object: Id) -> Id {
+    let location = match location {
+        Some(location) => location,
+        None => return Id(NilablePtr::nil()),
+    };
+    let result = Id(object.0.clone());
+    store(&mut lock(), location, object);
+    result
+}
+
+/*
+ * The location is not initialized, so it is not unregistered.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_initWeak(location: Option<&mut Id>, object: Id) -> Id {
+    let location = match location {
+        Some(location) => location,
+        None => return Id(NilablePtr::nil()),
+    };
+    unsafe { ptr::write(location as *mut Id, Id(NilablePtr::nil())) };
+    objc_storeWeak(Some(location), object)
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_destroyWeak(location: Option<&mut Id>) {
+    objc_storeWeak(location, Id(NilablePtr::nil()));
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_loadWeakRetained(location: Option<&mut Id>) -> Id {
+    let location = match location {
+        Some(location) => location,
+        None => return Id(NilablePtr::nil()),
+    };
+    arc::register_selectors();
+    let table = lock();
+    let object = Id(location.0.clone());
+    let retained = object.0.as_ref().map_or(true, arc::try_retain_counted);
+    drop(table);
+    if retained {
+        object
+    } else {
+        arc::objc_retain(object)
+    }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_loadWeak(location: Option<&mut Id>) -> Id {
+    arc::objc_autorelease(objc_loadWeakRetained(location))
+}
+
+/*
+ * Initializes the destination to refer to the object the source refers to.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_copyWeak(destination: Option<&mut Id>, source: Option<&mut Id>) {
+    let object = objc_loadWeakRetained(source);
+    let retained = Id(object.0.clone());
+    objc_initWeak(destination, object);
+    arc::objc_release(retained);
+}
+
+/*
+ * Initializes the destination to refer to the object the source refers to, and sets the source
+ * to nil without registering it again.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_moveWeak(destination: Option<&mut Id>, source: Option<&mut Id>) {
+    let (destination, source) = match (destination, source) {
+        (Some(destination), Some(source)) => (destination, source),
+        _ => return,
+    };
+    let mut table = lock();
+    let object = mem::replace(source, Id(NilablePtr::nil()));
+    if let Some(object) = address(&object) {
+        table.unregister(object, source as *mut Id as usize);
+    }
+    unsafe { ptr::write(destination as *mut Id, Id(NilablePtr::nil())) };
+    store(&mut table, destination, object);
+}
+
+/*
+ * Sets the weak references to the object to nil, for Foundations whose -dealloc does not go
+ * through object_dispose.  Returns whether there were any.
+ */
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "C" fn objc_delete_weak_refs(object: Id) -> Bool {
+    Bool::from(address(&object).is_some_and(|object| lock().clear(object)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{objc_destroyWeak, objc_initWeak, objc_loadWeakRetained, objc_moveWeak};
+    use crate::api::{Object, Sel};
+    use crate::arc::objc_release;
+    use crate::ptr::NilablePtr;
+    use crate::testing::{self, id};
+    use crate::Id;
+    use std::thread;
+
+    #[test]
+    fn weak() {
+        let class = testing::declare("RobjcWeakReferent", None, |_| {});
+        let object = class.create_instance().as_ptr();
+
+        let mut first = Id(NilablePtr::nil());
+        let mut second = Id(NilablePtr::nil());
+        objc_initWeak(Some(&mut first), id(object));
+        objc_initWeak(Some(&mut second), id(object));
+        let mut moved = Id(NilablePtr::nil());
+        objc_moveWeak(Some(&mut moved), Some(&mut second));
+        assert!(second.0.as_ref().is_none());
+
+        let loaded = objc_loadWeakRetained(Some(&mut first));
+        assert_eq!(loaded.0.as_ref().unwrap().as_ptr() as *mut Object, object);
+        objc_release(loaded);
+        assert!(first.0.as_ref().is_some());
+
+        objc_release(id(object));
+        assert!(first.0.as_ref().is_none());
+        assert!(moved.0.as_ref().is_none());
+        objc_destroyWeak(Some(&mut first));
+        objc_destroyWeak(Some(&mut moved));
+    }
+
+    /*
+     * A load racing with the last release gets either nil or a retained object.
+     */
+    #[test]
+    fn race() {
+        let class = testing::declare("RobjcWeakRaced", None, |_| {});
+        for _ in 0..100 {
+            let object = class.create_instance().as_ptr();
+            let mut weak = Box::new(Id(NilablePtr::nil()));
+            objc_initWeak(Some(&mut weak), id(object));
+            let location = &mut *weak as *mut Id as usize;
+            let loader = thread::spawn(move || loop {
+                let location = unsafe { &mut *(location as *mut Id) };
+                let loaded = objc_loadWeakRetained(Some(location));
+                if loaded.0.as_ref().is_none() {
+                    break;
+                }
+                objc_release(loaded);
+            });
+            objc_release(id(object));
+            loader.join().unwrap();
+            assert!(weak.0.as_ref().is_none());
+        }
+    }
+
+    /*
+     * Objects which implement retain are sent it without holding the table.
+     */
+    #[test]
+    fn overridden() {
+        extern "C" fn retain(this: *mut Object, _selector: Sel) -> *mut Object {
+            let mut weak = Id(NilablePtr::nil());
+            objc_initWeak(Some(&mut weak), id(this));
+            objc_destroyWeak(Some(&mut weak));
+            this
+        }
+        let class = testing::declare("RobjcWeakOverridden", None, |decl| {
+            testing::add_method(decl, "retain", retain as extern "C" fn(_, _) -> _);
+        });
+        let object = class.create_instance().as_ptr();
+
+        let mut weak = Id(NilablePtr::nil());
+        objc_initWeak(Some(&mut weak), id(object));
+        let loaded = objc_loadWeakRetained(Some(&mut weak));
+        assert_eq!(loaded.0.as_ref().unwrap().as_ptr() as *mut Object, object);
+        objc_destroyWeak(Some(&mut weak));
+    }
+}
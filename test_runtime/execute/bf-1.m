@@ -0,0 +1,44 @@
+/* Written for robjc after gcc/testsuite/objc/execute/bf-1.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test that structs with bitfields are laid out by the runtime as by the compiler.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <objc/runtime.h>
+
+struct Flags
+{
+  char c;
+  unsigned int a : 3;
+  unsigned int b : 7;
+  int d;
+  unsigned int e : 20;
+  unsigned int f : 20;
+  char g;
+};
+
+int main (void)
+{
+  struct objc_struct_layout layout;
+  unsigned int size, align;
+  int members = 0;
+
+  objc_layout_structure (@encode (struct Flags), &layout);
+  while (objc_layout_structure_next_member (&layout))
+    members++;
+  objc_layout_finish_structure (&layout, &size, &align);
+
+  if (members != 7)
+    abort ();
+
+  if (size != sizeof (struct Flags) || align != __alignof__ (struct Flags))
+    abort ();
+
+  if (objc_sizeof_type (@encode (struct Flags)) != sizeof (struct Flags))
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,75 @@
+/* Written for robjc after gcc/testsuite/objc/execute/encode-1.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test that the runtime computes the sizes and alignments of the types encoded by the
+   compiler as the compiler lays them out.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include <objc/runtime.h>
+
+struct Small
+{
+  char c;
+  short s;
+};
+
+struct Mixed
+{
+  char c;
+  double d;
+  int i;
+  struct Small small;
+  char array[3];
+  id object;
+};
+
+union Either
+{
+  int i;
+  double d;
+  char c[12];
+};
+
+#define CHECK(type)                                                     \
+  do                                                                    \
+    {                                                                   \
+      if (objc_sizeof_type (@encode (type)) != sizeof (type))           \
+        abort ();                                                       \
+      if (objc_alignof_type (@encode (type)) != __alignof__ (type))     \
+        abort ();                                                       \
+      if (*objc_skip_typespec (@encode (type)) != '\0')                 \
+        abort ();                                                       \
+    }                                                                   \
+  while (0)
+
+int main (void)
+{
+  CHECK (char);
+  CHECK (short);
+  CHECK (int);
+  CHECK (long);
+  CHECK (long long);
+  CHECK (float);
+  CHECK (double);
+  CHECK (id);
+  CHECK (Class);
+  CHECK (SEL);
+  CHECK (char *);
+  CHECK (int *);
+  CHECK (struct Small);
+  CHECK (struct Mixed);
+  CHECK (union Either);
+  CHECK (struct Mixed[4]);
+
+  if (strcmp (objc_skip_type_qualifiers ("rn^i"), "^i") != 0)
+    abort ();
+
+  if (objc_get_type_qualifiers ("rO@") != (_F_CONST | _F_BYCOPY))
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,49 @@
+/* Written for robjc after gcc/testsuite/objc/execute/exceptions/catchall-1.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test that @catch (id) catches every exception.  */
+
+/* { dg-do run } */
+/* { dg-options "-fobjc-exceptions" } */
+
+#include <stdlib.h>
+#include "../../TestsuiteObject.m"
+
+@interface MyException : TestsuiteObject
+@end
+
+@implementation MyException
+@end
+
+static int thrower (void)
+{
+  @throw [MyException new];
+  return 0;
+}
+
+int main (void)
+{
+  int caught = 0;
+  int finally = 0;
+
+  @try
+    {
+      thrower ();
+    }
+  @catch (id exception)
+    {
+      if (object_getClass (exception) != objc_getClass ("MyException"))
+        abort ();
+      caught = 1;
+    }
+  @finally
+    {
+      finally = 1;
+    }
+
+  if (! caught || ! finally)
+    abort ();
+
+  return 0;
+}
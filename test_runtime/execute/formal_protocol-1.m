@@ -0,0 +1,34 @@
+/* Written for robjc after gcc/testsuite/objc/execute/formal_protocol-1.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test that protocol objects are usable at run time.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include "../TestsuiteObject.m"
+
+@protocol Enabling
+- (BOOL) isEnabled;
+@end
+
+@interface Feature : TestsuiteObject <Enabling>
+@end
+
+@implementation Feature
+- (BOOL) isEnabled { return YES; }
+@end
+
+int main (void)
+{
+  Protocol *protocol = @protocol (Enabling);
+
+  if (protocol == nil)
+    abort ();
+
+  if (! class_conformsToProtocol ([Feature class], protocol))
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,41 @@
+/* Written for robjc after gcc/testsuite/objc/execute/forward-1.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test that messages a class does not respond to are forwarded.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include "../TestsuiteObject.m"
+
+static int forwarded = 0;
+
+@interface Forwarder : TestsuiteObject
+- (id) forwardingTargetForSelector: (SEL)selector;
+@end
+
+@interface Target : TestsuiteObject
+- (int) answer;
+@end
+
+@implementation Target
+- (int) answer { forwarded++; return 42; }
+@end
+
+@implementation Forwarder
+- (id) forwardingTargetForSelector: (SEL)selector
+{
+  return [Target new];
+}
+@end
+
+int main (void)
+{
+  id forwarder = [Forwarder new];
+
+  if ([forwarder answer] != 42 || forwarded != 1)
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,43 @@
+/* Written for robjc after gcc/testsuite/objc/execute/many_args_method.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test methods taking more arguments than are passed in registers.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include "../TestsuiteObject.m"
+
+@interface Calculator : TestsuiteObject
+- (long) sum: (long)a : (long)b : (long)c : (long)d : (long)e
+            : (long)f : (long)g : (long)h : (long)i : (long)j;
+- (double) mix: (int)a : (double)b : (char)c : (float)d : (long)e
+              : (double)f : (double)g : (double)h : (double)i : (double)j;
+@end
+
+@implementation Calculator
+- (long) sum: (long)a : (long)b : (long)c : (long)d : (long)e
+            : (long)f : (long)g : (long)h : (long)i : (long)j
+{
+  return a + b + c + d + e + f + g + h + i + j;
+}
+- (double) mix: (int)a : (double)b : (char)c : (float)d : (long)e
+              : (double)f : (double)g : (double)h : (double)i : (double)j
+{
+  return a + b + c + d + e + f + g + h + i + j;
+}
+@end
+
+int main (void)
+{
+  Calculator *calculator = [Calculator new];
+
+  if ([calculator sum: 1 : 2 : 3 : 4 : 5 : 6 : 7 : 8 : 9 : 10] != 55)
+    abort ();
+
+  if ([calculator mix: 1 : 2 : 3 : 4 : 5 : 6 : 7 : 8 : 9 : 10] != 55)
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,62 @@
+/* Written for robjc after gcc/testsuite/objc/execute/nil_method-1.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test that messages to nil return zero of every return type.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include "../TestsuiteObject.m"
+
+struct Large
+{
+  long a;
+  long b;
+  long c;
+  long d;
+};
+
+@interface Values : TestsuiteObject
+- (char) charValue;
+- (int) intValue;
+- (long long) longLongValue;
+- (float) floatValue;
+- (double) doubleValue;
+- (long double) longDoubleValue;
+- (id) objectValue;
+- (struct Large) largeValue;
+@end
+
+@implementation Values
+- (char) charValue { return 1; }
+- (int) intValue { return 1; }
+- (long long) longLongValue { return 1; }
+- (float) floatValue { return 1; }
+- (double) doubleValue { return 1; }
+- (long double) longDoubleValue { return 1; }
+- (id) objectValue { return self; }
+- (struct Large) largeValue { struct Large l = { 1, 2, 3, 4 }; return l; }
+@end
+
+int main (void)
+{
+  Values *values = nil;
+  struct Large large;
+
+  if ([values charValue] != 0 || [values intValue] != 0 || [values longLongValue] != 0)
+    abort ();
+
+  if ([values floatValue] != 0 || [values doubleValue] != 0 || [values longDoubleValue] != 0)
+    abort ();
+
+  if ([values objectValue] != nil)
+    abort ();
+
+  large = [[Values new] largeValue];
+  large = [values largeValue];
+  if (large.a != 0 || large.b != 0 || large.c != 0 || large.d != 0)
+    abort ();
+
+  return 0;
+}
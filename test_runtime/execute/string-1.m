@@ -0,0 +1,37 @@
+/* Test that string literals are instances of NXConstantString, the default constant string
+   class, and respond to its methods.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include <objc/runtime.h>
+
+@interface NXConstantString
+{
+  Class isa;
+  char *c_string;
+  unsigned int len;
+}
+- (const char *) cString;
+- (unsigned int) length;
+@end
+
+int main (void)
+{
+  NXConstantString *string = @"constant string";
+
+  if (object_getClass (string) != objc_getClass ("NXConstantString"))
+    abort ();
+
+  if (strcmp ([string cString], "constant string") != 0)
+    abort ();
+
+  if ([string length] != strlen ("constant string"))
+    abort ();
+
+  if ([@"" length] != 0)
+    abort ();
+
+  return 0;
+}
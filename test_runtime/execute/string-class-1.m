@@ -0,0 +1,35 @@
+/* Test that string literals get the class chosen with -fconstant-string-class=.  */
+
+/* { dg-do run } */
+/* { dg-options "-fconstant-string-class=MyString" } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyString : TestsuiteObject
+{
+  char *string;
+  unsigned int length;
+}
+- (const char *) string;
+- (unsigned int) length;
+@end
+
+@implementation MyString
+- (const char *) string { return string; }
+- (unsigned int) length { return length; }
+@end
+
+int main (void)
+{
+  MyString *string = @"my string";
+
+  if (object_getClass (string) != objc_getClass ("MyString"))
+    abort ();
+
+  if (strcmp ([string string], "my string") != 0 || [string length] != 9)
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,60 @@
+/* Test the argument frame API on methods compiled with the types of va_method.m and
+   bycopy-1.m, against the frames the compiler describes.  */
+
+#include <stdlib.h>
+#include <string.h>
+#include <objc/runtime.h>
+
+@protocol MyProtocol
+- (bycopy id) bycopyMethod;
+@end
+
+@interface MathClass <MyProtocol>
+{
+  Class isa;
+}
++ (int) sum: (int)firstNumber, ...;
+@end
+
+@implementation MathClass
++ (int) sum: (int)firstNumber, ...
+{
+  return firstNumber;
+}
+- (bycopy id) bycopyMethod
+{
+  return self;
+}
++ initialize { return self; }
+@end
+
+int main (void)
+{
+  Class class = objc_getClass ("MathClass");
+  Method sum = class_getClassMethod (class, @selector (sum:));
+  Method bycopy = class_getInstanceMethod (class, @selector (bycopyMethod));
+  char frame[64];
+  union arglist arglist;
+  const char *type;
+
+  if (method_get_number_of_arguments (sum) != 3
+      || method_get_number_of_arguments (bycopy) != 2)
+    abort ();
+
+  if (method_get_sizeof_arguments (sum) < 2 * sizeof (void *) + sizeof (int)
+      || method_get_sizeof_arguments (bycopy) < 2 * sizeof (void *))
+    abort ();
+
+  arglist.arg_ptr = frame;
+  if (method_get_first_argument (sum, &arglist, &type) != frame || type[0] != '@')
+    abort ();
+  if (method_get_nth_argument (sum, &arglist, 2, &type) == NULL || type[0] != 'i')
+    abort ();
+  if (method_get_nth_argument (sum, &arglist, 3, &type) != NULL)
+    abort ();
+
+  if (method_get_number_of_arguments (NULL) != 0 || method_get_sizeof_arguments (NULL) != 0)
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,54 @@
+/* Test that messages sent to nil return zero whatever the return
+   type is, including floating point and struct return values.  */
+
+#include <stdlib.h>
+#include <objc/objc.h>
+
+struct Small
+{
+  double x;
+  double y;
+};
+
+struct Large
+{
+  double x;
+  double y;
+  double width;
+  double height;
+};
+
+@protocol Values
+- (int) intValue;
+- (float) floatValue;
+- (double) doubleValue;
+- (long double) longDoubleValue;
+- (struct Small) small;
+- (struct Large) large;
+@end
+
+int main (void)
+{
+  id receiver = nil;
+  struct Small small = { 1.0, 2.0 };
+  struct Large large = { 1.0, 2.0, 3.0, 4.0 };
+
+  if ([receiver intValue] != 0)
+    abort ();
+  if ([receiver floatValue] != 0.0f)
+    abort ();
+  if ([receiver doubleValue] != 0.0)
+    abort ();
+  if ([receiver longDoubleValue] != 0.0L)
+    abort ();
+
+  small = [receiver small];
+  if (small.x != 0.0 || small.y != 0.0)
+    abort ();
+
+  large = [receiver large];
+  if (large.x != 0.0 || large.y != 0.0 || large.width != 0.0 || large.height != 0.0)
+    abort ();
+
+  return 0;
+}
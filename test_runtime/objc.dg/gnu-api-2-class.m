@@ -0,0 +1,121 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-class.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'class', covering all functions starting with 'class'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+{
+  int value;
+}
+- (int) value;
++ (id) factory;
+@end
+
+@implementation MyRootClass
+- (int) value { return value; }
++ (id) factory { return [self new]; }
+@end
+
+@interface MySubClass : MyRootClass
+- (int) other;
+@end
+
+@implementation MySubClass
+- (int) other { return 1; }
+@end
+
+int main (void)
+{
+  /* class_getName, class_isMetaClass */
+  {
+    Class class = objc_getClass ("MySubClass");
+
+    if (strcmp (class_getName (class), "MySubClass") != 0)
+      abort ();
+
+    if (class_isMetaClass (class))
+      abort ();
+
+    if (! class_isMetaClass (object_getClass (class)))
+      abort ();
+
+    if (class_getName (Nil) != NULL)
+      abort ();
+  }
+
+  /* class_getSuperclass */
+  {
+    Class class = objc_getClass ("MySubClass");
+
+    if (class_getSuperclass (class) != objc_getClass ("MyRootClass"))
+      abort ();
+
+    if (class_getSuperclass (Nil) != Nil)
+      abort ();
+  }
+
+  /* class_getInstanceMethod, class_getClassMethod */
+  {
+    Class class = objc_getClass ("MySubClass");
+
+    if (class_getInstanceMethod (class, @selector (other)) == NULL)
+      abort ();
+
+    if (class_getInstanceMethod (class, @selector (value)) == NULL)
+      abort ();
+
+    if (class_getInstanceMethod (class, @selector (factory)) != NULL)
+      abort ();
+
+    if (class_getClassMethod (class, @selector (factory)) == NULL)
+      abort ();
+  }
+
+  /* class_createInstance */
+  {
+    MySubClass *object = class_createInstance (objc_getClass ("MySubClass"), 0);
+
+    if (object == nil || object_getClass (object) != objc_getClass ("MySubClass"))
+      abort ();
+
+    if ([object value] != 0 || [object other] != 1)
+      abort ();
+  }
+
+  /* class_getInstanceSize */
+  {
+    if (class_getInstanceSize (objc_getClass ("MyRootClass")) < sizeof (Class) + sizeof (int))
+      abort ();
+  }
+
+  /* class_respondsToSelector */
+  {
+    if (! class_respondsToSelector (objc_getClass ("MySubClass"), @selector (value)))
+      abort ();
+
+    if (class_respondsToSelector (objc_getClass ("MyRootClass"), @selector (other)))
+      abort ();
+  }
+
+  /* class_copyMethodList */
+  {
+    unsigned int count;
+    Method *list = class_copyMethodList (objc_getClass ("MySubClass"), &count);
+
+    if (count != 1 || list == NULL || list[1] != NULL)
+      abort ();
+
+    free (list);
+  }
+
+  return 0;
+}
@@ -0,0 +1,59 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-ivar.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'ivar', covering all functions starting with 'ivar'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+{
+  int a;
+  id b;
+}
+@end
+
+@implementation MyRootClass
+@end
+
+int main (void)
+{
+  /* class_getInstanceVariable, ivar_getName, ivar_getTypeEncoding, ivar_getOffset */
+  {
+    Ivar ivar = class_getInstanceVariable (objc_getClass ("MyRootClass"), "b");
+
+    if (ivar == NULL)
+      abort ();
+
+    if (strcmp (ivar_getName (ivar), "b") != 0)
+      abort ();
+
+    if (strcmp (ivar_getTypeEncoding (ivar), @encode (id)) != 0)
+      abort ();
+
+    if (ivar_getOffset (ivar) < sizeof (Class) + sizeof (int))
+      abort ();
+
+    if (class_getInstanceVariable (objc_getClass ("MyRootClass"), "c") != NULL)
+      abort ();
+  }
+
+  /* class_copyIvarList */
+  {
+    unsigned int count;
+    Ivar *list = class_copyIvarList (objc_getClass ("MyRootClass"), &count);
+
+    if (count != 2 || strcmp (ivar_getName (list[0]), "a") != 0)
+      abort ();
+
+    free (list);
+  }
+
+  return 0;
+}
@@ -0,0 +1,80 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-method.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'method', covering all functions starting with 'method'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+- (id) variable;
+- (int) sum: (int)a with: (int)b;
+@end
+
+@implementation MyRootClass
+- (id) variable { return self; }
+- (int) sum: (int)a with: (int)b { return a + b; }
+@end
+
+int main (void)
+{
+  /* method_getImplementation */
+  {
+    Method method = class_getInstanceMethod (objc_getClass ("MyRootClass"), @selector (variable));
+    MyRootClass *object = [MyRootClass new];
+    IMP imp = method_getImplementation (method);
+
+    if (imp == NULL || (*imp) (object, @selector (variable)) != object)
+      abort ();
+
+    if (method_getImplementation (NULL) != NULL)
+      abort ();
+  }
+
+  /* method_get_number_of_arguments, method_get_sizeof_arguments */
+  {
+    Method method = class_getInstanceMethod (objc_getClass ("MyRootClass"),
+                                             @selector (sum:with:));
+
+    if (method_get_number_of_arguments (method) != 4)
+      abort ();
+
+    if (method_get_sizeof_arguments (method) < 2 * sizeof (id) + 2 * sizeof (int))
+      abort ();
+  }
+
+  /* method_getName, method_getTypeEncoding */
+  {
+    Method method = class_getInstanceMethod (objc_getClass ("MyRootClass"), @selector (variable));
+
+    if (strcmp (sel_getName (method_getName (method)), "variable") != 0)
+      abort ();
+
+    if (method_getTypeEncoding (method)[0] != '@')
+      abort ();
+  }
+
+  /* method_setImplementation, method_exchangeImplementations */
+  {
+    Method variable = class_getInstanceMethod (objc_getClass ("MyRootClass"),
+                                               @selector (variable));
+    Method sum = class_getInstanceMethod (objc_getClass ("MyRootClass"),
+                                          @selector (sum:with:));
+    IMP original = method_getImplementation (variable);
+
+    if (method_setImplementation (variable, method_getImplementation (sum)) != original)
+      abort ();
+
+    method_exchangeImplementations (variable, sum);
+    if (method_getImplementation (variable) != original)
+      abort ();
+  }
+
+  return 0;
+}
@@ -0,0 +1,59 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-objc.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'objc', covering all functions starting with 'objc'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+@end
+
+@implementation MyRootClass
+@end
+
+int main (void)
+{
+  /* objc_getClass, objc_get_class */
+  {
+    if (objc_getClass ("MyRootClass") == Nil)
+      abort ();
+
+    if (objc_getClass ("RobjcNonExistingClass") != Nil)
+      abort ();
+
+    if (objc_get_class ("MyRootClass") != objc_getClass ("MyRootClass"))
+      abort ();
+  }
+
+  /* objc_lookUpClass */
+  {
+    if (objc_lookUpClass ("MyRootClass") != objc_getClass ("MyRootClass"))
+      abort ();
+
+    if (objc_lookUpClass ("RobjcNonExistingClass") != Nil)
+      abort ();
+  }
+
+  /* objc_getMetaClass */
+  {
+    if (objc_getMetaClass ("MyRootClass") != object_getClass (objc_getClass ("MyRootClass")))
+      abort ();
+  }
+
+  /* objc_getClassList */
+  {
+    int count = objc_getClassList (NULL, 0);
+
+    if (count < 2)
+      abort ();
+  }
+
+  return 0;
+}
@@ -0,0 +1,79 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-object.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'object', covering all functions starting with 'object'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+@end
+
+@implementation MyRootClass
+@end
+
+@interface MySubClass : MyRootClass
+{
+  id variable_ivar;
+}
+@end
+
+@implementation MySubClass
+@end
+
+int main (void)
+{
+  /* object_getClass */
+  {
+    MySubClass *object = [[MySubClass alloc] init];
+
+    if (object_getClass (object) != objc_getClass ("MySubClass"))
+      abort ();
+
+    if (object_getClass (nil) != Nil)
+      abort ();
+  }
+
+  /* object_dispose */
+  {
+    MySubClass *object = [[MySubClass alloc] init];
+
+    if (object_dispose (object) != nil)
+      abort ();
+  }
+
+  /* object_getClassName */
+  {
+    MySubClass *object = [[MySubClass alloc] init];
+
+    if (strcmp (object_getClassName (object), "MySubClass") != 0)
+      abort ();
+  }
+
+  /* object_setClass */
+  {
+    MySubClass *object = [[MySubClass alloc] init];
+
+    object_setClass (object, objc_getClass ("MyRootClass"));
+    if (object_getClass (object) != objc_getClass ("MyRootClass"))
+      abort ();
+  }
+
+  /* object_getIvar, object_setIvar */
+  {
+    MySubClass *object = [[MySubClass alloc] init];
+    Ivar ivar = class_getInstanceVariable (objc_getClass ("MySubClass"), "variable_ivar");
+
+    object_setIvar (object, ivar, object);
+    if (object_getIvar (object, ivar) != object)
+      abort ();
+  }
+
+  return 0;
+}
@@ -0,0 +1,85 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-property.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'property', covering all functions starting with 'property'.
+
+  GCC does not emit property metadata for the GNU runtime, so properties declared here are
+  only found with compilers emitting GNUstep ABI metadata.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+{
+  id property_ivar;
+}
+@property (retain, nonatomic) id property;
+@end
+
+@implementation MyRootClass
+@synthesize property = property_ivar;
+@end
+
+int main (void)
+{
+  /* class_getProperty, property_getName, property_getAttributes */
+  {
+    objc_property_t property = class_getProperty (objc_getClass ("MyRootClass"), "property");
+
+    if (property != NULL)
+      {
+        if (strcmp (property_getName (property), "property") != 0)
+          abort ();
+
+        if (strncmp (property_getAttributes (property), "T@", 2) != 0)
+          abort ();
+      }
+
+    if (class_getProperty (objc_getClass ("MyRootClass"), "none") != NULL)
+      abort ();
+
+    if (class_getProperty (Nil, "property") != NULL)
+      abort ();
+
+    if (property_getName (NULL) != NULL)
+      abort ();
+  }
+
+  /* class_copyPropertyList */
+  {
+    unsigned int count = 1;
+    objc_property_t *list = class_copyPropertyList (Nil, &count);
+
+    if (list != NULL || count != 0)
+      abort ();
+
+    list = class_copyPropertyList (objc_getClass ("MyRootClass"), &count);
+    if (count > 1 || (count == 1 && list[1] != NULL))
+      abort ();
+
+    free (list);
+  }
+
+  /* property_copyAttributeValue */
+  {
+    objc_property_t property = class_getProperty (objc_getClass ("MyRootClass"), "property");
+
+    if (property != NULL)
+      {
+        char *value = property_copyAttributeValue (property, "T");
+
+        if (value == NULL || strcmp (value, "@") != 0)
+          abort ();
+
+        free (value);
+      }
+  }
+
+  return 0;
+}
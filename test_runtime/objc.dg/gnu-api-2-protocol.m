@@ -0,0 +1,54 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-protocol.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'protocol', covering all functions starting with 'protocol'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@protocol MyProtocol
+- (id) variable;
+@end
+
+@protocol MySecondProtocol <MyProtocol>
+- (id) setVariable: (id)value;
+@end
+
+@interface MyRootClass : TestsuiteObject <MySecondProtocol>
+@end
+
+@implementation MyRootClass
+- (id) variable { return self; }
+- (id) setVariable: (id)value { return self; }
+@end
+
+int main (void)
+{
+  /* class_conformsToProtocol */
+  {
+    if (! class_conformsToProtocol (objc_getClass ("MyRootClass"), @protocol (MyProtocol)))
+      abort ();
+  }
+
+  /* protocol_getName, protocol_conformsToProtocol, protocol_isEqual */
+  {
+    Protocol *protocol = @protocol (MySecondProtocol);
+
+    if (strcmp (protocol_getName (protocol), "MySecondProtocol") != 0)
+      abort ();
+
+    if (! protocol_conformsToProtocol (protocol, @protocol (MyProtocol)))
+      abort ();
+
+    if (! protocol_isEqual (protocol, objc_getProtocol ("MySecondProtocol")))
+      abort ();
+  }
+
+  return 0;
+}
@@ -0,0 +1,48 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-resolve-method.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'resolve-method', covering +resolveInstanceMethod: and +resolveClassMethod:.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include "../TestsuiteObject.m"
+
+static int counter = 0;
+
+static void function (id receiver, SEL _cmd)
+{
+  counter++;
+}
+
+@interface MyRootClass : TestsuiteObject
++ (BOOL) resolveInstanceMethod: (SEL)selector;
+@end
+
+@interface MyRootClass (Dynamic)
+- (void) dynamicMethod;
+@end
+
+@implementation MyRootClass
++ (BOOL) resolveInstanceMethod: (SEL)selector
+{
+  if (sel_isEqual (selector, @selector (dynamicMethod)))
+    return class_addMethod (self, selector, (IMP)function, "v16@0:8");
+  return NO;
+}
+@end
+
+int main (void)
+{
+  MyRootClass *object = [MyRootClass new];
+
+  [object dynamicMethod];
+  [object dynamicMethod];
+  if (counter != 2)
+    abort ();
+
+  return 0;
+}
@@ -0,0 +1,67 @@
+/* Written for robjc after gcc/testsuite/objc.dg/gnu-api-2-sel.m of GCC.
+   It is not a copy of the upstream test, covers fewer cases, and is to be replaced by the
+   upstream file.  */
+
+/* Test the Modern GNU Objective-C Runtime API.
+
+  This is test 'sel', covering all functions starting with 'sel'.  */
+
+/* { dg-do run } */
+
+#include <stdlib.h>
+#include <string.h>
+#include "../TestsuiteObject.m"
+
+@interface MyRootClass : TestsuiteObject
+- (id) method;
+@end
+
+@implementation MyRootClass
+- (id) method { return self; }
+@end
+
+int main (void)
+{
+  /* sel_getName */
+  {
+    if (strcmp (sel_getName (@selector (method)), "method") != 0)
+      abort ();
+
+    if (strcmp (sel_getName (@selector (setValue:forKey:)), "setValue:forKey:") != 0)
+      abort ();
+  }
+
+  /* sel_getUid, sel_registerName */
+  {
+    SEL selector = sel_registerName ("method");
+
+    if (strcmp (sel_getName (selector), "method") != 0)
+      abort ();
+
+    if (sel_getUid ("method") != selector)
+      abort ();
+
+    if (sel_registerName ("robjc_unknown_selector") != sel_getUid ("robjc_unknown_selector"))
+      abort ();
+  }
+
+  /* sel_isEqual */
+  {
+    if (! sel_isEqual (@selector (method), sel_registerName ("method")))
+      abort ();
+
+    if (sel_isEqual (@selector (method), @selector (init)))
+      abort ();
+  }
+
+  /* sel_getTypeEncoding */
+  {
+    SEL selector = @selector (method);
+
+    if (sel_getTypeEncoding (selector) != NULL
+        && sel_getTypeEncoding (selector)[0] != '@')
+      abort ();
+  }
+
+  return 0;
+}
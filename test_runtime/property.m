@@ -0,0 +1,88 @@
+/* Test synthesized accessors of atomic object and struct properties,
+   which are compiled into calls to objc_getProperty, objc_setProperty
+   and objc_getPropertyStruct/objc_setPropertyStruct.  */
+
+#include <stdlib.h>
+#include "TestsuiteObject.m"
+
+static int retain_count = 0;
+static int release_count = 0;
+static int copy_count = 0;
+
+@interface Value : TestsuiteObject
+- (id) retain;
+- (void) release;
+- (id) autorelease;
+- (id) copy;
+@end
+
+@implementation Value
+- (id) retain
+{
+  retain_count++;
+  return self;
+}
+- (void) release
+{
+  release_count++;
+}
+- (id) autorelease
+{
+  return self;
+}
+- (id) copy
+{
+  copy_count++;
+  return [Value new];
+}
+@end
+
+struct Point
+{
+  long x;
+  long y;
+  long z;
+};
+
+@interface Holder : TestsuiteObject
+{
+  id retained;
+  id copied;
+  struct Point point;
+}
+@property (retain) id retained;
+@property (copy) id copied;
+@property struct Point point;
+@end
+
+@implementation Holder
+@synthesize retained;
+@synthesize copied;
+@synthesize point;
+@end
+
+int main (void)
+{
+  Holder *holder = [Holder new];
+  Value *value = [Value new];
+  struct Point p = { 1, 2, 3 };
+
+  [holder setRetained: value];
+  if ([holder retained] != value || retain_count < 1)
+    abort ();
+
+  [holder setRetained: nil];
+  if ([holder retained] != nil || release_count != 1)
+    abort ();
+
+  [holder setCopied: value];
+  if ([holder copied] == value || [holder copied] == nil || copy_count != 1)
+    abort ();
+
+  [holder setPoint: p];
+  p = [holder point];
+  if (p.x != 1 || p.y != 2 || p.z != 3)
+    abort ();
+
+  return 0;
+}
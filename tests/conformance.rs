@@ -0,0 +1,326 @@
+/*
+ * Compiles the Objective-C tests in test_runtime against the runtime and runs them.
+ *
+ * Each test is reported as PASS, FAIL, XFAIL (failing and listed in
+ * test_runtime/expected_failures), XPASS (passing but listed) or SKIP.  The test fails on FAIL
+ * and XPASS, so that the list of expected failures is kept up to date.  Without an Objective-C
+ * compiler the whole suite is skipped.
+ *
+ * Options for a test are given in the DejaGnu style of GCC's testsuite, e.g.
+ * { dg-options "-fobjc-exceptions" }.  Tests marked { dg-do compile } are only compiled.
+ * Setting ROBJC_CONFORMANCE to a substring selects the tests whose names contain it.
+ */
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Status {
+    Pass,
+    Fail,
+    ExpectedFail,
+    UnexpectedPass,
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Status::Pass => "PASS",
+            Status::Fail => "FAIL",
+            Status::ExpectedFail => "XFAIL",
+            Status::UnexpectedPass => "XPASS",
+            Status::Skip => "SKIP",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn compiler() -> String {
+    env::var("OBJC").unwrap_or_else(|_| "cc".to_string())
+}
+
+/*
+ * The cdylib is built next to the test executable, in target/<profile>/deps, and copied to
+ * target/<profile>.
+ */
+fn library_dir() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    let deps = exe.parent()?;
+    [deps, deps.parent()?]
+        .iter()
+        .find(|dir| dir.join("librobjc.so").exists())
+        .map(|dir| dir.to_path_buf())
+}
+
+fn has_objc_compiler(work_dir: &Path) -> bool {
+    let source = work_dir.join("probe.m");
+    fs::write(&source, "int main(void) { return 0; }\n").unwrap();
+    Command::new(compiler())
+        .args(["-x", "objective-c", "-c", "-o"])
+        .arg(work_dir.join("probe.o"))
+        .arg(&source)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/*
+ * Reads lines of the form "name.m  # reason".
+ */
+fn expected_failures(dir: &Path) -> BTreeMap<String, String> {
+    let list = fs::read_to_string(dir.join("expected_failures")).unwrap_or_default();
+    list.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, reason) = line.split_once('#').unwrap_or((line, ""));
+            (name.trim().to_string(), reason.trim().to_string())
+        })
+        .collect()
+}
+
+fn sources(dir: &Path) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            sources.extend(self::sources(&path));
+        } else if path.extension().is_some_and(|e| e == "m") {
+            sources.push(path);
+        }
+    }
+    sources
+}
+
+/*
+ * Tests are searched in subdirectories too, e.g. objc.dg for the tests from GCC's objc.dg.
+ * Sources included by other tests, e.g. TestsuiteObject.m, are not tests by themselves.
+ */
+fn tests(dir: &Path) -> Vec<PathBuf> {
+    let mut sources = sources(dir);
+    sources.sort();
+    let included: Vec<String> = sources
+        .iter()
+        .flat_map(|path| {
+            fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .filter_map(|line| {
+                    let line = line.trim().strip_prefix("#include")?.trim();
+                    let name = line.strip_prefix('"')?.split('"').next()?;
+                    Some(Path::new(name).file_name()?.to_string_lossy().into_owned())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    sources
+        .into_iter()
+        .filter(|path| !included.contains(&file_name(path)))
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
+
+/*
+ * Tests are named by their paths relative to test_runtime, e.g. objc.dg/gnu-api-2-sel.m.
+ */
+fn test_name(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap();
+    let components: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    components.join("/")
+}
+
+/*
+ * Collects the arguments of a DejaGnu directive, e.g. "-O2" of { dg-options "-O2" }.
+ */
+fn directive(source: &str, name: &str) -> Vec<String> {
+    let marker = format!("{{ {} ", name);
+    source
+        .lines()
+        .filter_map(|line| {
+            let start = line.find(&marker)? + marker.len();
+            let rest = &line[start..];
+            let rest = &rest[..rest.find('}')?];
+            Some(rest.trim().trim_matches('"').to_string())
+        })
+        .collect()
+}
+
+/*
+ * The outputs are read by threads, so that a child writing much does not block on the pipes.
+ */
+fn run_with_timeout(command: &mut Command) -> Result<Output, String> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let read = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = pipe.read_to_end(&mut buffer);
+            buffer
+        })
+    };
+    let stdout = read(Box::new(child.stdout.take().unwrap()));
+    let stderr = read(Box::new(child.stderr.take().unwrap()));
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if start.elapsed() > TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {:?}", TIMEOUT));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap(),
+        stderr: stderr.join().unwrap(),
+    })
+}
+
+fn describe(output: &Output) -> String {
+    format!(
+        "{}\n{}{}",
+        output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/*
+ * Returns whether the test passed, with the reason on failure.
+ */
+fn run_test(name: &str, source: &Path, work_dir: &Path, library_dir: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(source).unwrap();
+    let exe = work_dir.join(name.replace('/', "_")).with_extension("exe");
+    let compile_only = directive(&text, "dg-do").iter().any(|d| d == "compile");
+
+    let mut command = Command::new(compiler());
+    command
+        .args(["-x", "objective-c"])
+        .arg("-I")
+        .arg(root().join("include"))
+        .arg("-I")
+        .arg(source.parent().unwrap());
+    for options in directive(&text, "dg-options") {
+        command.args(options.split_whitespace());
+    }
+    if compile_only {
+        command.arg("-c");
+    }
+    command.arg("-o").arg(&exe).arg(source);
+    if !compile_only {
+        command
+            .arg("-L")
+            .arg(library_dir)
+            .arg("-lrobjc")
+            .arg(format!("-Wl,-rpath,{}", library_dir.display()));
+    }
+    let output = run_with_timeout(&mut command)?;
+    if !output.status.success() {
+        return Err(format!("compilation failed: {}", describe(&output)));
+    }
+    if compile_only {
+        return Ok(());
+    }
+
+    let output = run_with_timeout(Command::new(&exe).env("RUST_BACKTRACE", "1"))?;
+    if !output.status.success() {
+        return Err(format!("execution failed: {}", describe(&output)));
+    }
+    Ok(())
+}
+
+#[test]
+fn conformance() {
+    let dir = root().join("test_runtime");
+    let work_dir = env::temp_dir().join(format!("robjc-conformance-{}", std::process::id()));
+    fs::create_dir_all(&work_dir).unwrap();
+
+    if !has_objc_compiler(&work_dir) {
+        eprintln!("skipping conformance tests: no Objective-C compiler");
+        fs::remove_dir_all(&work_dir).unwrap();
+        return;
+    }
+    let library_dir = library_dir().expect("librobjc.so is not built");
+    let expected_failures = expected_failures(&dir);
+    let filter = env::var("ROBJC_CONFORMANCE").unwrap_or_default();
+
+    let mut results = Vec::new();
+    for source in tests(&dir) {
+        let name = test_name(&dir, &source);
+        let (status, detail) = if !name.contains(&filter) {
+            (Status::Skip, String::new())
+        } else {
+            match (
+                run_test(&name, &source, &work_dir, &library_dir),
+                expected_failures.contains_key(&name),
+            ) {
+                (Ok(()), false) => (Status::Pass, String::new()),
+                (Ok(()), true) => (Status::UnexpectedPass, String::new()),
+                (Err(e), false) => (Status::Fail, e),
+                (Err(_), true) => (Status::ExpectedFail, expected_failures[&name].clone()),
+            }
+        };
+        eprintln!("{}: {} {}", status, name, detail.trim_end());
+        results.push((name, status));
+    }
+    fs::remove_dir_all(&work_dir).unwrap();
+
+    for status in [
+        Status::Pass,
+        Status::Fail,
+        Status::ExpectedFail,
+        Status::UnexpectedPass,
+        Status::Skip,
+    ] {
+        let count = results.iter().filter(|(_, s)| *s == status).count();
+        eprintln!("# {}: {}", status, count);
+    }
+    let unexpected: Vec<_> = results
+        .iter()
+        .filter(|(_, s)| matches!(s, Status::Fail | Status::UnexpectedPass))
+        .collect();
+    assert!(
+        unexpected.is_empty(),
+        "unexpected results: {:?}",
+        unexpected
+    );
+}
+
+#[test]
+fn expected_failures_are_tests() {
+    let dir = root().join("test_runtime");
+    let tests: Vec<_> = tests(&dir)
+        .iter()
+        .map(|path| test_name(&dir, path))
+        .collect();
+    for name in expected_failures(&dir).keys() {
+        assert!(tests.contains(name), "{} is not a test", name);
+    }
+}
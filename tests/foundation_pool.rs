@@ -0,0 +1,88 @@
+/*
+ * Autorelease pools of a Foundation loaded while a pool of the runtime is pushed.  This is a
+ * test binary of its own, since the NSAutoreleasePool it declares cannot be removed and would
+ * take the objects autoreleased by any other test of the process.
+ */
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use robjc::api::{Class, Object, Sel};
+use robjc::declare::ClassDecl;
+
+/*
+ * Objects are passed as void pointers, as the exports are not part of the Rust API.
+ */
+extern "C" {
+    fn objc_autoreleasePoolPush() -> *mut c_void;
+    fn objc_autoreleasePoolPop(token: *mut c_void);
+    fn objc_autorelease(object: *mut c_void) -> *mut c_void;
+    fn objc_release(object: *mut c_void);
+    fn object_dispose(object: *mut c_void) -> *mut c_void;
+}
+
+static DEALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ADDED: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+extern "C" fn dealloc(this: *mut Object, _selector: Sel) {
+    DEALLOCATED.fetch_add(1, Ordering::SeqCst);
+    unsafe { object_dispose(this as *mut c_void) };
+}
+
+/*
+ * The NSAutoreleasePool of a Foundation, keeping the objects of all its pools together.
+ */
+extern "C" fn pool_new(_this: *mut Object, _selector: Sel) -> *mut Object {
+    let class = Class::named("NSAutoreleasePool").unwrap();
+    class.create_instance().as_ptr()
+}
+
+extern "C" fn pool_add_object(_this: *mut Object, _selector: Sel, object: *mut Object) {
+    ADDED.lock().unwrap().push(object as usize);
+}
+
+extern "C" fn pool_release(this: *mut Object, _selector: Sel) {
+    let added: Vec<usize> = ADDED.lock().unwrap().drain(..).collect();
+    for object in added {
+        unsafe { objc_release(object as *mut c_void) };
+    }
+    unsafe { object_dispose(this as *mut c_void) };
+}
+
+#[test]
+fn foundation_pool() {
+    let mut decl = ClassDecl::root("RobjcAutoreleased").unwrap();
+    decl.add_method(
+        Sel::register("dealloc").unwrap(),
+        dealloc as extern "C" fn(_, _),
+    );
+    let class = decl.register().unwrap();
+
+    unsafe {
+        let outer = objc_autoreleasePoolPush();
+        assert_eq!(outer as usize & 1, 1);
+        objc_autorelease(class.create_instance().as_ptr() as *mut c_void);
+
+        let mut decl = ClassDecl::root("NSAutoreleasePool").unwrap();
+        let new = Sel::register("new").unwrap();
+        decl.add_class_method(new, pool_new as extern "C" fn(_, _) -> _);
+        let add_object = Sel::register("addObject:").unwrap();
+        decl.add_class_method(add_object, pool_add_object as extern "C" fn(_, _, _));
+        let release = Sel::register("release").unwrap();
+        decl.add_method(release, pool_release as extern "C" fn(_, _));
+        decl.register().unwrap();
+
+        /* Pools pushed from then on are the Foundation's, and get the objects autoreleased. */
+        let inner = objc_autoreleasePoolPush();
+        assert_eq!(inner as usize & 1, 0);
+        objc_autorelease(class.create_instance().as_ptr() as *mut c_void);
+        assert_eq!(ADDED.lock().unwrap().len(), 1);
+        objc_autoreleasePoolPop(inner);
+        assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 1);
+
+        /* The pool of the runtime still releases the objects autoreleased before. */
+        objc_autoreleasePoolPop(outer);
+        assert_eq!(DEALLOCATED.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,310 @@
+/*
+ * Checks that the headers in include/objc declare exactly the functions and variables exported
+ * by the runtime, with the same return and parameter types for functions.  The Rust types are
+ * translated to C through RUST_TYPES, and the C types are compared without const and names.
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/*
+ * Exports which are called by code the compiler emits, but are not part of the API.
+ */
+const INTERNAL_EXPORTS: &[&str] = &["__objc_exec_class", "__objc_class_name_Protocol"];
+
+/*
+ * The C types of the Rust types used in the exports.
+ */
+const RUST_TYPES: &[(&str, &str)] = &[
+    ("()", "void"),
+    ("Bool", "BOOL"),
+    ("raw::c_int", "int"),
+    ("raw::c_uint", "unsigned"),
+    ("usize", "size_t"),
+    ("isize", "ptrdiff_t"),
+    ("Id", "id"),
+    ("Class", "Class"),
+    ("Sel", "SEL"),
+    ("Imp", "IMP"),
+    ("Method", "Method"),
+    ("Property", "objc_property_t"),
+    ("StrPtr", "char *"),
+    ("*mut raw::c_char", "char *"),
+    ("*const c_void", "void *"),
+    ("*mut c_void", "void *"),
+    ("*const raw::c_void", "void *"),
+    ("*mut raw::c_void", "void *"),
+    ("*mut Class", "Class *"),
+    ("*mut Property", "objc_property_t *"),
+    ("*mut PropertyAttribute", "objc_property_attribute_t *"),
+    ("Option<&mut Id>", "id *"),
+    ("Option<&mut StrPtr>", "char **"),
+    ("Option<&mut raw::c_uint>", "unsigned *"),
+    ("Option<&mut ArgList>", "arglist_t"),
+    ("&ObjcStructLayout", "struct objc_struct_layout *"),
+    ("&mut ObjcStructLayout", "struct objc_struct_layout *"),
+    ("Ptr<ObjcSuper>", "struct objc_super *"),
+    ("&mut ObjcModule", "struct objc_module *"),
+    ("Option<LoadObserverFunction>", "function pointer"),
+];
+
+/*
+ * Functions map to their return types followed by their parameter types, and variables to None.
+ */
+type Declarations = BTreeMap<String, Option<Vec<String>>>;
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            result.extend(files(&path, extension));
+        } else if path.extension().is_some_and(|e| e == extension) {
+            result.push(path);
+        }
+    }
+    result
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut result = String::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find("*/").expect("unterminated comment");
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/*
+ * Removes preprocessor directives, including the lines continued with backslashes, e.g. of
+ * macros defining functions.
+ */
+fn strip_directives(source: &str) -> String {
+    let mut lines = Vec::new();
+    let mut continued = false;
+    for line in source.lines() {
+        let directive = continued || line.trim_start().starts_with('#');
+        continued = directive && line.trim_end().ends_with('\\');
+        if !directive {
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+/*
+ * Splits a parenthesized list, e.g. "(a, b)" or "(void)", into the parameters.
+ */
+fn split_parameters(parameters: &str) -> Vec<&str> {
+    let inner = parameters.trim();
+    let inner = &inner[1..inner.len() - 1];
+    let inner = inner.trim().trim_end_matches(',').trim();
+    if inner.is_empty() || inner == "void" {
+        return Vec::new();
+    }
+    let mut depth = 0;
+    let mut start = 0;
+    let mut result = Vec::new();
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' | '>' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(inner[start..].trim());
+    result
+}
+
+/*
+ * Normalizes a C type, e.g. "const char *" to "char *" and "unsigned int" to "unsigned".
+ */
+fn normalize_c_type(t: &str) -> String {
+    if t.contains('(') {
+        return "function pointer".to_string();
+    }
+    let t = t.replace('*', " * ");
+    let words: Vec<&str> = t.split_whitespace().filter(|w| *w != "const").collect();
+    let mut result = words.join(" ").replace("* *", "**");
+    if result == "unsigned int" || result.starts_with("unsigned int ") {
+        result = result.replacen("unsigned int", "unsigned", 1);
+    }
+    result
+}
+
+/*
+ * The type of a C parameter without its name, e.g. "Class" of "Class class_".
+ */
+fn c_parameter_type(parameter: &str) -> String {
+    if parameter.contains('(') {
+        return normalize_c_type(parameter);
+    }
+    let name = identifier_at_end(parameter);
+    let head = parameter[..parameter.len() - name.len()].trim();
+    let is_type_only = head.is_empty()
+        || head == "const"
+        || head == "struct"
+        || head.ends_with(" struct")
+        || head == "unsigned";
+    normalize_c_type(if is_type_only { parameter } else { head })
+}
+
+fn rust_type(t: &str) -> String {
+    let t = t.split_whitespace().collect::<Vec<_>>().join(" ");
+    let t = t.replace("&'static ", "&");
+    RUST_TYPES
+        .iter()
+        .find(|(rust, _)| *rust == t)
+        .map(|(_, c)| c.to_string())
+        .unwrap_or_else(|| panic!("{} is not in RUST_TYPES", t))
+}
+
+/*
+ * Splits "name(parameters)" at the parenthesis closing the first one.
+ */
+fn split_call(s: &str) -> Option<(&str, &str)> {
+    let open = s.find('(')?;
+    let mut depth = 0;
+    for (i, c) in s[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[..open], &s[open..open + i + 1]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn identifier_at_end(s: &str) -> &str {
+    let s = s.trim_end();
+    let start = s
+        .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    &s[start..]
+}
+
+fn header_declarations() -> Declarations {
+    let mut declarations = BTreeMap::new();
+    for path in files(&root().join("include"), "h") {
+        let source = strip_comments(&fs::read_to_string(&path).unwrap());
+        /* The C++ linkage block would join the first declaration in it. */
+        let source = strip_directives(&source).replace("extern \"C\" {", "");
+        for statement in source.split(';') {
+            let statement = statement.trim();
+            let is_variable = statement.starts_with("extern ");
+            if statement.contains(['{', '}'])
+                || (statement.contains('[') && !is_variable)
+                || statement.starts_with("typedef")
+                || statement.starts_with('@')
+            {
+                continue;
+            }
+            if let Some(variable) = statement.strip_prefix("extern ") {
+                /* A function pointer is declared as "type (*name)(parameters)", and an array as
+                 * "type name[size]". */
+                let variable = variable.split('[').next().unwrap();
+                let name = match variable.find("(*") {
+                    Some(i) => &variable[i + 2..variable[i..].find(')').unwrap() + i],
+                    None => identifier_at_end(variable),
+                };
+                declarations.insert(name.trim().to_string(), None);
+            } else if let Some((head, parameters)) = split_call(statement) {
+                let name = identifier_at_end(head);
+                let mut types = vec![normalize_c_type(&head[..head.len() - name.len()])];
+                types.extend(
+                    split_parameters(parameters)
+                        .into_iter()
+                        .map(c_parameter_type),
+                );
+                declarations.insert(name.to_string(), Some(types));
+            }
+        }
+    }
+    declarations
+}
+
+fn exports() -> Declarations {
+    let mut exports = BTreeMap::new();
+    for path in files(&root().join("src"), "rs") {
+        let source = fs::read_to_string(&path).unwrap();
+        for (i, _) in source.match_indices("#[no_mangle]") {
+            let item = source[i..]
+                .lines()
+                .map(|line| line.trim())
+                .find(|line| !line.starts_with("#["))
+                .unwrap();
+            if let Some(variable) = item.strip_prefix("pub static ") {
+                let variable = variable.trim_start_matches("mut ");
+                let name = &variable[..variable.find(':').unwrap()];
+                exports.insert(name.trim().to_string(), None);
+                continue;
+            }
+            let item = &source[i..];
+            let item = &item[..item.find('{').unwrap()];
+            let item = &item[item.find(" fn ").expect("only functions are exported") + 4..];
+            let (name, parameters) = split_call(item).unwrap();
+            let return_type = item[name.len() + parameters.len()..]
+                .trim()
+                .trim_start_matches("->")
+                .trim();
+            let mut types = vec![rust_type(if return_type.is_empty() {
+                "()"
+            } else {
+                return_type
+            })];
+            types.extend(
+                split_parameters(parameters)
+                    .into_iter()
+                    .map(|parameter| rust_type(&parameter[parameter.find(':').unwrap() + 1..])),
+            );
+            exports.insert(name.trim().to_string(), Some(types));
+        }
+    }
+    for name in INTERNAL_EXPORTS {
+        assert!(exports.remove(*name).is_some(), "{} is not exported", name);
+    }
+    exports
+}
+
+#[test]
+fn headers_match_exports() {
+    let declarations = header_declarations();
+    let exports = exports();
+    assert!(!exports.is_empty());
+
+    for (name, types) in &exports {
+        match declarations.get(name) {
+            Some(declared) => assert_eq!(
+                declared, types,
+                "{} is exported as {:?}, but declared as {:?} (return and parameter types, or \
+                 None for variables)",
+                name, types, declared
+            ),
+            None => panic!("{} is exported, but not declared in the headers", name),
+        }
+    }
+    for name in declarations.keys() {
+        assert!(
+            exports.contains_key(name),
+            "{} is declared in the headers, but not exported",
+            name
+        );
+    }
+}